@@ -1,13 +1,119 @@
-use crate::enums::{AudioFormat, FunctionType, FrameRateMode, OutputFormat};
+use crate::batch::{BatchJob, SettingsJob};
+use crate::benchmark::{AbResult, BenchmarkResult};
+use crate::chapters::{self, ChapterPlan};
+use crate::crop::{Crop, CropTally};
+use crate::dialogs::PendingDialog;
+use crate::enums::{AacEncoder, AudioContainer, AudioFormat, CfrMethod, ChannelLayout, ChapterMode, ColorRange, CoverArt, CreationTime, FunctionType, FrameRateMode, GifDither, OutputFormat, QualityMode, Rotation, SizeLimit, SubtitleMode, TrimUnit, VideoEncoder};
+use eframe::egui;
 use parking_lot::RwLock;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use crate::ffmpeg_utils;
+use std::sync::atomic::AtomicBool;
+use crate::ffmpeg_utils::{self, ParsedCommand, SizeBreakdown};
+use crate::presets::{self, EncodeSettings, Preset};
+use crate::probe::{Chapter, MediaInfo};
+use crate::settings::Settings;
+use crate::subtitles::{self, Sidecar};
+use crate::frame_preview::FramePreview;
+use crate::gif::{self, GifPlan};
+use crate::separate::{self, SeparatePlan};
+use crate::two_pass::{self, TwoPassPlan};
+use crate::taskbar::TaskbarProgress;
+use crate::hotkeys::GlobalHotkeys;
+
+/// Subfolder (next to the input) that test-run outputs are written to
+pub const TEST_RUN_DIR: &str = "_tests";
+
+/// Lowpass cutoffs libopus accepts for -cutoff, in Hz
+pub const OPUS_CUTOFFS: [u32; 5] = [4000, 6000, 8000, 12000, 20000];
+
+/// Encode setting changes that can be undone
+const UNDO_LIMIT: usize = 20;
+
+/// Output FFmpeg writes to when piping to stdout
+pub const PIPE_OUTPUT: &str = "pipe:1";
+
+/// Keyframe interval for seek-friendly encodes, in frames
+pub const SEEK_FRIENDLY_GOP: u32 = 30;
+
+/// Length of the quick preview encode, in seconds
+pub const QUICK_PREVIEW_SECS: u32 = 5;
+
+/// Range of video bitrates offered in bitrate mode, in kbps
+pub const MIN_VIDEO_KBPS: u32 = 100;
+pub const MAX_VIDEO_KBPS: u32 = 50000;
+
+/// Source and output frames as raw RGBA, sized `ffmpeg_utils::PREVIEW_SIZE`
+pub type FramePair = (Vec<u8>, Vec<u8>);
+
+/// The last successful encode, for comparing it with its source
+#[derive(Debug, Clone, PartialEq)]
+pub struct LastEncode {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub source_start: f64,              // Where the output begins in the input, in seconds
+    pub length: Option<f64>,            // Of the output, in seconds, when known
+    pub finished: std::time::Instant,   // Tells apart encodes that wrote the same file
+}
+
+/// What's shown instead of a percentage while a job of unknown length runs
+#[derive(Debug, Clone, Copy)]
+pub struct LiveProgress {
+    pub started: std::time::Instant,
+    pub frames: u64,                    // Frames FFmpeg has written so far
+    pub time: f64,                      // Output time written so far, in seconds
+}
+
+/// Result of probing an input on the background thread
+pub struct ProbeOutcome {
+    pub path: String,                   // Input path that was probed
+    pub info: anyhow::Result<MediaInfo>,
+    pub sidecars: Vec<Sidecar>,         // Subtitle files found next to it
+}
+
+/// An exact frame count of an input running on a background thread
+pub struct FrameCountJob {
+    pub path: String,                   // Input being counted
+    pub progress: Arc<RwLock<f32>>,     // 0-1 through the file
+    pub cancel: Arc<AtomicBool>,
+    pub result: Arc<RwLock<Option<anyhow::Result<Option<u64>>>>>, // Set when done; Ok(None) if cancelled
+}
+
+/// A cropdetect pass over an input running on a background thread
+pub struct CropDetectJob {
+    pub path: String,                   // Input being scanned
+    pub progress: Arc<RwLock<f32>>,     // 0-1 through the file
+    pub cancel: Arc<AtomicBool>,
+    pub tally: Arc<RwLock<CropTally>>,  // Suggestions so far, shown as they come in
+    pub result: Arc<RwLock<Option<anyhow::Result<bool>>>>, // Set when done; Ok(false) if cancelled
+}
+
+/// Language and title written to one output audio track; empty fields are left alone
+#[derive(Debug, Clone, Default)]
+pub struct AudioTrackTags {
+    pub language: String,               // ISO 639-2 code, e.g. "eng"
+    pub title: String,                  // Shown by players, e.g. "English Commentary"
+    pub source: usize,                  // Index among the input's audio streams
+    pub default: bool,                  // Carries the default disposition in the output
+    pub source_default: bool,           // Whether the input's stream was flagged default
+}
 
 pub struct MyApp {
     // File paths
     pub input_path: String,
     pub output_path: String,
+    pub audio_input_path: String,       // Second input: the soundtrack for ImageToVideo, or replacement audio
+    pub replace_audio: bool,            // CompressVideo/ConvertToMp4: take the audio from audio_input_path instead
+    pub mix_audio: bool,                // CompressVideo: mix that audio under the input's own rather than replacing it
+    pub mix_balance: f32,               // 0 is only the input's audio, 1 only the added track, 0.5 both at full volume
+    pub shortest: bool,                 // Multi-input functions: stop at the end of the shortest input (-shortest)
+    pub spectrogram_size: [u32; 2],     // Spectrogram image width and height
+    pub loop_crossfade: f64,            // LoopClip: seconds of the end blended into the start
+    pub gif_fps: f32,                   // Gif: frame rate of the animation
+    pub gif_width: u32,                 // Gif: width in pixels, 0 keeps the source width
+    pub gif_palette: bool,              // Gif: generate a palette for the clip in a first pass
+    pub gif_max_colors: u32,            // Gif: colours in the generated palette
+    pub gif_dither: GifDither,          // Gif: how frames are mapped onto the generated palette
     
     // Operation settings
     pub selected_function: FunctionType,
@@ -15,27 +121,163 @@ pub struct MyApp {
     pub audio_format: AudioFormat,
     
     // Video settings
+    pub video_encoder: VideoEncoder,
     pub crf: u8,
+    pub qp: u8,                         // Quantizer for CQP mode
     pub video_bitrate: u32,
+    pub strict_cbr: bool,               // Bitrate mode: hold the rate constant instead of averaging it
+    pub two_pass: bool,                 // Bitrate mode: analyse the video in a first pass, for a more accurate size
+    pub size_limit: Option<SizeLimit>,  // Compress Video: keep the bitrate where the output fits under this
+    pub seek_friendly: bool,            // Short fixed GOP so editors can scrub the output quickly
+    pub extra_options: String,          // Extra output options placed just before the output file
+    pub filter_complex: String,         // Compress Video: a filter graph (-filter_complex) replacing the video filters
+    pub filter_maps: String,            // Outputs to map with the filter graph, e.g. "[v] 0:a"; empty lets FFmpeg pick
+    pub roi_enabled: bool,              // Encode roi_rect of the probed input at higher quality
+    pub roi_rect: [u32; 4],             // Region of interest as x, y, width, height in source pixels
+    pub roi_boost: f32,                 // How much the region is favoured: addroi's qoffset, negated (0.1-1.0)
+    pub crop: Option<Crop>,             // Black bars to cut from the probed input
+    pub crop_job: Option<CropDetectJob>,
+    pub crop_tally: CropTally,          // Crops detected in the probed input and how often each came up
+    pub cbr_buffer_secs: f32,           // Strict CBR: VBV buffer size as seconds of bitrate
     pub framerate_mode: FrameRateMode,
-    pub use_crf: bool,                  // Whether to use CRF or bitrate for video quality
-    pub encoding_preset: String,         // FFmpeg preset (ultrafast, medium, veryslow, etc.)
+    pub cfr_method: CfrMethod,          // CFR: how frames are dropped or duplicated to reach the rate
+    pub quality_mode: QualityMode,      // CRF, CQP or bitrate, depending on what the encoder supports
+    pub encoding_preset: String,         // Encoder preset (ultrafast, medium, p4, etc.)
     pub frame_rate: f32,                // Frame rate for CFR mode (frames per second)
     pub original_fps: f32,              // Original video's frame rate
+    pub media_info: Option<MediaInfo>,  // ffprobe results for the current input
+    pub probing: bool,                  // A probe of the current input is in flight
+    pub probe_results: Arc<RwLock<Vec<ProbeOutcome>>>, // Finished probes, applied on the next frame
+    pub frame_count_job: Option<FrameCountJob>,
+    pub counted_frames: Option<u64>,    // Exact frame count of the probed input, once counted
+    pub probed_path: String,            // Input path media_info was gathered for
+    pub remove_audio: bool,             // CompressVideo: drop all audio streams (-an)
+    pub remove_video: bool,             // CompressVideo: drop video, producing audio-only output (-vn)
+    pub rotation: Rotation,
+    pub scale_height: Option<u32>,      // Output height (width follows the aspect ratio), None keeps the source size
+    pub ladder_heights: Vec<u32>,       // Resolution ladder: one output per height, empty when off
+    pub trim_enabled: bool,
+    pub trim_unit: TrimUnit,
+    pub trim_start: String,             // In trim_unit; empty starts at the beginning
+    pub trim_end: String,               // In trim_unit; empty runs to the end
+    pub frame_preview: Option<FramePreview>, // Frame at the trim point being stepped through
+    pub keyframes_at_cuts: bool,        // Re-encodes: force keyframes where the trimmed clip starts and ends
+    pub copy_timestamps: bool,          // ConvertToMp4: keep the source timestamps (-copyts) to avoid A/V drift
+    pub reset_timestamps: bool,         // ConvertToMp4/CompressVideo: shift the output's timeline to start at zero
+    pub creation_time: CreationTime,
+    pub custom_creation_time: String,   // ISO 8601, used when creation_time is Custom
+    pub subtitle_mode: SubtitleMode,
+    pub subtitle_path: String,          // External subtitle file to mux or burn
+    pub subtitle_charset: String,       // Text encoding of the subtitle file when burning in
+    pub subtitle_language: String,      // Language tag for a muxed subtitle file; empty takes it from the file name
+    pub subtitle_default: bool,         // Flag the muxed subtitle track as default, so players show it
+    pub subtitle_forced: bool,          // Flag the muxed subtitle track as forced
+    pub sidecar_subtitles: Vec<Sidecar>, // Subtitle files found next to the probed input
+    pub chapter_mode: ChapterMode,
+    pub chapter_interval_min: u32,      // Minutes between chapters in interval mode
+    pub scene_threshold: f32,           // Scene score (0-1) that starts a new chapter in scene mode
+    pub smart_copy: bool,               // CompressVideo: copy the video stream when re-encoding wouldn't change it
+    pub hdr10_passthrough: bool,        // x265: encode HDR10 sources as 10-bit with their HDR metadata
+    pub color_range: ColorRange,        // Range to write the video in; Auto keeps the source's
+    pub separate_streams: bool,         // Compress Video: encode audio and video to their own files, then mux
     
     // Audio settings
     pub audio_bitrate: u32,
-    pub audio_quality: u8,              // Quality level (0-9 for MP3, 0-10 for OPUS, etc.)
+    pub audio_quality: u8,              // VBR quality 0-10, higher is better; compression level for Opus and FLAC
     pub use_audio_quality: bool,        // Whether to use quality or bitrate for audio
+    pub aac_encoder: AacEncoder,
+    pub fdk_vbr: u8,                    // libfdk_aac VBR mode (1-5), 0 = use bitrate instead
+    pub audio_cutoff: u32,              // AAC/Opus lowpass in Hz, 0 = let the encoder choose
+    pub channel_layout: ChannelLayout,
+    pub extra_audio_formats: Vec<AudioFormat>, // ExtractAudio: also write these formats, one file each
+    pub silence_trim: bool,             // Audio-only outputs: cut silence from the start and end (silenceremove)
+    pub silence_internal: bool,         // Also cut silent gaps within the audio
+    pub silence_threshold_db: f32,      // Quieter than this counts as silence
+    pub silence_min_secs: f32,          // Shortest internal gap that is cut
+    pub audio_container: AudioContainer, // ExtractAudio: container, independent of the codec
+    pub extract_track: Option<usize>,   // ExtractAudio: index into audio_tags, None for all
+    pub default_subtitle: Option<usize>, // Subtitle stream to flag as default, None to keep the source's flags
+    pub remux_drop_audio: bool,         // ConvertToMp4: leave out every audio stream (-an)
+    pub remux_drop_subtitles: bool,     // ConvertToMp4: leave out the source's subtitle streams
+    pub remux_excluded: Vec<u32>,       // ConvertToMp4: stream indices of the probed input to leave out
+    pub split_by_chapters: bool,        // ExtractAudio: one output file per chapter of the input
+    pub split_points: Vec<(f64, Option<String>)>, // Track starts and titles loaded from a cue sheet or timestamp list
+    pub split_points_file: String,      // Where split_points were loaded from, for display
+    pub batch_collisions: Vec<PathBuf>, // Outputs more than one queued input would write, found when starting
+    pub rename_collisions: bool,        // Tell colliding outputs apart on the next start instead of refusing
+    pub cover_art: CoverArt,            // ExtractAudio: picture embedded as album art
+    pub cover_path: String,             // Image used when cover_art is File
+    pub audio_tags: Vec<AudioTrackTags>, // One per audio stream of the probed input, in output order
     
     // App state
     pub last_command: String,
+    pub last_command_wrapped: String,   // last_command one option per line, for display only
+    pub command_import: String,         // A pasted FFmpeg command to load the settings from
+    pub clipboard_failed: bool,         // Copy Command couldn't reach the clipboard: show the command selectable instead
+    pub select_command: bool,           // Select the whole command on the next frame, for copying by hand
+    pub output_ext_note: Option<String>, // Why the output extension was changed, or a warning about it
+    pub bpp_note: Option<(String, bool)>, // Bits per pixel of a bitrate encode, and whether it's too low
     pub output_log: Arc<RwLock<String>>,
     pub progress: Arc<RwLock<f32>>,
+    pub eta: Arc<RwLock<Option<std::time::Duration>>>, // Estimated time left, from the unsmoothed progress
     pub running: Arc<RwLock<bool>>,
+    pub stopping: Arc<RwLock<bool>>,    // Stop is still waiting for the last process to exit
     pub child: Arc<Mutex<Option<std::process::Child>>>,
-    pub duration: f32,
+    pub preview_child: Arc<Mutex<Option<std::process::Child>>>, // Quick preview encode, alongside the main one
+    pub file_dialog: Option<PendingDialog>, // Native file dialog currently open, if any
+    pub taskbar: TaskbarProgress,       // Progress shown on the taskbar, dock or window title while a job runs
+    pub hotkeys: Option<GlobalHotkeys>, // Created the first time global hotkeys are turned on
+    pub hotkeys_unavailable: bool,      // The platform listener failed to start; don't retry every frame
+    pub duration: Option<f64>,          // Input length from the probe; None when it couldn't be determined
+    pub live_progress: Arc<RwLock<Option<LiveProgress>>>, // Set while a job of unknown length runs
     pub auto_scroll: bool,
+    pub test_run: bool,                 // Overwrite a single preview file instead of numbering outputs
+    pub available_encoders: Vec<String>, // Encoders reported by the installed FFmpeg
+    pub available_filters: Vec<String>, // Filters reported by the installed FFmpeg
+    pub display_rotation: bool,         // FFmpeg takes -display_rotation (6.0+); older builds read the rotate tag
+    pub pipe_output: bool,              // Write to stdout instead of a file
+    pub pipe_command: String,           // Shell command fed the piped output; empty to just count bytes
+    pub score_after_encode: bool,       // Measure VMAF (or SSIM/PSNR) against the source after encoding
+    pub settings: Settings,             // Persisted preferences
+    pub active_preset: Option<Preset>,  // Last preset loaded or saved, as it was then
+    pub new_preset_name: String,
+    pub undo_stack: Vec<EncodeSettings>, // Earlier encode settings, most recent last
+    pub undo_baseline: Option<EncodeSettings>, // Settings after the last recorded change
+    pub auto_applied: Option<EncodeSettings>, // What Auto last picked, while it's in charge
+    pub saved_settings: Settings,       // Last state written to disk, to detect changes
+    pub show_settings: bool,
+    pub encode_started: Option<std::time::Instant>, // When the current run started; cleared when it's stopped
+    pub applied_ui_scale: f32,          // Zoom last handed to egui, to notice Ctrl +/- zooming
+    
+    // Before/after comparison of the last encode
+    pub last_encode: Arc<RwLock<Option<LastEncode>>>,
+    pub compared_encode: Option<LastEncode>, // The encode the compare textures were taken from
+    pub output_sizes: Arc<RwLock<Option<(PathBuf, SizeBreakdown)>>>, // Where the bytes of the last successful output went
+    pub show_compare: bool,
+    pub compare_time: f64,                                      // Timestamp to compare at, in seconds
+    pub compare_frames: Arc<RwLock<Option<FramePair>>>,        // Freshly extracted frames
+    pub compare_textures: Option<(egui::TextureHandle, egui::TextureHandle)>,
+    pub compare_busy: Arc<RwLock<bool>>,
+    pub show_preset_diff: bool,
+    pub preset_diff: [Option<String>; 2], // Presets compared side by side; None is the current settings
+    pub preset_diff_all: bool,          // List every field, not just the ones that differ
+    pub show_benchmark: bool,
+    pub benchmark_seconds: u32,         // Length of source each encoder is benchmarked on
+    pub benchmark_results: Arc<RwLock<Vec<BenchmarkResult>>>,
+    pub show_ab: bool,
+    pub ab_values: [u8; 2],             // The two CRF (or QP) values compared
+    pub ab_start: f64,                  // Start of the compared segment, in seconds
+    pub ab_seconds: u32,
+    pub ab_metrics: bool,               // Also measure PSNR and SSIM against the source
+    pub ab_results: Arc<RwLock<Vec<AbResult>>>,
+    pub temp_paths: Vec<PathBuf>,       // Temp files jobs may leave behind, deleted by Cleanup
+    pub partial_output: Option<PathBuf>, // File a single encode is writing, for Stop to keep or delete
+    
+    // Batch processing
+    pub batch_queue: Vec<PathBuf>,                  // Files queued for the next batch run
+    pub settings_jobs: Vec<SettingsJob>,            // Input, output and settings snapshots for the job list
+    pub batch_job: Arc<RwLock<Option<BatchJob>>>,  // The batch currently being processed
+    pub pending_resume: Option<BatchJob>,          // Unfinished batch found on startup
 }
 
 impl Default for MyApp {
@@ -43,122 +285,2025 @@ impl Default for MyApp {
         Self {
             input_path: String::new(),
             output_path: String::new(),
+            audio_input_path: String::new(),
+            replace_audio: false,
+            mix_audio: false,
+            mix_balance: 0.25,
+            shortest: true,
+            spectrogram_size: [1280, 480],
+            loop_crossfade: 1.0,
+            gif_fps: 15.0,
+            gif_width: 480,
+            gif_palette: true,
+            gif_max_colors: 256,
+            gif_dither: GifDither::default(),
             selected_function: FunctionType::ExtractAudio,
             output_format: OutputFormat::Mp4,
             audio_format: AudioFormat::MP3,
+            video_encoder: VideoEncoder::X264,
             crf: 28,
+            qp: 23,
             video_bitrate: 2000, // 2000 kbps
+            strict_cbr: false,
+            two_pass: false,
+            size_limit: None,
+            seek_friendly: false,
+            extra_options: String::new(),
+            filter_complex: String::new(),
+            filter_maps: String::new(),
+            roi_enabled: false,
+            roi_rect: [0, 0, 0, 0],
+            roi_boost: 0.4,
+            crop: None,
+            crop_job: None,
+            crop_tally: CropTally::default(),
+            cbr_buffer_secs: 1.0,
             framerate_mode: FrameRateMode::CFR,
-            use_crf: true,      // Default to CRF mode for video
+            cfr_method: CfrMethod::OutputRate,
+            quality_mode: QualityMode::Crf, // Default to CRF mode for video
             encoding_preset: "medium".to_string(), // Default encoding preset
             frame_rate: 30.0,    // Default frame rate (fps)
             original_fps: 30.0,  // Will be updated when probing input file
+            media_info: None,
+            probing: false,
+            probe_results: Arc::new(RwLock::new(Vec::new())),
+            frame_count_job: None,
+            counted_frames: None,
+            probed_path: String::new(),
+            remove_audio: false,
+            remove_video: false,
+            rotation: Rotation::None,
+            scale_height: None,
+            ladder_heights: Vec::new(),
+            trim_enabled: false,
+            trim_unit: TrimUnit::Timecode,
+            trim_start: String::new(),
+            trim_end: String::new(),
+            frame_preview: None,
+            keyframes_at_cuts: false,
+            copy_timestamps: false,
+            reset_timestamps: false,
+            creation_time: CreationTime::Source,
+            custom_creation_time: String::new(),
+            subtitle_mode: SubtitleMode::Off,
+            subtitle_charset: "UTF-8".to_string(),
+            subtitle_language: String::new(),
+            subtitle_default: false,
+            subtitle_forced: false,
+            subtitle_path: String::new(),
+            sidecar_subtitles: Vec::new(),
+            chapter_mode: ChapterMode::Off,
+            chapter_interval_min: 10,
+            scene_threshold: 0.4,
+            smart_copy: true,
+            hdr10_passthrough: true,
+            color_range: ColorRange::default(),
+            separate_streams: false,
             audio_bitrate: 192, // 192 kbps
-            audio_quality: 4,   // Middle quality for codecs that use it (like OPUS)
+            audio_quality: 6,   // Comes out as LAME's -q:a 4 once flipped for MP3
             use_audio_quality: true, // Default to VBR for audio
+            aac_encoder: AacEncoder::Native,
+            fdk_vbr: 0,
+            audio_cutoff: 0,
+            channel_layout: ChannelLayout::Source,
+            extra_audio_formats: Vec::new(),
+            silence_trim: false,
+            silence_internal: false,
+            silence_threshold_db: -50.0,
+            silence_min_secs: 1.0,
+            audio_container: AudioContainer::Native,
+            extract_track: None,
+            default_subtitle: None,
+            remux_drop_audio: false,
+            remux_drop_subtitles: false,
+            remux_excluded: Vec::new(),
+            split_by_chapters: false,
+            split_points: Vec::new(),
+            split_points_file: String::new(),
+            batch_collisions: Vec::new(),
+            rename_collisions: false,
+            cover_art: CoverArt::None,
+            cover_path: String::new(),
+            audio_tags: Vec::new(),
             last_command: String::new(),
+            last_command_wrapped: String::new(),
+            command_import: String::new(),
+            clipboard_failed: false,
+            select_command: false,
+            output_ext_note: None,
+            bpp_note: None,
             output_log: Arc::new(RwLock::new(String::new())),
             progress: Arc::new(RwLock::new(0.0)),
+            eta: Arc::new(RwLock::new(None)),
             running: Arc::new(RwLock::new(false)),
+            stopping: Arc::new(RwLock::new(false)),
             child: Arc::new(Mutex::new(None)),
-            duration: 1.0,
+            preview_child: Arc::new(Mutex::new(None)),
+            file_dialog: None,
+            taskbar: TaskbarProgress::default(),
+            hotkeys: None,
+            hotkeys_unavailable: false,
+            duration: None,
+            live_progress: Arc::new(RwLock::new(None)),
             auto_scroll: true,
+            test_run: false,
+            available_encoders: Vec::new(),
+            display_rotation: false,
+            available_filters: Vec::new(),
+            pipe_output: false,
+            pipe_command: String::new(),
+            score_after_encode: false,
+            settings: Settings::default(),
+            active_preset: None,
+            new_preset_name: String::new(),
+            undo_stack: Vec::new(),
+            undo_baseline: None,
+            auto_applied: None,
+            saved_settings: Settings::default(),
+            show_settings: false,
+            encode_started: None,
+            applied_ui_scale: 1.0,
+            last_encode: Arc::new(RwLock::new(None)),
+            compared_encode: None,
+            output_sizes: Arc::new(RwLock::new(None)),
+            show_compare: false,
+            compare_time: 0.0,
+            compare_frames: Arc::new(RwLock::new(None)),
+            compare_textures: None,
+            compare_busy: Arc::new(RwLock::new(false)),
+            show_preset_diff: false,
+            preset_diff: [None, None],
+            preset_diff_all: false,
+            show_benchmark: false,
+            benchmark_seconds: 10,
+            benchmark_results: Arc::new(RwLock::new(Vec::new())),
+            show_ab: false,
+            ab_values: [20, 26],
+            ab_start: 0.0,
+            ab_seconds: 10,
+            ab_metrics: true,
+            ab_results: Arc::new(RwLock::new(Vec::new())),
+            temp_paths: Vec::new(),
+            partial_output: None,
+            batch_queue: Vec::new(),
+            settings_jobs: Vec::new(),
+            batch_job: Arc::new(RwLock::new(None)),
+            pending_resume: None,
+        }
+    }
+}
+
+impl MyApp {
+    pub fn default_output(&self) -> String {
+        self.default_output_for(Path::new(&self.input_path))
+    }
+    
+    pub fn default_output_for(&self, input: &Path) -> String {
+        if input.file_stem().is_none() { 
+            return String::new(); 
+        }
+        
+        let stem = input.file_stem().unwrap().to_string_lossy();
+        let dir = input.parent().unwrap_or_else(|| Path::new("."));
+        
+        // Test runs always reuse the same file so repeated runs don't pile up numbered copies
+        if self.test_run {
+            let file_name = format!("{}_preview.{}", stem, self.output_ext());
+            return dir.join(TEST_RUN_DIR).join(file_name).display().to_string();
+        }
+        
+        let suffix = match self.selected_function {
+            FunctionType::ExtractAudio => {
+                match self.extract_language(input) {
+                    Some(language) => format!("{}-Audio.{}.{}", stem, language, self.audio_ext()),
+                    None => format!("{}-Audio.{}", stem, self.audio_ext()),
+                }
+            },
+            FunctionType::CompressVideo => {
+                format!("{}-Compressed.{}", stem, self.output_ext())
+            },
+            FunctionType::ConvertToMp4 => {
+                format!("{}-Converted.{}", stem, self.output_format.ext())
+            },
+            FunctionType::ImageToVideo => {
+                format!("{}-Video.{}", stem, self.output_format.ext())
+            },
+            FunctionType::Spectrogram => {
+                format!("{}-Spectrogram.{}", stem, self.output_ext())
+            },
+            FunctionType::LoopClip => {
+                format!("{}-Loop.{}", stem, self.output_format.ext())
+            },
+            FunctionType::Gif => {
+                format!("{}-Animated.{}", stem, self.output_ext())
+            },
+        };
+        
+        dir.join(suffix).display().to_string()
+    }
+    
+    /// The file a job will actually write when asked for `path`. This is the only place
+    /// output names get numbered: the fields and command preview show the name as chosen,
+    /// and it's settled here once, right before the job starts. Test runs overwrite their
+    /// one preview file; anything else that's taken gets the next free numbered name.
+    pub fn resolve_output_path(&self, path: PathBuf) -> PathBuf {
+        if self.test_run {
+            path
+        } else {
+            ffmpeg_utils::unique_path(path, self.settings.naming_scheme)
+        }
+    }
+    
+    /// Snapshot of the current encode settings, for saving as a preset
+    pub fn encode_settings(&self) -> EncodeSettings {
+        EncodeSettings {
+            function: self.selected_function,
+            output_format: self.output_format,
+            audio_format: self.audio_format,
+            video_encoder: self.video_encoder,
+            quality_mode: self.quality_mode,
+            crf: self.crf,
+            qp: self.qp,
+            video_bitrate: self.video_bitrate,
+            strict_cbr: self.strict_cbr,
+            two_pass: self.two_pass,
+            size_limit: self.size_limit,
+            seek_friendly: self.seek_friendly,
+            extra_options: self.extra_options.clone(),
+            filter_complex: self.filter_complex.clone(),
+            filter_maps: self.filter_maps.clone(),
+            cbr_buffer_secs: self.cbr_buffer_secs,
+            encoding_preset: self.encoding_preset.clone(),
+            framerate_mode: self.framerate_mode,
+            cfr_method: self.cfr_method,
+            frame_rate: self.frame_rate,
+            scale_height: self.scale_height,
+            rotation: self.rotation,
+            smart_copy: self.smart_copy,
+            hdr10_passthrough: self.hdr10_passthrough,
+            color_range: self.color_range,
+            remove_audio: self.remove_audio,
+            remove_video: self.remove_video,
+            audio_bitrate: self.audio_bitrate,
+            audio_quality: self.audio_quality,
+            use_audio_quality: self.use_audio_quality,
+            aac_encoder: self.aac_encoder,
+            fdk_vbr: self.fdk_vbr,
+            audio_cutoff: self.audio_cutoff,
+            channel_layout: self.channel_layout,
+            extra_audio_formats: self.extra_audio_formats.clone(),
+            silence_trim: self.silence_trim,
+            silence_internal: self.silence_internal,
+            silence_threshold_db: self.silence_threshold_db,
+            silence_min_secs: self.silence_min_secs,
+            audio_container: self.audio_container,
+            gif_fps: self.gif_fps,
+            gif_width: self.gif_width,
+            gif_palette: self.gif_palette,
+            gif_max_colors: self.gif_max_colors,
+            gif_dither: self.gif_dither,
+        }
+    }
+    
+    pub fn apply_encode_settings(&mut self, settings: &EncodeSettings) {
+        self.restore_encode_settings(settings);
+        self.output_path = self.default_output();
+        self.update_command();
+    }
+    
+    /// Fill the widgets from a parsed FFmpeg command, returning what was picked up. Codecs and
+    /// values the GUI has no control for are left as they were and mentioned in the summary.
+    pub fn load_command(&mut self, parsed: ParsedCommand) -> Vec<String> {
+        let mut loaded = Vec::new();
+        let output_ext = parsed.output.as_deref()
+            .and_then(|output| Path::new(output).extension())
+            .map(|ext| ext.to_string_lossy().to_lowercase());
+        
+        if let Some(input) = parsed.input {
+            loaded.push(format!("input {}", input));
+            self.input_path = input;
+        }
+        let audio_only = parsed.no_video
+            || (parsed.video_codec.is_none() && output_ext.as_deref().is_some_and(|ext| AudioFormat::all().iter().any(|f| f.ext() == ext)));
+        // Copying everything is a remux; copying only the video is what smart copy does
+        let copies_video = parsed.video_codec.as_deref() == Some("copy");
+        let copies_audio = parsed.audio_codec.is_none() || parsed.audio_codec.as_deref() == Some("copy");
+        self.selected_function = match parsed.video_codec.as_deref() {
+            _ if audio_only => FunctionType::ExtractAudio,
+            Some("copy") if copies_audio => FunctionType::ConvertToMp4,
+            _ => FunctionType::CompressVideo,
+        };
+        loaded.push(format!("{:?}", self.selected_function));
+        
+        if self.selected_function == FunctionType::CompressVideo && copies_video {
+            self.smart_copy = true;
+            loaded.push("smart copy (the video is copied while the settings match the source)".to_string());
+        }
+        
+        if self.selected_function == FunctionType::CompressVideo {
+            if let Some(format) = OutputFormat::all().into_iter().find(|f| output_ext.as_deref() == Some(f.ext())) {
+                self.set_output_format(format);
+            }
+            match parsed.video_codec.as_deref().filter(|_| !copies_video).map(|codec| (codec, VideoEncoder::all().into_iter().find(|e| e.codec() == codec))) {
+                Some((_, Some(encoder))) => {
+                    self.set_video_encoder(encoder);
+                    loaded.push(encoder.display_name().to_string());
+                },
+                Some((codec, None)) => loaded.push(format!("video codec {} not available here, kept {}", codec, self.video_encoder.display_name())),
+                None => {},
+            }
+            let modes = self.video_encoder.quality_modes();
+            if let Some(crf) = parsed.crf.filter(|_| modes.contains(&QualityMode::Crf)) {
+                self.quality_mode = QualityMode::Crf;
+                self.crf = crf.clamp(*self.video_encoder.crf_range().start(), *self.video_encoder.crf_range().end());
+                loaded.push(format!("CRF {}", self.crf));
+            } else if let Some(qp) = parsed.qp.or(parsed.crf).filter(|_| modes.contains(&QualityMode::Cqp)) {
+                self.quality_mode = QualityMode::Cqp;
+                self.qp = qp.clamp(*self.video_encoder.qp_range().start(), *self.video_encoder.qp_range().end());
+                loaded.push(format!("QP {}", self.qp));
+            } else if let Some(kbps) = parsed.video_bitrate_kbps {
+                self.quality_mode = QualityMode::Bitrate;
+                self.video_bitrate = kbps.clamp(MIN_VIDEO_KBPS, MAX_VIDEO_KBPS);
+                loaded.push(format!("{} kbps video", self.video_bitrate));
+            }
+            if let Some(preset) = parsed.preset {
+                if self.video_encoder.presets().contains(&preset.as_str()) {
+                    loaded.push(format!("preset {}", preset));
+                    self.encoding_preset = preset;
+                } else {
+                    loaded.push(format!("preset {} not known to {}, ignored", preset, self.video_encoder.display_name()));
+                }
+            }
+            if let Some(height) = parsed.scale_height {
+                self.scale_height = Some(height);
+                loaded.push(format!("scaled to {}", ffmpeg_utils::resolution_label(height)));
+            }
+            if let Some(fps) = parsed.frame_rate {
+                self.framerate_mode = FrameRateMode::CFR;
+                self.frame_rate = fps;
+                loaded.push(format!("{} fps", fps));
+            }
+            self.remove_audio = parsed.no_audio;
+        }
+        
+        let audio_format = match parsed.audio_codec.as_deref() {
+            Some(codec) => AudioFormat::all().into_iter().find(|f| f.codec() == codec)
+                .or_else(|| AacEncoder::all().iter().any(|e| e.codec() == codec).then_some(AudioFormat::AAC)),
+            None => AudioFormat::all().into_iter().find(|f| self.selected_function == FunctionType::ExtractAudio && output_ext.as_deref() == Some(f.ext())),
+        };
+        if let Some(format) = audio_format {
+            self.audio_format = format;
+            if let Some(encoder) = AacEncoder::all().into_iter().find(|e| parsed.audio_codec.as_deref() == Some(e.codec())) {
+                self.aac_encoder = encoder;
+            }
+            loaded.push(format!("{} audio", format.codec()));
+        }
+        if let Some(kbps) = parsed.audio_bitrate_kbps {
+            self.audio_bitrate = kbps;
+            self.use_audio_quality = false;
+            loaded.push(format!("{} kbps audio", kbps));
+        }
+        
+        if parsed.start.is_some() || parsed.end.is_some() || parsed.duration.is_some() {
+            let end = parsed.end.or(parsed.duration.map(|duration| parsed.start.unwrap_or(0.0) + duration));
+            self.trim_enabled = true;
+            self.trim_unit = TrimUnit::Timecode;
+            self.trim_start = parsed.start.map(ffmpeg_utils::format_timestamp).unwrap_or_default();
+            self.trim_end = end.map(ffmpeg_utils::format_timestamp).unwrap_or_default();
+            loaded.push("trim".to_string());
+        }
+        
+        self.extra_options = ffmpeg_utils::join_args(&parsed.extra);
+        if !parsed.extra.is_empty() {
+            loaded.push(format!("extra options {}", self.extra_options));
+        }
+        for ignored in parsed.ignored {
+            loaded.push(format!("ignored {}", ignored));
+        }
+        
+        self.output_path = match parsed.output {
+            Some(output) => output,
+            None => self.default_output(),
+        };
+        self.update_command();
+        loaded
+    }
+    
+    /// Set the encode settings alone, leaving paths and everything else as they are
+    fn restore_encode_settings(&mut self, settings: &EncodeSettings) {
+        self.selected_function = settings.function;
+        self.output_format = settings.output_format;
+        self.audio_format = settings.audio_format;
+        self.video_encoder = settings.video_encoder;
+        self.quality_mode = settings.quality_mode;
+        self.crf = settings.crf;
+        self.qp = settings.qp;
+        self.video_bitrate = settings.video_bitrate;
+        self.strict_cbr = settings.strict_cbr;
+        self.two_pass = settings.two_pass;
+        self.size_limit = settings.size_limit;
+        self.seek_friendly = settings.seek_friendly;
+        self.extra_options = settings.extra_options.clone();
+        self.filter_complex = settings.filter_complex.clone();
+        self.filter_maps = settings.filter_maps.clone();
+        self.cbr_buffer_secs = settings.cbr_buffer_secs;
+        self.encoding_preset = settings.encoding_preset.clone();
+        self.framerate_mode = settings.framerate_mode;
+        self.cfr_method = settings.cfr_method;
+        self.frame_rate = settings.frame_rate;
+        self.scale_height = settings.scale_height;
+        self.rotation = settings.rotation;
+        self.smart_copy = settings.smart_copy;
+        self.hdr10_passthrough = settings.hdr10_passthrough;
+        self.color_range = settings.color_range;
+        self.remove_audio = settings.remove_audio;
+        self.remove_video = settings.remove_video;
+        self.audio_bitrate = settings.audio_bitrate;
+        self.audio_quality = settings.audio_quality;
+        self.use_audio_quality = settings.use_audio_quality;
+        self.aac_encoder = settings.aac_encoder;
+        self.fdk_vbr = settings.fdk_vbr;
+        self.audio_cutoff = settings.audio_cutoff;
+        self.channel_layout = settings.channel_layout;
+        self.extra_audio_formats = settings.extra_audio_formats.clone();
+        self.silence_trim = settings.silence_trim;
+        self.silence_internal = settings.silence_internal;
+        self.silence_threshold_db = settings.silence_threshold_db;
+        self.silence_min_secs = settings.silence_min_secs;
+        self.audio_container = settings.audio_container;
+        self.gif_fps = settings.gif_fps;
+        self.gif_width = settings.gif_width;
+        self.gif_palette = settings.gif_palette;
+        self.gif_max_colors = settings.gif_max_colors;
+        self.gif_dither = settings.gif_dither;
+    }
+    
+    /// Keep the Auto picks in line with the probed input and encoder. Changing one of the
+    /// picked values by hand hands them back to the user.
+    pub fn sync_auto_settings(&mut self) {
+        let info = self.media_info.as_ref().filter(|_| self.probed_path == self.input_path);
+        let Some(info) = info.filter(|_| {
+            self.settings.auto_video && self.selected_function == FunctionType::CompressVideo && !self.remove_video
+        }) else {
+            self.auto_applied = None;
+            return;
+        };
+        let current = self.encode_settings();
+        if let Some(applied) = &self.auto_applied
+            && applied.video_encoder == current.video_encoder
+            && applied.changed_fields(&current).iter().any(|field| presets::AUTO_FIELDS.contains(field))
+        {
+            self.settings.auto_video = false;
+            self.auto_applied = None;
+            return;
+        }
+        // A new input or encoder gets a fresh pick
+        let recommended = presets::recommend_settings(info, &current);
+        if recommended != current {
+            self.restore_encode_settings(&recommended);
+            self.update_command();
+        }
+        self.auto_applied = Some(recommended);
+    }
+    
+    /// Aim Compress Video just under `limit`: an average bitrate that fits the input's length,
+    /// over two passes where the encoder has them
+    pub fn apply_size_limit(&mut self, limit: Option<SizeLimit>) {
+        self.size_limit = limit;
+        if limit.is_some() {
+            self.quality_mode = QualityMode::Bitrate;
+            self.strict_cbr = false;
+            self.two_pass = self.video_encoder.supports_two_pass();
+            self.use_audio_quality = false; // A known audio bitrate to fit the video around
+            self.settings.auto_video = false;
+            self.sync_size_limit();
+        }
+        self.update_command();
+    }
+    
+    /// Whether a size limit sets the video bitrate
+    pub fn size_limited(&self) -> bool {
+        self.size_limit.is_some()
+            && self.selected_function == FunctionType::CompressVideo
+            && !self.remove_video
+            && self.effective_quality_mode() == QualityMode::Bitrate
+    }
+    
+    /// Why the size limit can't be used, when the run writes several outputs. The bitrate
+    /// is fitted to the probed input's length, which queued files, rungs and tracks don't share.
+    pub fn size_limit_problem(&self) -> Option<&'static str> {
+        if !self.size_limited() {
+            None
+        } else if !self.batch_queue.is_empty() {
+            Some("A size limit fits one input. Remove the limit or encode the queued files one at a time")
+        } else if self.ladder_active() {
+            Some("A size limit fits one output. Remove the limit or untick the resolution ladder")
+        } else if self.split_chapters().is_some() {
+            Some("A size limit fits the whole input. Remove the limit or don't split it into tracks")
+        } else {
+            None
+        }
+    }
+    
+    /// The size limit and the output's length, when the limit sets the bitrate
+    pub fn size_limit_target(&self) -> Option<(SizeLimit, f64)> {
+        if !self.size_limited() {
+            return None;
+        }
+        Some((self.size_limit?, self.trimmed_duration(self.duration)?))
+    }
+    
+    /// Audio the output carries, in kbps: every kept track at the audio bitrate
+    pub fn output_audio_kbps(&self) -> u32 {
+        let info = self.media_info.as_ref().filter(|_| self.probed_path == self.input_path);
+        let tracks = if self.remove_audio {
+            0
+        } else if self.replaces_audio() {
+            1
+        } else {
+            info.map_or(1, |info| info.streams.iter().filter(|s| s.codec_type == "audio").count())
+        };
+        self.audio_bitrate * tracks as u32
+    }
+    
+    /// Video bitrate that fits the output under the size limit, None if the audio alone fills it
+    pub fn size_limit_bitrate(&self, limit: SizeLimit, duration: f64) -> Option<u32> {
+        two_pass::fit_bitrate(limit.megabytes(), duration, self.output_audio_kbps())
+            .filter(|&kbps| kbps >= MIN_VIDEO_KBPS)
+    }
+    
+    /// Keep the bitrate on the size limit as the input, trim and audio change
+    pub fn sync_size_limit(&mut self) {
+        let Some((limit, duration)) = self.size_limit_target() else {
+            return;
+        };
+        if let Some(kbps) = self.size_limit_bitrate(limit, duration)
+            && kbps.min(MAX_VIDEO_KBPS) != self.video_bitrate
+        {
+            self.video_bitrate = kbps.min(MAX_VIDEO_KBPS);
+            self.update_command();
+        }
+    }
+    
+    /// The two passes of `args`, a complete command writing `output`, when two-pass is on
+    /// and the video really is encoded
+    pub fn two_pass_plan(&self, args: &[String], output: &Path) -> Option<TwoPassPlan> {
+        let copies_video = args.windows(2).any(|pair| pair[0] == "-c:v" && pair[1] == "copy");
+        let active = self.two_pass
+            && self.selected_function == FunctionType::CompressVideo
+            && !self.remove_video
+            && !copies_video
+            && self.video_encoder.supports_two_pass()
+            && self.effective_quality_mode() == QualityMode::Bitrate;
+        active.then(|| TwoPassPlan::new(args, two_pass::log_prefix(output)))
+    }
+    
+    /// Evaluate `f` as if `settings` were the current encode settings, then put the
+    /// current ones back
+    pub fn with_encode_settings<T>(&mut self, settings: &EncodeSettings, f: impl FnOnce(&Self) -> T) -> T {
+        let current = self.encode_settings();
+        self.restore_encode_settings(settings);
+        let result = f(self);
+        self.restore_encode_settings(&current);
+        result
+    }
+    
+    /// Note a temp file a job is about to create, so Cleanup can remove it if the job doesn't
+    pub fn register_temp(&mut self, path: PathBuf) {
+        if !self.temp_paths.contains(&path) {
+            self.temp_paths.push(path);
+        }
+    }
+    
+    /// Remember the encode settings as they were before the latest change, once the change
+    /// is finished: a slider drag or an edit in a text field becomes a single undo step
+    pub fn record_undo_snapshot(&mut self, editing: bool) {
+        let current = self.encode_settings();
+        if editing || self.undo_baseline.as_ref() == Some(&current) {
+            return;
+        }
+        if let Some(previous) = self.undo_baseline.replace(current) {
+            self.undo_stack.push(previous);
+            if self.undo_stack.len() > UNDO_LIMIT {
+                self.undo_stack.remove(0);
+            }
+        }
+    }
+    
+    /// Step the encode settings back one change; paths, the log and running jobs are untouched
+    pub fn undo(&mut self) {
+        let Some(previous) = self.undo_stack.pop() else {
+            return;
+        };
+        self.restore_encode_settings(&previous);
+        self.undo_baseline = Some(previous);
+        self.update_command();
+    }
+    
+    /// Fields that differ from the active preset, empty when there is none
+    pub fn preset_changes(&self) -> Vec<&'static str> {
+        match &self.active_preset {
+            Some(preset) => self.encode_settings().changed_fields(&preset.settings),
+            None => Vec::new(),
+        }
+    }
+    
+    /// Whether the output only contains audio, so its extension follows the audio format
+    pub fn is_audio_output(&self) -> bool {
+        match self.selected_function {
+            FunctionType::ExtractAudio => true,
+            FunctionType::CompressVideo => self.remove_video,
+            FunctionType::ConvertToMp4 | FunctionType::ImageToVideo | FunctionType::Spectrogram | FunctionType::LoopClip | FunctionType::Gif => false,
+        }
+    }
+    
+    pub fn output_ext(&self) -> &'static str {
+        if self.selected_function == FunctionType::Spectrogram {
+            "png"
+        } else if self.selected_function == FunctionType::Gif {
+            "gif"
+        } else if self.is_audio_output() {
+            self.audio_ext()
+        } else {
+            self.output_format.ext()
+        }
+    }
+    
+    /// The chosen audio container, if it can hold the audio codec; otherwise the codec's own
+    pub fn active_audio_container(&self) -> AudioContainer {
+        if self.selected_function == FunctionType::ExtractAudio && self.audio_container.supports(self.audio_format) {
+            self.audio_container
+        } else {
+            AudioContainer::Native
+        }
+    }
+    
+    fn audio_ext(&self) -> &'static str {
+        self.audio_ext_for(self.audio_format)
+    }
+    
+    /// Extension of an extraction written as `format`, in the chosen container if it can hold it
+    pub fn audio_ext_for(&self, format: AudioFormat) -> &'static str {
+        if self.selected_function == FunctionType::ExtractAudio && self.audio_container.supports(format) {
+            self.audio_container.ext(format)
+        } else {
+            AudioContainer::Native.ext(format)
+        }
+    }
+    
+    /// Every format an extraction writes: the chosen one, then any extra ones
+    pub fn extraction_formats(&self) -> Vec<AudioFormat> {
+        let mut formats = vec![self.audio_format];
+        if self.selected_function == FunctionType::ExtractAudio {
+            formats.extend(self.extra_audio_formats.iter().filter(|&&format| format != self.audio_format));
+        }
+        formats
+    }
+    
+    /// Whether a run extracts more than one format, as a batch with one item per format
+    pub fn multi_format_active(&self) -> bool {
+        self.extraction_formats().len() > 1
+    }
+    
+    /// The current settings with the audio written as `format`
+    pub fn settings_for_format(&self, format: AudioFormat) -> EncodeSettings {
+        EncodeSettings { audio_format: format, ..self.encode_settings() }
+    }
+    
+    /// Sections of the current input to write one file each from: the tracks of a loaded
+    /// cue sheet or timestamp list, or else the input's chapters when splitting by chapters
+    pub fn split_chapters(&self) -> Option<Vec<Chapter>> {
+        if !self.selected_function.supports_splitting() {
+            return None;
+        }
+        let info = self.media_info.as_ref().filter(|_| self.probed_path == self.input_path)?;
+        if !self.split_points.is_empty() {
+            // Each track runs to the next one's start, the last to the end of the input
+            let end = self.duration?;
+            let chapters = self.split_points.iter().enumerate()
+                .map(|(i, (start, title))| Chapter {
+                    start: *start,
+                    end: self.split_points.get(i + 1).map_or(end, |next| next.0),
+                    title: title.clone(),
+                })
+                .filter(|chapter| chapter.end > chapter.start)
+                .collect();
+            return Some(chapters);
+        }
+        (self.selected_function == FunctionType::ExtractAudio && self.split_by_chapters && !info.chapters.is_empty())
+            .then(|| info.chapters.clone())
+    }
+    
+    /// Cover pictures are only written to the codec's own file type
+    pub fn cover_art_supported(&self) -> bool {
+        self.audio_format.supports_cover_art() && self.active_audio_container() == AudioContainer::Native
+    }
+    
+    /// The quality mode actually used: it must be supported by the encoder,
+    /// and constant-quality modes fall back to bitrate in VFR mode
+    pub fn effective_quality_mode(&self) -> QualityMode {
+        let supported = self.video_encoder.quality_modes();
+        if self.framerate_mode == FrameRateMode::VFR {
+            QualityMode::Bitrate
+        } else if supported.contains(&self.quality_mode) {
+            self.quality_mode
+        } else {
+            supported[0]
+        }
+    }
+    
+    /// Why the active video options need `input` re-encoded, or None when copying
+    /// the stream (plus rotation metadata) gives the same result
+    pub fn reencode_reason(&self, input: &str, scale_height: Option<u32>) -> Option<String> {
+        let Some(info) = self.media_info.as_ref().filter(|_| self.probed_path == input) else {
+            return Some("source has not been probed".to_string());
+        };
+        let Some(stream) = info.video_stream() else {
+            return Some("no video stream found".to_string());
+        };
+        
+        if stream.codec_name != self.video_encoder.stream_codec() {
+            return Some(format!("source is {}, not {}", stream.codec_name, self.video_encoder.stream_codec()));
+        }
+        
+        if self.subtitle_mode_for(input) == SubtitleMode::Burn {
+            return Some("burning in subtitles".to_string());
+        }
+        
+        if let Some(height) = scale_height
+            && height != stream.height
+        {
+            return Some(format!("scaling to {}", ffmpeg_utils::resolution_label(height)));
+        }
+        
+        // A copied stream can only be cut at its existing keyframes
+        if self.cut_keyframes().is_some() {
+            return Some("forcing keyframes at the trim points".to_string());
+        }
+        if self.seek_friendly {
+            return Some("seek-friendly keyframes".to_string());
+        }
+        if self.crop_for(input).is_some() {
+            return Some("cropping black bars".to_string());
+        }
+        if self.roi_for(input).is_some() {
+            return Some("encoding a region of interest".to_string());
+        }
+        if self.custom_filter_graph().is_some() {
+            return Some("custom filter graph".to_string());
+        }
+        if self.output_color_range(input).is_some_and(|(_, convert)| convert) {
+            return Some("converting the colour range".to_string());
+        }
+        
+        // Frame rate conversion drops or duplicates frames
+        if self.framerate_mode == FrameRateMode::CFR
+            && info.fps().is_none_or(|fps| (fps - self.frame_rate).abs() > 0.01)
+        {
+            return Some("frame rate change".to_string());
+        }
+        
+        match self.effective_quality_mode() {
+            // A quality target can't be checked against an existing stream
+            QualityMode::Crf | QualityMode::Cqp => Some("constant-quality mode always re-encodes".to_string()),
+            QualityMode::Bitrate if self.strict_cbr => Some("strict CBR needs a re-encode".to_string()),
+            QualityMode::Bitrate => match info.video_bitrate_kbps() {
+                Some(kbps) if kbps <= self.video_bitrate => None,
+                Some(kbps) => Some(format!("source bitrate {} kbps is above the {} kbps target", kbps, self.video_bitrate)),
+                None => Some("source bitrate unknown".to_string()),
+            },
+        }
+    }
+    
+    /// Whether a run produces one output per resolution ladder rung
+    pub fn ladder_active(&self) -> bool {
+        self.selected_function == FunctionType::CompressVideo
+            && !self.remove_video
+            && !self.ladder_heights.is_empty()
+    }
+    
+    /// Trim start and end in seconds of the input, when trimming applies
+    pub fn trim_range(&self) -> Option<(Option<f64>, Option<f64>)> {
+        if !self.trim_enabled || !self.selected_function.supports_trim() {
+            return None;
+        }
+        let start = ffmpeg_utils::parse_trim_point(&self.trim_start, self.trim_unit, self.original_fps);
+        let end = ffmpeg_utils::parse_trim_point(&self.trim_end, self.trim_unit, self.original_fps);
+        Some((start, end))
+    }
+    
+    /// Whether the trimmed video is stream-copied, so its start lands on the keyframe
+    /// at or before the trim point rather than exactly on it
+    pub fn trim_snaps_to_keyframe(&self) -> bool {
+        if self.trim_range().is_none_or(|(start, _)| start.is_none()) {
+            return false;
+        }
+        match self.selected_function {
+            FunctionType::ConvertToMp4 => true,
+            FunctionType::CompressVideo => {
+                !self.remove_video && self.smart_copy && self.reencode_reason(&self.input_path, self.scale_height).is_none()
+            },
+            _ => false,
+        }
+    }
+    
+    /// `-force_key_frames` times for the trim points. Trimming seeks the input, so the
+    /// output timeline starts at the cut: the points are 0 and the clip's length.
+    fn cut_keyframes(&self) -> Option<String> {
+        if !self.keyframes_at_cuts {
+            return None;
+        }
+        let (start, end) = self.trim_range()?;
+        let mut times = vec!["0".to_string()];
+        if let Some(end) = end {
+            times.push(format!("{:.3}", end - start.unwrap_or(0.0)));
+        }
+        Some(times.join(","))
+    }
+    
+    /// `trimmed_duration` for any input: the loaded one's probed length, or another's probed now
+    pub fn trimmed_duration_of(&self, input: &Path) -> Option<f64> {
+        let duration = if Path::new(&self.probed_path) == input {
+            self.duration
+        } else {
+            ffmpeg_utils::probe_duration(input)
+        };
+        self.trimmed_duration(duration.filter(|&duration| duration > 0.0))
+    }
+    
+    /// Length of the output for progress reporting, given the full input duration.
+    /// A trim with both points set has a known length even when the input's is unknown.
+    pub fn trimmed_duration(&self, duration: Option<f64>) -> Option<f64> {
+        match self.trim_range() {
+            Some((start, end)) => {
+                let end = match (end, duration) {
+                    (Some(end), Some(duration)) => end.min(duration),
+                    (end, duration) => end.or(duration)?,
+                };
+                Some((end - start.unwrap_or(0.0)).max(1.0))
+            }
+            None => duration,
+        }
+    }
+    
+    /// Whether the output goes to stdout; a spectrogram is a single image and always goes to a file
+    pub fn pipe_active(&self) -> bool {
+        self.pipe_output && self.selected_function != FunctionType::Spectrogram
+    }
+    
+    /// Muxer for piped output, which has no extension to infer it from. MP4 normally
+    /// seeks back to write its index, so it has to be fragmented to stream.
+    fn pipe_format_args(&self) -> Vec<String> {
+        let fragmented_mp4 = || vec![
+            "-f".to_string(), "mp4".to_string(),
+            "-movflags".to_string(), "frag_keyframe+empty_moov".to_string(),
+        ];
+        let format = if self.selected_function == FunctionType::Gif {
+            "gif"
+        } else if self.is_audio_output() {
+            match self.active_audio_container() {
+                AudioContainer::Matroska => "matroska",
+                AudioContainer::Ogg => "ogg",
+                AudioContainer::Mp4 if self.audio_format != AudioFormat::AAC => return fragmented_mp4(),
+                AudioContainer::Mp4 | AudioContainer::Native => self.audio_format.stream_muxer(),
+            }
+        } else {
+            match self.output_format {
+                OutputFormat::Mp4 => return fragmented_mp4(),
+                OutputFormat::Mkv => "matroska",
+                OutputFormat::WebM => "webm",
+                OutputFormat::Ogg => "ogg",
+            }
+        };
+        vec!["-f".to_string(), format.to_string()]
+    }
+    
+    /// The separate audio, video and mux commands for `output`, when Compress Video encodes
+    /// its streams separately. Both streams have to be kept, and the audio has to be the input's.
+    pub fn separate_plan(&self, input: &str, output: &str) -> Option<SeparatePlan> {
+        if self.selected_function != FunctionType::CompressVideo
+            || !self.separate_streams
+            || self.remove_audio
+            || self.remove_video
+            || self.replaces_audio()
+            || self.pipe_active()
+        {
+            return None;
+        }
+        let trim = self.trim_range();
+        
+        let mut audio_args = vec!["-nostdin".to_string()];
+        audio_args.extend(ffmpeg_utils::split_args(&self.settings.global_options).unwrap_or_default());
+        audio_args.extend(ffmpeg_utils::trim_args(trim));
+        audio_args.extend(["-i".to_string(), input.to_string()]);
+        if self.audio_reordered(input) {
+            audio_args.extend(self.ordered_maps(false));
+        } else {
+            audio_args.extend(["-map".to_string(), "0:a".to_string()]);
+        }
+        audio_args.extend(["-vn".to_string(), "-sn".to_string(), "-dn".to_string()]);
+        audio_args.extend(self.audio_encode_args());
+        audio_args.extend(self.audio_tag_args(input));
+        let audio_path = separate::audio_path(Path::new(input), &audio_args);
+        audio_args.extend(["-y".to_string(), ffmpeg_utils::temp_output_path(&audio_path).display().to_string()]);
+        
+        // The usual command without its audio, and without the per-track options that would
+        // name audio streams no longer there
+        let video_path = separate::video_path(Path::new(output));
+        let full = self.build_command_trimmed(input, &video_path.display().to_string(), self.scale_height, trim);
+        let mut video_args = Vec::new();
+        let mut args = full.into_iter();
+        while let Some(arg) = args.next() {
+            if arg.starts_with("-metadata:s:a:") || arg.starts_with("-disposition:a:") {
+                args.next();
+            } else if arg == "-y" {
+                video_args.extend(["-an".to_string(), arg]);
+            } else {
+                video_args.push(arg);
+            }
+        }
+        
+        let mut mux_args = vec![
+            "-nostdin".to_string(),
+            "-i".to_string(), video_path.display().to_string(),
+            "-i".to_string(), audio_path.display().to_string(),
+            "-map".to_string(), "0".to_string(),
+            "-map".to_string(), "1:a".to_string(),
+            "-c".to_string(), "copy".to_string(),
+        ];
+        mux_args.extend(self.creation_time_args());
+        mux_args.extend(["-y".to_string(), output.to_string()]);
+        
+        let video_passes = self.two_pass_plan(&video_args, &video_path);
+        Some(SeparatePlan { audio_args, audio_path, video_args, video_passes, video_path, mux_args })
+    }
+    
+    /// How the GIF is drawn, when making one
+    pub fn gif_plan(&self) -> Option<GifPlan> {
+        (self.selected_function == FunctionType::Gif).then(|| GifPlan {
+            fps: self.gif_fps,
+            width: self.gif_width,
+            max_colors: self.gif_max_colors,
+            dither: self.gif_dither,
+            palette: self.gif_palette,
+            trim: self.trim_range(),
+        })
+    }
+    
+    /// The GIF plan when it needs a palette pass before the encode
+    pub fn palette_plan(&self) -> Option<GifPlan> {
+        self.gif_plan().filter(|plan| plan.palette)
+    }
+    
+    /// Chapters to generate for the current function, if any
+    pub fn chapter_plan(&self) -> Option<ChapterPlan> {
+        // Piped output has no file name to hang the chapter metadata off, and the
+        // sections of a split are a chapter each already
+        if !self.selected_function.supports_chapters()
+            || self.chapter_mode == ChapterMode::Off
+            || self.pipe_active()
+            || self.split_chapters().is_some()
+        {
+            return None;
+        }
+        Some(ChapterPlan {
+            mode: self.chapter_mode,
+            interval_secs: self.chapter_interval_min as f64 * 60.0,
+            scene_threshold: self.scene_threshold,
+            trim: self.trim_range(),
+        })
+    }
+    
+    /// The external subtitle handling that applies to `input`. The file was picked for the
+    /// probed input, so other files (batch items) don't get it; burning needs a re-encode.
+    pub fn subtitle_mode_for(&self, input: &str) -> SubtitleMode {
+        let applies = match self.selected_function {
+            FunctionType::CompressVideo => !self.remove_video,
+            FunctionType::ConvertToMp4 => self.subtitle_mode != SubtitleMode::Burn,
+            _ => false,
+        };
+        if applies && self.probed_path == input && !self.subtitle_path.trim().is_empty() {
+            self.subtitle_mode
+        } else {
+            SubtitleMode::Off
+        }
+    }
+    
+    /// The region of interest for `input`, as fractions of the source frame. The rectangle was
+    /// drawn on the probed input, so other files (batch items) don't get it.
+    pub fn roi_for(&self, input: &str) -> Option<[f32; 4]> {
+        if !self.roi_enabled
+            || self.selected_function != FunctionType::CompressVideo
+            || self.remove_video
+            || !self.video_encoder.supports_roi()
+            || self.probed_path != input
+        {
+            return None;
+        }
+        let (width, height) = self.media_info.as_ref().and_then(MediaInfo::resolution)?;
+        // The region is drawn on the whole frame, of which a crop keeps only part
+        let [left, top, width, height] = self.crop_for(input)
+            .map_or([0, 0, width, height], |crop| [crop.x, crop.y, crop.width, crop.height]);
+        let [x, y, w, h] = self.roi_rect;
+        let (x, end_x) = (x.clamp(left, left + width) - left, (x + w).clamp(left, left + width) - left);
+        let (y, end_y) = (y.clamp(top, top + height) - top, (y + h).clamp(top, top + height) - top);
+        let (w, h) = (end_x - x, end_y - y);
+        if w == 0 || h == 0 {
+            return None;
+        }
+        let (width, height) = (width as f32, height as f32);
+        Some([x as f32 / width, y as f32 / height, w as f32 / width, h as f32 / height])
+    }
+    
+    /// The crop for `input`. It was detected on the probed input, so other files (batch
+    /// items) don't get it.
+    pub fn crop_for(&self, input: &str) -> Option<Crop> {
+        if self.selected_function != FunctionType::CompressVideo || self.remove_video || self.probed_path != input {
+            return None;
+        }
+        self.crop
+    }
+    
+    /// `addroi` for the region of interest, in terms of the filtered frame's size so rotation
+    /// and scaling earlier in the chain don't move it
+    fn roi_filter(&self, input: &str) -> Option<String> {
+        let [x, y, w, h] = self.rotation.rotate_rect(self.roi_for(input)?);
+        Some(format!(
+            "addroi=x=iw*{:.4}:y=ih*{:.4}:w=iw*{:.4}:h=ih*{:.4}:qoffset={:.2}",
+            x, y, w, h, -self.roi_boost
+        ))
+    }
+    
+    /// The CFR target rate as passed to -r or the fps filter
+    fn cfr_rate(&self) -> String {
+        if self.settings.exact_ntsc_rates {
+            ffmpeg_utils::frame_rate_arg(self.frame_rate)
+        } else {
+            format!("{:.3}", self.frame_rate)
+        }
+    }
+    
+    /// The fps filter, when it's how CFR is reached. A custom filter graph replaces the
+    /// filter chain, so it falls back to -r then.
+    fn cfr_filter(&self) -> Option<String> {
+        let applies = self.framerate_mode == FrameRateMode::CFR
+            && self.cfr_method == CfrMethod::FpsFilter
+            && self.custom_filter_graph().is_none();
+        applies.then(|| format!("fps={}", self.cfr_rate()))
+    }
+    
+    /// Frame rate, crop, rotation, scaling, subtitle and region-of-interest filters for a
+    /// re-encode. The frame rate goes first so the rest only process the frames kept, then the
+    /// crop since it's in source pixels, rotation so the height applies to the result, subtitles
+    /// after so they're drawn upright at output size, and the region last since the encoder
+    /// reads it off the final frames.
+    fn video_filters(&self, input: &str, scale_height: Option<u32>) -> Option<String> {
+        let scale = scale_height.map(|h| format!("scale=-2:{}", h)); // -2 keeps the width even
+        let burn = (self.subtitle_mode_for(input) == SubtitleMode::Burn)
+            .then(|| subtitles::burn_filter(Path::new(self.subtitle_path.trim()), &self.subtitle_charset));
+        let filters: Vec<String> = self.cfr_filter().into_iter()
+            .chain(self.crop_for(input).map(|crop| crop.filter()))
+            .chain(self.rotation.filter().map(str::to_string))
+            .chain(scale)
+            .chain(self.color_range_filter(input))
+            .chain(burn)
+            .chain(self.roi_filter(input))
+            .collect();
+        if filters.is_empty() { None } else { Some(filters.join(",")) }
+    }
+    
+    /// The hand-written filter graph, when one is set for a video encode
+    pub fn custom_filter_graph(&self) -> Option<&str> {
+        let graph = self.filter_complex.trim();
+        (self.selected_function == FunctionType::CompressVideo && !self.remove_video && !graph.is_empty()).then_some(graph)
+    }
+    
+    /// `-map` for each stream or graph output listed alongside the filter graph
+    fn filter_map_args(&self) -> Vec<String> {
+        ffmpeg_utils::split_args(&self.filter_maps)
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|map| ["-map".to_string(), map])
+            .collect()
+    }
+    
+    /// Range to tag the output with, and whether the pixels have to be converted to it.
+    /// Auto keeps a probed source's range; a source that declares none is left alone.
+    pub fn output_color_range(&self, input: &str) -> Option<(&'static str, bool)> {
+        let source = self.media_info.as_ref()
+            .filter(|_| self.probed_path == input)
+            .and_then(MediaInfo::color_range);
+        match self.color_range.value() {
+            None => source.map(|range| (range, false)),
+            Some(target) => Some((target, source != Some(target))),
+        }
+    }
+    
+    /// Expand or squeeze levels into the chosen range; without a declared source range
+    /// scale goes by what the decoder reports
+    fn color_range_filter(&self, input: &str) -> Option<String> {
+        let (target, convert) = self.output_color_range(input)?;
+        if !convert {
+            return None;
+        }
+        let source = self.media_info.as_ref()
+            .filter(|_| self.probed_path == input)
+            .and_then(MediaInfo::color_range);
+        Some(match source {
+            Some(source) => format!("scale=in_range={}:out_range={}", source, target),
+            None => format!("scale=out_range={}", target),
+        })
+    }
+    
+    /// Switch encoders, keeping the quality mode, preset and container valid for the new one
+    pub fn set_video_encoder(&mut self, encoder: VideoEncoder) {
+        self.video_encoder = encoder;
+        if !encoder.quality_modes().contains(&self.quality_mode) {
+            self.quality_mode = encoder.quality_modes()[0];
+        }
+        if !encoder.presets().contains(&self.encoding_preset.as_str()) {
+            self.encoding_preset = encoder.default_preset().to_string();
+        }
+        self.crf = self.crf.clamp(*encoder.crf_range().start(), *encoder.crf_range().end());
+        self.qp = self.qp.clamp(*encoder.qp_range().start(), *encoder.qp_range().end());
+        if !self.output_format.supports_encoder(encoder) {
+            self.output_format = encoder.native_format();
+            self.fit_audio_to_container();
+            self.output_path = self.default_output();
+        }
+    }
+    
+    /// Switch containers, moving a Compress Video encode to an encoder and audio codec it can carry
+    pub fn set_output_format(&mut self, format: OutputFormat) {
+        self.output_format = format;
+        if self.selected_function == FunctionType::CompressVideo {
+            if !format.supports_encoder(self.video_encoder)
+                && let Some(encoder) = VideoEncoder::all().into_iter().find(|&e| format.supports_encoder(e) && self.has_encoder(e.codec()))
+            {
+                self.set_video_encoder(encoder);
+            }
+            self.fit_audio_to_container();
+        }
+        self.output_path = self.default_output();
+    }
+    
+    fn fit_audio_to_container(&mut self) {
+        if !self.output_format.supports_audio(self.audio_format)
+            && let Some(format) = [AudioFormat::VORBIS, AudioFormat::OPUS, AudioFormat::AAC].into_iter().find(|&f| self.output_format.supports_audio(f))
+        {
+            self.audio_format = format;
+        }
+    }
+    
+    /// Length of the segment a loop is made from: the trim range, or the whole probed input
+    pub fn loop_length(&self, input: &str, trim: Option<(Option<f64>, Option<f64>)>) -> Option<f64> {
+        let (start, end) = trim.unwrap_or_default();
+        let end = end.or(self.duration.filter(|_| self.probed_path == input))?;
+        Some(end - start.unwrap_or(0.0))
+    }
+    
+    /// Why the loop can't be made, e.g. a crossfade too long for the segment
+    pub fn loop_problem(&self) -> Option<String> {
+        let Some(length) = self.loop_length(&self.input_path, self.trim_range()) else {
+            return Some("The segment's length is unknown; set a trim end point".to_string());
+        };
+        (length <= 2.0 * self.loop_crossfade).then(|| format!(
+            "A {:.1} s crossfade needs a segment longer than {:.1} s (this one is {:.1} s)",
+            self.loop_crossfade,
+            2.0 * self.loop_crossfade,
+            length
+        ))
+    }
+    
+    /// Why the chosen container can't hold this encode's video or audio, if it can't
+    pub fn container_problem(&self) -> Option<String> {
+        let encoder = match self.selected_function {
+            FunctionType::CompressVideo if !self.remove_video => self.video_encoder,
+            FunctionType::ImageToVideo | FunctionType::LoopClip => VideoEncoder::X264,
+            _ => return None,
+        };
+        let format = self.output_format;
+        if !format.supports_encoder(encoder) {
+            return Some(format!("{} output can't hold {} video", format.display_name(), encoder.display_name()));
+        }
+        let encodes_audio = !(self.selected_function == FunctionType::CompressVideo && self.remove_audio);
+        if encodes_audio && !format.supports_audio(self.audio_format) {
+            return Some(format!("{} output can't hold {} audio", format.display_name(), self.audio_format.display_name()));
+        }
+        None
+    }
+    
+    /// Whether the installed FFmpeg has the named encoder
+    pub fn has_encoder(&self, name: &str) -> bool {
+        self.available_encoders.iter().any(|e| e == name)
+    }
+    
+    pub fn has_filter(&self, name: &str) -> bool {
+        self.available_filters.iter().any(|f| f == name)
+    }
+    
+    /// FFmpeg audio codec for the selected format, honouring the AAC encoder choice
+    fn audio_codec(&self) -> &'static str {
+        match self.audio_format {
+            AudioFormat::AAC => self.aac_encoder.codec(),
+            _ => self.audio_format.codec(),
+        }
+    }
+    
+    /// The VBR quality for the encoder's -q:a. LAME counts down from 9 (smallest) to 0 (best)
+    /// where Vorbis counts up from 0 to 10, so the shared 0-10 value is flipped for MP3.
+    pub fn vbr_quality(&self) -> u8 {
+        let quality = self.audio_quality.min(10);
+        match self.audio_format {
+            AudioFormat::MP3 => (9.0 - f32::from(quality) * 0.9).round() as u8,
+            _ => quality,
+        }
+    }
+    
+    /// Rate control arguments for AAC, which differ between the native and FDK encoders
+    fn aac_args(&self) -> Vec<String> {
+        let mut args = match self.aac_encoder {
+            AacEncoder::Fdk if self.fdk_vbr > 0 => vec![
+                "-vbr".to_string(),
+                self.fdk_vbr.to_string(),
+            ],
+            AacEncoder::Fdk => vec![
+                "-b:a".to_string(),
+                format!("{k}k", k = self.audio_bitrate),
+            ],
+            AacEncoder::Native => vec![
+                "-b:a".to_string(),
+                format!("{k}k", k = self.audio_bitrate),
+                "-strict".to_string(),
+                "experimental".to_string(),
+            ],
+        };
+        args.extend(self.cutoff_args());
+        args
+    }
+    
+    /// Lowpass cutoff for AAC and Opus, when one is set. libopus only takes a few fixed
+    /// values, so anything else is rounded to the nearest of those.
+    fn cutoff_args(&self) -> Vec<String> {
+        if self.audio_cutoff == 0 {
+            return Vec::new();
+        }
+        let cutoff = match self.audio_format {
+            AudioFormat::AAC => self.audio_cutoff,
+            AudioFormat::OPUS => OPUS_CUTOFFS.into_iter().min_by_key(|c| c.abs_diff(self.audio_cutoff)).unwrap_or(self.audio_cutoff),
+            _ => return Vec::new(),
+        };
+        vec!["-cutoff".to_string(), cutoff.to_string()]
+    }
+    
+    /// Cover art source for the current settings, if the output gets one
+    pub fn active_cover_art(&self) -> CoverArt {
+        if self.selected_function == FunctionType::ExtractAudio && self.cover_art_supported() {
+            self.cover_art
+        } else {
+            CoverArt::None
+        }
+    }
+    
+    /// The single audio stream to extract from `input`, if one was picked for the probed input
+    fn extract_track_for(&self, input: &str) -> Option<usize> {
+        self.extract_track
+            .filter(|_| self.selected_function == FunctionType::ExtractAudio && self.probed_path == input)
+    }
+    
+    /// Language tag of the extracted track, used to name the output, e.g. `movie-Audio.fr.mka`
+    fn extract_language(&self, input: &Path) -> Option<String> {
+        let track = self.extract_track_for(&input.display().to_string())?;
+        let language = self.audio_tags.get(track)?.language.trim();
+        // "und" is FFmpeg's placeholder for an untagged stream
+        let usable = !language.is_empty() && language != "und"
+            && language.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+        usable.then(|| language.to_string())
+    }
+    
+    /// Whether the input's audio is swapped for the audio file's, e.g. a dub or new music
+    pub fn replaces_audio(&self) -> bool {
+        self.replace_audio
+            && match self.selected_function {
+                FunctionType::CompressVideo => !self.remove_audio && !self.remove_video,
+                FunctionType::ConvertToMp4 => !self.remux_drop_audio,
+                _ => false,
+            }
+    }
+    
+    /// Whether the audio file is mixed under the input's own first audio track instead of
+    /// replacing it; mixing needs a re-encode, so only Compress Video offers it
+    pub fn mixes_audio(&self) -> bool {
+        self.replaces_audio() && self.mix_audio && self.selected_function == FunctionType::CompressVideo
+    }
+    
+    /// Whether `input` has audio, as far as the probe knows; inputs that weren't probed are
+    /// assumed to
+    pub fn input_has_audio(&self, input: &str) -> bool {
+        self.media_info.as_ref()
+            .filter(|_| self.probed_path == input)
+            .is_none_or(MediaInfo::has_audio)
+    }
+    
+    /// Volumes of the input's audio and the added track for the mix balance
+    pub fn mix_weights(&self) -> (f32, f32) {
+        ((2.0 * (1.0 - self.mix_balance)).min(1.0), (2.0 * self.mix_balance).min(1.0))
+    }
+    
+    /// Whether the command reads the separate audio file
+    pub fn uses_audio_input(&self) -> bool {
+        self.selected_function.needs_audio_input() || self.replaces_audio()
+    }
+    
+    /// `-map` arguments swapping the input's audio for all of the audio file's
+    fn replaced_audio_maps(&self, input_index: usize) -> Vec<String> {
+        vec![
+            "-map".to_string(), "-0:a".to_string(),
+            "-map".to_string(), format!("{}:a", input_index),
+        ]
+    }
+    
+    /// An `amix` graph putting the audio file under the input's first audio track, mapped in
+    /// place of the input's audio. It runs as long as the input's track.
+    fn mixed_audio_args(&self, input_index: usize) -> Vec<String> {
+        let (original, added) = self.mix_weights();
+        vec![
+            "-filter_complex".to_string(),
+            format!(
+                "[0:a:0][{}:a:0]amix=inputs=2:duration=first:dropout_transition=0:weights={:.2} {:.2}:normalize=0[mixed]",
+                input_index, original, added
+            ),
+            "-map".to_string(), "-0:a".to_string(),
+            "-map".to_string(), "[mixed]".to_string(),
+        ]
+    }
+    
+    /// Whether the audio tracks of `input` were put in a different order than the source's
+    fn audio_reordered(&self, input: &str) -> bool {
+        !self.replaces_audio()
+            && self.probed_path == input && self.audio_tags.iter().enumerate().any(|(i, tags)| tags.source != i)
+    }
+    
+    /// `-map` arguments with the audio tracks in the chosen order. With `all_streams`, video
+    /// comes first and subtitles, data and attachments follow the audio.
+    fn ordered_maps(&self, all_streams: bool) -> Vec<String> {
+        let mut maps = Vec::new();
+        if all_streams {
+            maps.push("0:v?".to_string());
+        }
+        maps.extend(self.audio_tags.iter().map(|tags| format!("0:a:{}", tags.source)));
+        if all_streams {
+            maps.extend(["0:s?".to_string(), "0:d?".to_string(), "0:t?".to_string()]);
+        }
+        maps.into_iter().flat_map(|map| ["-map".to_string(), map]).collect()
+    }
+    
+    /// Stream indices of `input` to leave out of a remux; they were picked for the probed input
+    fn remux_excluded_for(&self, input: &str) -> &[u32] {
+        if self.selected_function == FunctionType::ConvertToMp4 && self.probed_path == input {
+            &self.remux_excluded
+        } else {
+            &[]
+        }
+    }
+    
+    /// Whether a remux leaves out any stream of the given type picked one by one
+    fn remux_excludes(&self, codec_type: &str) -> bool {
+        self.media_info.as_ref().is_some_and(|info| {
+            info.streams.iter().any(|s| s.codec_type == codec_type && self.remux_excluded.contains(&s.index))
+        })
+    }
+    
+    /// Whether the output keeps all of the input's subtitle streams
+    fn keeps_subtitles(&self) -> bool {
+        match self.selected_function {
+            FunctionType::CompressVideo => !self.remove_video && self.output_format.supports_subtitles(),
+            FunctionType::ConvertToMp4 => !self.remux_drop_subtitles && !self.remux_excludes("subtitle"),
+            _ => false,
+        }
+    }
+    
+    /// Default-track flags for the output, written only when they differ from the source's:
+    /// some TVs pick the track to play from these rather than from the language
+    fn disposition_args(&self, input: &str) -> Vec<String> {
+        if self.probed_path != input {
+            return Vec::new();
+        }
+        
+        let mut args = Vec::new();
+        if self.keeps_all_audio() && self.audio_tags.iter().any(|tags| tags.default != tags.source_default) {
+            for (i, tags) in self.audio_tags.iter().enumerate() {
+                args.extend([format!("-disposition:a:{}", i), if tags.default { "default" } else { "0" }.to_string()]);
+            }
+        }
+        if let Some(default) = self.default_subtitle
+            && self.keeps_subtitles()
+        {
+            let count = self.media_info.as_ref().map_or(0, |info| info.streams.iter().filter(|s| s.codec_type == "subtitle").count());
+            for i in 0..count {
+                args.extend([format!("-disposition:s:{}", i), if i == default { "default" } else { "0" }.to_string()]);
+            }
+        } else if self.subtitle_default && self.subtitle_mode_for(input) == SubtitleMode::Mux {
+            // A muxed default track takes over from the source's, or players pick the first
+            for i in 0..self.muxed_subtitle_index() {
+                args.extend([format!("-disposition:s:{}", i), "0".to_string()]);
+            }
+        }
+        args
+    }
+    
+    /// Whether every audio stream of the input ends up in the output, in order, so the
+    /// per-track tags line up with the output track indices
+    pub fn keeps_all_audio(&self) -> bool {
+        if self.replaces_audio() {
+            return false;
+        }
+        match self.selected_function {
+            FunctionType::ExtractAudio => self.extract_track.is_none(),
+            FunctionType::ConvertToMp4 => !self.remux_drop_audio && !self.remux_excludes("audio"),
+            FunctionType::CompressVideo => !self.remove_audio,
+            FunctionType::ImageToVideo | FunctionType::Spectrogram | FunctionType::LoopClip | FunctionType::Gif => false,
+        }
+    }
+    
+    /// Language and title metadata for each output audio track of `input`
+    fn audio_tag_args(&self, input: &str) -> Vec<String> {
+        if !self.keeps_all_audio() || self.probed_path != input {
+            return Vec::new();
+        }
+        
+        let mut args = Vec::new();
+        for (i, tags) in self.audio_tags.iter().enumerate() {
+            for (key, value) in [("language", &tags.language), ("title", &tags.title)] {
+                let value = value.trim();
+                if !value.is_empty() {
+                    args.extend([format!("-metadata:s:a:{}", i), format!("{}={}", key, value)]);
+                }
+            }
+        }
+        args
+    }
+    
+    /// Set or remove creation_time on the output and its streams, which FFmpeg otherwise
+    /// copies from the source. PNG spectrograms have no such metadata.
+    fn creation_time_args(&self) -> Vec<String> {
+        let value = match self.creation_time {
+            CreationTime::Source => return Vec::new(),
+            _ if self.selected_function == FunctionType::Spectrogram => return Vec::new(),
+            CreationTime::Now => "now".to_string(),     // FFmpeg fills in the current UTC time
+            CreationTime::Custom => self.custom_creation_time.trim().to_string(),
+            CreationTime::Clear => String::new(),       // An empty value drops the tag
+        };
+        vec![
+            "-metadata".to_string(), format!("creation_time={}", value),
+            "-metadata:s".to_string(), format!("creation_time={}", value),
+        ]
+    }
+    
+    /// Start the output's timeline at zero. Stream captures can begin at a large timestamp,
+    /// which players show as an odd initial seek or a wrong duration.
+    pub fn reset_timestamp_args(&self) -> Vec<String> {
+        let applies = match self.selected_function {
+            FunctionType::CompressVideo => true,
+            FunctionType::ConvertToMp4 => !self.copy_timestamps, // Which already passes make_zero
+            _ => false,
+        };
+        if !self.reset_timestamps || !applies {
+            return Vec::new();
+        }
+        vec!["-avoid_negative_ts".to_string(), "make_zero".to_string()]
+    }
+    
+    /// Output index of the muxed subtitle track: it follows the source subtitles the output keeps
+    fn muxed_subtitle_index(&self) -> usize {
+        match self.media_info.as_ref() {
+            _ if self.selected_function == FunctionType::ConvertToMp4 && self.remux_drop_subtitles => 0,
+            // WebM and Ogg drop the source's subtitles, so the muxed file is the only track
+            _ if self.selected_function == FunctionType::CompressVideo && !self.output_format.supports_subtitles() => 0,
+            Some(info) => info.streams.iter()
+                .filter(|s| s.codec_type == "subtitle" && !self.remux_excluded_for(&self.probed_path).contains(&s.index))
+                .count(),
+            None => 0,
+        }
+    }
+    
+    /// Language tag for the muxed subtitle file: the one typed in, else one from its name
+    pub fn muxed_subtitle_language(&self) -> Option<String> {
+        let language = self.subtitle_language.trim();
+        if !language.is_empty() {
+            return Some(language.to_string());
+        }
+        let path = Path::new(self.subtitle_path.trim());
+        self.sidecar_subtitles.iter()
+            .find(|sidecar| sidecar.path == path)
+            .and_then(|sidecar| sidecar.language.clone())
+    }
+    
+    /// Map the external subtitle input as an extra track after the source's own subtitles
+    fn subtitle_mux_args(&self, input_index: usize) -> Vec<String> {
+        let path = Path::new(self.subtitle_path.trim());
+        let index = self.muxed_subtitle_index();
+        let mut args = vec![
+            "-map".to_string(), format!("{}:0", input_index),
+            format!("-c:s:{}", index), subtitles::mux_codec(path, self.output_ext()).to_string(),
+        ];
+        if let Some(language) = self.muxed_subtitle_language() {
+            args.extend([format!("-metadata:s:s:{}", index), format!("language={}", language)]);
+        }
+        // Players only show a track unasked when it's flagged
+        let disposition = match (self.subtitle_default, self.subtitle_forced) {
+            (true, true) => Some("default+forced"),
+            (true, false) => Some("default"),
+            (false, true) => Some("forced"),
+            (false, false) => None,
+        };
+        if let Some(disposition) = disposition {
+            args.extend([format!("-disposition:s:{}", index), disposition.to_string()]);
         }
+        args
     }
-}
-
-impl MyApp {
-    pub fn default_output(&self) -> String {
-        let input = Path::new(&self.input_path);
-        if input.file_stem().is_none() { 
-            return String::new(); 
+    
+    /// The HDR10 x265 parameters for `input` when passthrough applies: x265 is selected
+    /// and the probed source is HDR10. Other files (batch items) are left alone.
+    pub fn hdr10_params(&self, input: &str) -> Option<String> {
+        if !self.hdr10_passthrough || self.video_encoder != VideoEncoder::X265 || self.probed_path != input {
+            return None;
         }
+        self.media_info.as_ref()?.hdr10_x265_params()
+    }
+    
+    /// Everything that goes into x265's single -x265-params option, since a second one would replace the first
+    fn x265_params(&self, input: &str) -> Vec<String> {
+        let mut params = Vec::new();
+        if self.strict_cbr && self.effective_quality_mode() == QualityMode::Bitrate {
+            params.push("hrd=1".to_string());
+        }
+        params.extend(self.hdr10_params(input));
+        if self.seek_friendly {
+            params.push("scenecut=0".to_string());
+        }
+        if params.is_empty() {
+            Vec::new()
+        } else {
+            vec!["-x265-params".to_string(), params.join(":")]
+        }
+    }
+    
+    /// Whether the function keeps `input`'s video stream as-is rather than encoding it
+    fn copies_video(&self, input: &str, scale_height: Option<u32>) -> bool {
+        match self.selected_function {
+            FunctionType::CompressVideo => {
+                !self.remove_video && self.smart_copy && self.reencode_reason(input, scale_height).is_none()
+            },
+            FunctionType::ConvertToMp4 => true,
+            _ => false,
+        }
+    }
+    
+    /// Clockwise rotation for a copied video stream, or None when it needs none.
+    /// The source's own rotation is written back too, since some FFmpeg versions
+    /// drop the display matrix when stream copying and leave phone videos sideways.
+    fn copied_rotation(&self, input: &str) -> Option<u32> {
+        let source = self.media_info.as_ref()
+            .filter(|_| self.probed_path == input)
+            .map_or(0, MediaInfo::rotation);
+        (source != 0 || self.rotation != Rotation::None).then(|| (source + self.rotation.degrees()) % 360)
+    }
+    
+    /// Rotate a copied video stream through its display matrix, which players honour on
+    /// playback. This is an input option, counted anticlockwise.
+    fn display_rotation_args(&self, input: &str) -> Vec<String> {
+        match self.copied_rotation(input) {
+            Some(degrees) if self.display_rotation => vec![
+                "-display_rotation:v:0".to_string(),
+                ((360 - degrees) % 360).to_string(),
+            ],
+            _ => Vec::new(),
+        }
+    }
+    
+    /// Rotate a copied video stream by tagging it, for FFmpeg builds before 6.0 that
+    /// have no -display_rotation. Newer ones ignore the tag.
+    fn rotation_metadata_args(&self, input: &str) -> Vec<String> {
+        match self.copied_rotation(input) {
+            Some(degrees) if !self.display_rotation => vec![
+                "-metadata:s:v:0".to_string(),
+                format!("rotate={}", degrees),
+            ],
+            _ => Vec::new(),
+        }
+    }
+    
+    /// Audio codec and quality arguments used when audio is encoded alongside video
+    fn audio_encode_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "-c:a".to_string(),
+            self.audio_codec().to_string(),
+        ];
         
-        let stem = input.file_stem().unwrap().to_string_lossy();
-        let dir = input.parent().unwrap_or_else(|| Path::new("."));
-        
-        let suffix = match self.selected_function {
-            FunctionType::ExtractAudio => {
-                format!("{}-Audio.{}", stem, self.audio_format.ext())
+        // Audio codec and quality settings based on format
+        match self.audio_format {
+            AudioFormat::MP3 | AudioFormat::VORBIS => {
+                if self.use_audio_quality {
+                    // Variable bitrate mode (VBR)
+                    args.extend([
+                        "-q:a".to_string(),
+                        self.vbr_quality().to_string(),
+                    ]);
+                } else {
+                    // Constant bitrate mode (CBR)
+                    args.extend([
+                        "-b:a".to_string(),
+                        format!("{k}k", k = self.audio_bitrate),
+                    ]);
+                }
             },
-            FunctionType::CompressVideo => {
-                format!("{}-Compressed.{}", stem, self.output_format.ext())
+            AudioFormat::OPUS => {
+                args.extend([
+                    "-b:a".to_string(),
+                    format!("{k}k", k = self.audio_bitrate),
+                    "-compression_level".to_string(),
+                    self.audio_quality.to_string(),
+                    "-strict".to_string(),
+                    "experimental".to_string(),
+                ]);
+                args.extend(self.cutoff_args());
             },
-            FunctionType::ConvertToMp4 => {
-                format!("{}-Converted.{}", stem, self.output_format.ext())
+            AudioFormat::AAC => {
+                args.extend(self.aac_args());
             },
-        };
+            AudioFormat::FLAC => {
+                args.extend([
+                    "-compression_level".to_string(),
+                    self.audio_quality.to_string(),
+                    "-strict".to_string(),
+                    "experimental".to_string(),
+                ]);
+            },
+            AudioFormat::WAV => {
+                args.extend([
+                    "-ar".to_string(),  // Sample rate
+                    "44100".to_string(), // CD quality
+                ]);
+            }
+        }
+        args.extend(self.channel_args());
+        args.extend(self.silence_args());
         
-        let output_path = dir.join(suffix);
-        ffmpeg_utils::unique_path(output_path).display().to_string()
+        args
+    }
+    
+    /// Up- or downmix to the chosen channel layout. MP3 can't carry surround, so a
+    /// surround choice becomes stereo there rather than failing the encode.
+    fn channel_args(&self) -> Vec<String> {
+        let layout = if self.channel_layout.is_surround() && !self.audio_format.supports_surround() {
+            ChannelLayout::Stereo
+        } else {
+            self.channel_layout
+        };
+        let Some((channels, name)) = layout.layout() else {
+            return Vec::new();
+        };
+        vec![
+            "-ac".to_string(), channels.to_string(),
+            "-channel_layout".to_string(), name.to_string(),
+        ]
+    }
+    
+    /// Silence removal for audio-only outputs. Cutting audio under a video would put it
+    /// out of sync, so video outputs never get it.
+    fn silence_args(&self) -> Vec<String> {
+        if !self.silence_trim || !self.is_audio_output() {
+            return Vec::new();
+        }
+        let threshold = format!("{}dB", self.silence_threshold_db);
+        let filter = if self.silence_internal {
+            // The leading silence, then every gap of at least the minimum, the end included
+            format!(
+                "silenceremove=start_periods=1:start_threshold={t}:stop_periods=-1:stop_duration={d}:stop_threshold={t}",
+                t = threshold,
+                d = self.silence_min_secs,
+            )
+        } else {
+            // silenceremove only trims from the front, so the end is trimmed as the reversed start
+            let start = format!("silenceremove=start_periods=1:start_threshold={}", threshold);
+            format!("{s},areverse,{s},areverse", s = start)
+        };
+        vec!["-af".to_string(), filter]
     }
     
     pub fn update_command(&mut self) {
         // Always update the output path extension based on the selected format
+        self.output_ext_note = None;
         if !self.output_path.is_empty() {
-            let path = Path::new(&self.output_path);
+            let output_path = self.output_path.clone();
+            let path = Path::new(&output_path);
             
             // Get the parent directory and stem
             let dir = path.parent().unwrap_or_else(|| Path::new("."));
             let stem = path.file_stem().unwrap_or_default().to_string_lossy();
             
-            // Create a new path with the correct extension
-            let ext = match self.selected_function {
-                FunctionType::ExtractAudio => self.audio_format.ext(),
-                _ => self.output_format.ext(),
-            };
-            
-            let new_path = dir.join(format!("{}.{}", stem, ext));
+            // Create a new path with the correct extension, unless the user typed one
+            // we don't produce, which they most likely chose on purpose
+            let ext = self.output_ext_for(path);
             
-            // Check if the new path exists and make it unique if needed
-            let unique_path = if new_path.exists() {
-                ffmpeg_utils::unique_path(new_path)
-            } else {
-                new_path
-            };
-            
-            self.output_path = unique_path.display().to_string();
+            // Taken names are numbered when the job starts (resolve_output_path), not here
+            self.output_path = dir.join(format!("{}.{}", stem, ext)).display().to_string();
         } else {
             self.output_path = self.default_output();
         }
         
+        self.bpp_note = self.bits_per_pixel_note();
+        
         // Update the command
-        self.last_command = self.build_command().join(" ");
+        let args = self.build_command();
+        self.last_command = ffmpeg_utils::command_line(&args, cfg!(windows));
+        self.last_command_wrapped = ffmpeg_utils::wrapped_command_line(&args, cfg!(windows));
+        if self.pipe_active() && !self.pipe_command.trim().is_empty() {
+            self.last_command = format!("{} | {}", self.last_command, self.pipe_command.trim());
+            self.last_command_wrapped = format!("{}\n  | {}", self.last_command_wrapped, self.pipe_command.trim());
+        }
+    }
+    
+    /// Output frame size: the probed source's, or scaled to the chosen height keeping its aspect
+    pub fn output_resolution(&self) -> Option<(u32, u32)> {
+        let (width, height) = self.media_info.as_ref()
+            .filter(|_| self.probed_path == self.input_path)
+            .and_then(MediaInfo::resolution)?;
+        Some(match self.scale_height {
+            Some(scaled) if height > 0 => {
+                let scaled_width = (width as f32 * scaled as f32 / height as f32 / 2.0).round() as u32 * 2;
+                (scaled_width, scaled)
+            },
+            _ => (width, height),
+        })
+    }
+    
+    /// Bits per pixel for a bitrate-mode video encode at the output size and frame rate, rated.
+    /// When it's too low the note suggests a smaller size instead.
+    fn bits_per_pixel_note(&self) -> Option<(String, bool)> {
+        let encodes_video = self.selected_function == FunctionType::CompressVideo && !self.remove_video;
+        if !encodes_video || self.effective_quality_mode() != QualityMode::Bitrate {
+            return None;
+        }
+        let (width, height) = self.output_resolution()?;
+        let fps = if self.framerate_mode == FrameRateMode::CFR { self.frame_rate } else { self.original_fps };
+        if let Some(advice) = ffmpeg_utils::bitrate_advice(self.video_bitrate, width, height, fps) {
+            return Some((advice, true));
+        }
+        let bpp = ffmpeg_utils::bits_per_pixel(self.video_bitrate, width, height, fps);
+        Some((format!("Bits per pixel: {:.3} ({})", bpp, ffmpeg_utils::bits_per_pixel_rating(bpp)), false))
+    }
+    
+    /// Extension to use for the typed output `path`, noting any change or mismatch in `output_ext_note`
+    fn output_ext_for(&mut self, path: &Path) -> String {
+        let expected = self.output_ext();
+        let Some(typed) = path.extension().map(|e| e.to_string_lossy().to_lowercase()) else {
+            return expected.to_string();
+        };
+        if typed == expected {
+            return typed;
+        }
+        
+        let format_name = if self.selected_function == FunctionType::Spectrogram {
+            "PNG"
+        } else if self.is_audio_output() {
+            self.audio_format.display_name()
+        } else {
+            self.output_format.display_name()
+        };
+        let known = AudioFormat::all().iter().map(AudioFormat::ext)
+            .chain(AudioContainer::all().iter().map(|container| container.ext(self.audio_format)))
+            .chain(OutputFormat::all().iter().map(OutputFormat::ext))
+            .any(|ext| ext == typed);
+        
+        if known {
+            self.output_ext_note = Some(format!("Changed .{} to .{} to match the {} output", typed, expected, format_name));
+            expected.to_string()
+        } else {
+            if self.settings.force_format.trim().is_empty() {
+                self.output_ext_note = Some(format!(
+                    ".{} is not the usual extension for {} output, so FFmpeg may not recognise it or may pick a different container. Set Force format (-f) under Advanced to be sure.",
+                    typed, format_name
+                ));
+            }
+            typed
+        }
+    }
+    
+    /// Where the quick preview is written; overwritten by each preview
+    pub fn quick_preview_path(&self) -> PathBuf {
+        std::env::temp_dir().join(format!("ffmpeg_gui_preview.{}", self.output_ext()))
+    }
+    
+    /// The current command limited to the first few seconds and written to `output`
+    pub fn quick_preview_command(&self, output: &str) -> Vec<String> {
+        let mut args = self.build_command_with(&self.input_path, output);
+        
+        // Chapters are only generated right before a real encode, so the preview goes without
+        let metadata = chapters::metadata_path(Path::new(output)).display().to_string();
+        if let Some(i) = args.iter().position(|arg| *arg == metadata) {
+            args.drain(i - 1..=i);
+        }
+        if let Some(i) = args.iter().position(|arg| arg == "-map_chapters") {
+            args.drain(i..=i + 1);
+        }
+        
+        // Likewise the GIF palette; the preview generates it within the same run
+        if let Some(plan) = self.palette_plan() {
+            let palette = gif::palette_path(Path::new(output)).display().to_string();
+            if let Some(i) = args.iter().position(|arg| *arg == palette) {
+                args.drain(i - 1..=i);
+            }
+            if let Some(i) = args.iter().position(|arg| arg == "-filter_complex") {
+                args[i + 1] = plan.filter_graph(true);
+            }
+        }
+        
+        // Limit the duration just before the output file
+        let output = args.pop().unwrap_or_default();
+        args.extend(["-t".to_string(), QUICK_PREVIEW_SECS.to_string(), output]);
+        args
+    }
+    
+    /// Short video-only encode of the start of `input` with `encoder` at its default preset,
+    /// using the current quality settings translated to what the encoder supports
+    pub fn benchmark_args(&self, encoder: VideoEncoder, input: &str, output: &str, seconds: u32) -> Vec<String> {
+        let mut cmd = vec![
+            "-t".to_string(), seconds.to_string(),
+            "-i".to_string(), input.to_string(),
+            "-map".to_string(), "0:v:0".to_string(),
+            "-an".to_string(),
+            "-c:v".to_string(), encoder.codec().to_string(),
+        ];
+        
+        if self.effective_quality_mode() == QualityMode::Bitrate {
+            cmd.extend(encoder.bitrate_args(self.video_bitrate));
+        } else if encoder.quality_modes().contains(&QualityMode::Crf) {
+            cmd.extend(encoder.crf_args(self.crf.clamp(*encoder.crf_range().start(), *encoder.crf_range().end()), self.video_bitrate));
+        } else {
+            cmd.extend(encoder.cqp_args(self.qp.clamp(*encoder.qp_range().start(), *encoder.qp_range().end())));
+        }
+        
+        cmd.extend(encoder.preset_args(encoder.default_preset()));
+        cmd.extend(["-y".to_string(), output.to_string()]);
+        cmd
+    }
+    
+    /// Video-only encode of a segment of `input` with the current encoder and preset at
+    /// CRF `value` (QP for encoders without CRF), for the A/B comparison
+    pub fn ab_args(&self, value: u8, input: &str, output: &str) -> Vec<String> {
+        let mut cmd = vec![
+            "-ss".to_string(), ffmpeg_utils::format_timestamp(self.ab_start),
+            "-t".to_string(), self.ab_seconds.to_string(),
+            "-i".to_string(), input.to_string(),
+            "-map".to_string(), "0:v:0".to_string(),
+            "-an".to_string(),
+            "-c:v".to_string(), self.video_encoder.codec().to_string(),
+        ];
+        
+        if self.video_encoder.quality_modes().contains(&QualityMode::Crf) {
+            cmd.extend(self.video_encoder.crf_args(value, self.video_bitrate));
+        } else {
+            cmd.extend(self.video_encoder.cqp_args(value));
+        }
+        
+        cmd.extend(self.video_encoder.preset_args(&self.encoding_preset));
+        cmd.extend(["-y".to_string(), output.to_string()]);
+        cmd
     }
     
     pub fn build_command(&self) -> Vec<String> {
-        let input = self.input_path.clone();
-        let output = if self.output_path.is_empty() {
+        let output = if self.pipe_active() {
+            PIPE_OUTPUT.to_string()
+        } else if self.output_path.is_empty() {
             self.default_output()
         } else {
             self.output_path.clone()
         };
         
-        let mut cmd = vec!["-i".to_string(), input];
+        self.build_command_with(&self.input_path, &output)
+    }
+    
+    /// Build the FFmpeg arguments for an arbitrary input/output pair using the current settings
+    pub fn build_command_with(&self, input: &str, output: &str) -> Vec<String> {
+        self.build_command_scaled(input, output, self.scale_height)
+    }
+    
+    /// Build the command with an explicit output height, used for each rung of a resolution ladder
+    pub fn build_command_scaled(&self, input: &str, output: &str, scale_height: Option<u32>) -> Vec<String> {
+        self.build_command_trimmed(input, output, scale_height, self.trim_range())
+    }
+    
+    /// Build the command for an explicit (start, end) section of the input, used for each
+    /// chapter when splitting by chapters
+    pub fn build_command_trimmed(
+        &self,
+        input: &str,
+        output: &str,
+        scale_height: Option<u32>,
+        trim: Option<(Option<f64>, Option<f64>)>,
+    ) -> Vec<String> {
+        // Never read the terminal: a script looping over files would otherwise have its input eaten
+        let mut cmd = vec!["-nostdin".to_string()];
+        
+        // Global and input options have to come before the -i they apply to
+        cmd.extend(ffmpeg_utils::split_args(&self.settings.global_options).unwrap_or_default());
+        
+        // A still image has to be looped to last as long as the audio
+        if self.selected_function == FunctionType::ImageToVideo {
+            cmd.extend(["-loop".to_string(), "1".to_string()]);
+        }
+        // Trim as input options so FFmpeg seeks instead of decoding up to the start point.
+        // When re-encoding this is also frame-exact: FFmpeg jumps to the keyframe before the
+        // start, decodes from there and drops the frames ahead of it, so no two-stage
+        // -ss is needed. Stream copies can only start on a keyframe (see trim_snaps_to_keyframe).
+        cmd.extend(ffmpeg_utils::trim_args(trim));
+        let copy_video = self.copies_video(input, scale_height);
+        if copy_video {
+            cmd.extend(self.display_rotation_args(input));
+        }
+        cmd.extend(["-i".to_string(), input.to_string()]);
+        
+        // A cover image file is the second input
+        let cover_art = self.active_cover_art();
+        if cover_art == CoverArt::File {
+            cmd.extend(["-i".to_string(), self.cover_path.clone()]);
+        }
+        
+        // Generated chapters come in as a second, metadata-only input
+        let chapters = self.chapter_plan().is_some();
+        if chapters {
+            cmd.extend([
+                "-i".to_string(),
+                chapters::metadata_path(Path::new(output)).display().to_string(),
+            ]);
+        }
+        
+        // A GIF's palette, from the first pass, is the second input
+        let gif = self.gif_plan();
+        if gif.as_ref().is_some_and(|plan| plan.palette) {
+            cmd.extend(["-i".to_string(), gif::palette_path(Path::new(output)).display().to_string()]);
+        }
+        
+        // An external subtitle file to mux comes after any chapter metadata
+        let subtitle_mode = self.subtitle_mode_for(input);
+        let subtitle_input = if chapters { 2 } else { 1 };
+        if subtitle_mode == SubtitleMode::Mux {
+            cmd.extend(["-i".to_string(), self.subtitle_path.trim().to_string()]);
+        }
+        
+        // Replacement audio comes last, trimmed like the video so the two stay in sync
+        let replaces_audio = self.replaces_audio();
+        let audio_input = subtitle_input + usize::from(subtitle_mode == SubtitleMode::Mux);
+        if replaces_audio {
+            cmd.extend(ffmpeg_utils::trim_args(trim));
+            cmd.extend(["-i".to_string(), self.audio_input_path.clone()]);
+        }
         
         match self.selected_function {
             FunctionType::ExtractAudio => {
                 // Simple, direct approach for all audio formats
                 // Select audio stream only (no video)
+                cmd.push("-sn".to_string()); // No subtitles
+                match self.extract_track_for(input) {
+                    Some(track) => {
+                        let source = self.audio_tags.get(track).map_or(track, |tags| tags.source);
+                        cmd.extend(["-map".to_string(), format!("0:a:{}", source)]);
+                    },
+                    None if self.audio_reordered(input) => cmd.extend(self.ordered_maps(false)),
+                    None => cmd.extend(["-map".to_string(), "0:a".to_string()]), // Map only audio streams
+                }
+                
+                // Either no video at all, or a single picture flagged as album art
+                match cover_art {
+                    CoverArt::None => cmd.push("-vn".to_string()),
+                    CoverArt::FirstFrame => cmd.extend([
+                        "-map".to_string(), "0:v:0".to_string(),
+                        "-c:v".to_string(), "mjpeg".to_string(),
+                        "-frames:v".to_string(), "1".to_string(),
+                        "-disposition:v:0".to_string(), "attached_pic".to_string(),
+                    ]),
+                    CoverArt::File => cmd.extend([
+                        "-map".to_string(), "1".to_string(),
+                        "-c:v".to_string(), "copy".to_string(),
+                        "-disposition:v:0".to_string(), "attached_pic".to_string(),
+                    ]),
+                }
+                
                 cmd.extend([
-                    "-vn".to_string(),  // No video
-                    "-sn".to_string(), // No subtitles
-                    "-map".to_string(), "0:a".to_string(), // Map only audio streams
+                    "-c:a".to_string(),
+                    self.audio_codec().to_string(),
                 ]);
                 
                 // Add specific settings for each audio format
                 match self.audio_format {
-                    AudioFormat::MP3 => {
-                        cmd.extend([
-                            "-c:a".to_string(),
-                            "libmp3lame".to_string(),
-                        ]);
-                        
+                    AudioFormat::MP3 | AudioFormat::VORBIS => {
                         if self.use_audio_quality {
                             // Variable bitrate mode (VBR)
                             cmd.extend([
                                 "-q:a".to_string(),
-                                self.audio_quality.to_string(),
+                                self.vbr_quality().to_string(),
                             ]);
                         } else {
                             // Constant bitrate mode (CBR)
@@ -170,175 +2315,317 @@ impl MyApp {
                     },
                     AudioFormat::OPUS => {
                         cmd.extend([
-                            "-c:a".to_string(),
-                            "libopus".to_string(),
                             "-b:a".to_string(),
                             format!("{k}k", k = self.audio_bitrate),
                             "-strict".to_string(),
                             "experimental".to_string(),
                         ]);
+                        cmd.extend(self.cutoff_args());
                     },
                     AudioFormat::AAC => {
-                        cmd.extend([
-                            "-c:a".to_string(),
-                            "aac".to_string(),
-                            "-b:a".to_string(),
-                            format!("{k}k", k = self.audio_bitrate),
-                            "-strict".to_string(),
-                            "experimental".to_string(),
-                        ]);
+                        cmd.extend(self.aac_args());
                     },
                     AudioFormat::FLAC => {
                         cmd.extend([
-                            "-c:a".to_string(),
-                            "flac".to_string(),
                             "-compression_level".to_string(),
                             self.audio_quality.to_string(),
                         ]);
                     },
                     AudioFormat::WAV => {
                         cmd.extend([
-                            "-c:a".to_string(),
-                            "pcm_s16le".to_string(),
                             "-ar".to_string(),  // Sample rate
                             "44100".to_string(), // CD quality
                         ]);
                     }
                 }
+                cmd.extend(self.channel_args());
+                cmd.extend(self.silence_args());
                 
-                // Add output file
-                cmd.push("-y".to_string()); // Overwrite output file if it exists
-                cmd.push(output);
             },
             FunctionType::CompressVideo => {
-                // Ensure we map all streams to preserve them
-                cmd.extend([
-                    "-map".to_string(), "0".to_string(), // Map all streams from input
-                ]);
-                
-                // Video codec
-                cmd.extend([
-                    "-c:v".to_string(),
-                    "libx264".to_string(),
-                ]);
-                
-                // Video quality settings - CRF or bitrate
-                if self.use_crf && self.framerate_mode == FrameRateMode::CFR {
-                    // Constant Rate Factor mode
+                let custom_graph = self.custom_filter_graph();
+                if custom_graph.is_some() {
+                    // The usual -map 0 would add the source's video next to the graph's output.
+                    // Without maps of its own, FFmpeg takes the graph's outputs and picks the rest.
+                    cmd.extend(self.filter_map_args());
+                } else if self.remove_video && self.audio_reordered(input) {
+                    cmd.extend(self.ordered_maps(false));
+                    cmd.push("-vn".to_string());
+                } else if self.remove_video {
+                    // Audio-only output, so only the audio streams are mapped
                     cmd.extend([
-                        "-crf".to_string(),
-                        self.crf.to_string(),
+                        "-map".to_string(), "0:a".to_string(),
+                        "-vn".to_string(),
                     ]);
+                } else if !self.remove_audio && self.audio_reordered(input) {
+                    // Map stream by stream so the audio tracks come out in the chosen order
+                    cmd.extend(self.ordered_maps(true));
                 } else {
-                    // Bitrate mode
+                    // Ensure we map all streams to preserve them
                     cmd.extend([
-                        "-b:v".to_string(),
-                        format!("{k}k", k = self.video_bitrate),
+                        "-map".to_string(), "0".to_string(), // Map all streams from input
                     ]);
+                    if self.remove_audio {
+                        cmd.push("-an".to_string());
+                    }
+                }
+                if replaces_audio && custom_graph.is_none() {
+                    // With no audio of its own to mix under, the file's audio simply replaces it
+                    if self.mixes_audio() && self.input_has_audio(input) {
+                        cmd.extend(self.mixed_audio_args(audio_input));
+                    } else {
+                        cmd.extend(self.replaced_audio_maps(audio_input));
+                    }
                 }
                 
-                // Encoding preset
-                cmd.extend([
-                    "-preset".to_string(),
-                    self.encoding_preset.clone(),
-                ]);
-                
-                // Frame rate settings
-                if self.framerate_mode == FrameRateMode::CFR {
-                    // Set specific frame rate for CFR mode
+                if copy_video {
+                    // Nothing would change the pixels, so keep the stream as-is
+                    cmd.extend(["-c:v".to_string(), "copy".to_string()]);
+                    cmd.extend(self.rotation_metadata_args(input));
+                } else if !self.remove_video {
+                    // Video codec
                     cmd.extend([
-                        "-r".to_string(),
-                        format!("{:.3}", self.frame_rate),
+                        "-c:v".to_string(),
+                        self.video_encoder.codec().to_string(),
                     ]);
-                } else {
-                    // For VFR mode
-                    cmd.extend(["-vsync".to_string(), "vfr".to_string()]);
-                }
-                
-                // Audio settings - use the same approach as audio extraction for consistency
-                cmd.extend([
-                    "-c:a".to_string(),
-                ]);
+                    
+                    // Re-encoding anyway, so rotate the pixels rather than the metadata
+                    if let Some(graph) = custom_graph {
+                        cmd.extend(["-filter_complex".to_string(), graph.to_string()]);
+                    } else if let Some(filters) = self.video_filters(input, scale_height) {
+                        cmd.extend(["-vf".to_string(), filters]);
+                    }
                 
-                // Audio codec and quality settings based on format
-                match self.audio_format {
-                    AudioFormat::MP3 => {
-                        cmd.push("libmp3lame".to_string());
-                        
-                        if self.use_audio_quality {
-                            // Variable bitrate mode (VBR)
-                            cmd.extend([
-                                "-q:a".to_string(),
-                                self.audio_quality.to_string(),
-                            ]);
-                        } else {
-                            // Constant bitrate mode (CBR)
-                            cmd.extend([
-                                "-b:a".to_string(),
-                                format!("{k}k", k = self.audio_bitrate),
-                            ]);
+                    // Video quality settings - CRF, CQP or bitrate
+                    match self.effective_quality_mode() {
+                        QualityMode::Crf => {
+                            // Constant Rate Factor mode
+                            cmd.extend(self.video_encoder.crf_args(self.crf, self.video_bitrate));
+                        },
+                        QualityMode::Cqp => {
+                            // Constant quantizer, the hardware encoders' equivalent
+                            cmd.extend(self.video_encoder.cqp_args(self.qp));
+                        },
+                        QualityMode::Bitrate => {
+                            cmd.extend(self.video_encoder.bitrate_args(self.video_bitrate));
+                            
+                            // Strict CBR pins the rate for players and links that reject variation
+                            if self.strict_cbr {
+                                let bufsize = (self.video_bitrate as f32 * self.cbr_buffer_secs).round() as u32;
+                                cmd.extend([
+                                    "-minrate".to_string(), format!("{}k", self.video_bitrate),
+                                    "-maxrate".to_string(), format!("{}k", self.video_bitrate),
+                                    "-bufsize".to_string(), format!("{}k", bufsize),
+                                ]);
+                                cmd.extend(self.video_encoder.cbr_args());
+                            }
                         }
-                    },
-                    AudioFormat::OPUS => {
-                        cmd.extend([
-                            "libopus".to_string(),
-                            "-b:a".to_string(),
-                            format!("{k}k", k = self.audio_bitrate),
-                            "-compression_level".to_string(),
-                            self.audio_quality.to_string(),
-                            "-strict".to_string(),
-                            "experimental".to_string(),
-                        ]);
-                    },
-                    AudioFormat::AAC => {
-                        cmd.extend([
-                            "aac".to_string(),
-                            "-b:a".to_string(),
-                            format!("{k}k", k = self.audio_bitrate),
-                            "-strict".to_string(),
-                            "experimental".to_string(),
-                        ]);
-                    },
-                    AudioFormat::FLAC => {
-                        cmd.extend([
-                            "flac".to_string(),
-                            "-compression_level".to_string(),
-                            self.audio_quality.to_string(),
-                            "-strict".to_string(),
-                            "experimental".to_string(),
-                        ]);
-                    },
-                    AudioFormat::WAV => {
+                    }
+                
+                    if self.seek_friendly {
+                        cmd.extend(self.video_encoder.fixed_gop_args(SEEK_FRIENDLY_GOP));
+                    }
+                    
+                    // A keyframe right at each cut keeps the clip clean to edit or concatenate
+                    if let Some(times) = self.cut_keyframes() {
+                        cmd.extend(["-force_key_frames".to_string(), times]);
+                    }
+                
+                    // Tag the range too, or players guess, usually limited
+                    if let Some((range, _)) = self.output_color_range(input) {
+                        cmd.extend(["-color_range".to_string(), range.to_string()]);
+                    }
+                    
+                    // HDR10 has to stay 10-bit PQ, tagged the same way in the container
+                    if self.hdr10_params(input).is_some() {
                         cmd.extend([
-                            "pcm_s16le".to_string(),
-                            "-ar".to_string(),  // Sample rate
-                            "44100".to_string(), // CD quality
+                            "-pix_fmt".to_string(), "yuv420p10le".to_string(),
+                            "-color_primaries".to_string(), "bt2020".to_string(),
+                            "-color_trc".to_string(), "smpte2084".to_string(),
+                            "-colorspace".to_string(), "bt2020nc".to_string(),
                         ]);
                     }
+                    if self.video_encoder == VideoEncoder::X265 {
+                        cmd.extend(self.x265_params(input));
+                    }
+                
+                    // Encoding preset
+                    cmd.extend(self.video_encoder.preset_args(&self.encoding_preset));
+                
+                    // Frame rate settings
+                    match self.framerate_mode {
+                        // The fps filter is in the filter chain, unless a custom graph replaced it
+                        FrameRateMode::CFR if self.cfr_filter().is_some() => {}
+                        FrameRateMode::CFR => {
+                            cmd.extend(["-r".to_string(), self.cfr_rate()]);
+                            if self.cfr_method == CfrMethod::Vsync {
+                                cmd.extend(["-vsync".to_string(), "cfr".to_string()]);
+                            }
+                        },
+                        FrameRateMode::VFR => {
+                            cmd.extend(["-vsync".to_string(), "vfr".to_string()]);
+                        },
+                        // Frames pass through at the source's timing, none dropped or duplicated
+                        FrameRateMode::Source => {}
+                    }
                 }
                 
-                // Preserve subtitles if present
-                cmd.extend([
-                    "-c:s".to_string(),
-                    "copy".to_string(),
-                ]);
+                if !self.remove_audio {
+                    // Audio settings - use the same approach as audio extraction for consistency
+                    cmd.extend(self.audio_encode_args());
+                }
+                
+                if !self.remove_video && self.output_format.supports_subtitles() {
+                    // Preserve subtitles if present
+                    cmd.extend([
+                        "-c:s".to_string(),
+                        "copy".to_string(),
+                    ]);
+                } else if !self.remove_video && subtitle_mode == SubtitleMode::Mux {
+                    // Only the source's: -sn would drop the muxed file's subtitles as well
+                    cmd.extend(["-map".to_string(), "-0:s?".to_string(), "-dn".to_string()]);
+                } else if !self.remove_video {
+                    // The legacy web containers can't hold the source's subtitle or data streams
+                    cmd.extend(["-sn".to_string(), "-dn".to_string()]);
+                }
                 
-                // Add output file
-                cmd.push("-y".to_string()); // Overwrite output file if it exists
-                cmd.push(output);
             },
             FunctionType::ConvertToMp4 => {
                 // Map all streams to preserve them
+                if self.audio_reordered(input) {
+                    cmd.extend(self.ordered_maps(true));
+                } else {
+                    cmd.extend([
+                        "-map".to_string(), "0".to_string(), // Map all streams from input
+                    ]);
+                }
+                // Unwanted streams are mapped out again after that, so everything else is still copied
+                if self.remux_drop_audio {
+                    cmd.push("-an".to_string());
+                }
+                if self.remux_drop_subtitles {
+                    // Not -sn, which would also drop a subtitle file being muxed in
+                    cmd.extend(["-map".to_string(), "-0:s".to_string()]);
+                }
+                for index in self.remux_excluded_for(input) {
+                    cmd.extend(["-map".to_string(), format!("-0:{}", index)]);
+                }
+                if replaces_audio {
+                    cmd.extend(self.replaced_audio_maps(audio_input));
+                }
                 cmd.extend([
-                    "-map".to_string(), "0".to_string(), // Map all streams from input
                     "-c".to_string(),
                     "copy".to_string(),
-                    "-y".to_string(), // Overwrite output file if it exists
-                    output,
+                ]);
+                // Carry the source timestamps over unchanged, shifted so the output starts at zero
+                if self.copy_timestamps {
+                    cmd.extend([
+                        "-copyts".to_string(),
+                        "-avoid_negative_ts".to_string(), "make_zero".to_string(),
+                    ]);
+                }
+                cmd.extend(self.rotation_metadata_args(input));
+            },
+            FunctionType::ImageToVideo => {
+                // The audio file is the second input; it must come before any output options
+                cmd.extend([
+                    "-i".to_string(), self.audio_input_path.clone(),
+                    "-map".to_string(), "0:v".to_string(),
+                    "-map".to_string(), "1:a".to_string(),
+                    "-c:v".to_string(), "libx264".to_string(),
+                    "-tune".to_string(), "stillimage".to_string(),
+                    "-pix_fmt".to_string(), "yuv420p".to_string(), // Widest player compatibility
+                ]);
+                cmd.extend(self.audio_encode_args());
+            },
+            FunctionType::LoopClip => {
+                // Play the segment from `fade` in, then blend its end into its first `fade`
+                // seconds. The output stops where that opening part ends, which is exactly
+                // where its start picks up again, so it repeats without a visible seam.
+                let fade = self.loop_crossfade;
+                let length = self.loop_length(input, trim).unwrap_or_default();
+                let mut graph = format!(
+                    "[0:v]split[va][vb];\
+                     [va]trim=start={fade},setpts=PTS-STARTPTS[body];\
+                     [vb]trim=end={fade},setpts=PTS-STARTPTS[head];\
+                     [body][head]xfade=transition=fade:duration={fade}:offset={offset:.3},format=yuv420p[v]",
+                    fade = fade,
+                    offset = length - 2.0 * fade,
+                );
+                let has_audio = self.input_has_audio(input);
+                if has_audio {
+                    graph.push_str(&format!(
+                        ";[0:a]asplit[aa][ab];\
+                         [aa]atrim=start={fade},asetpts=PTS-STARTPTS[abody];\
+                         [ab]atrim=end={fade},asetpts=PTS-STARTPTS[ahead];\
+                         [abody][ahead]acrossfade=d={fade}[a]",
+                        fade = fade,
+                    ));
+                }
+                cmd.extend([
+                    "-filter_complex".to_string(), graph,
+                    "-map".to_string(), "[v]".to_string(),
+                    "-c:v".to_string(), "libx264".to_string(),
+                    "-crf".to_string(), self.crf.min(51).to_string(),
+                ]);
+                if has_audio {
+                    cmd.extend(["-map".to_string(), "[a]".to_string()]);
+                    cmd.extend(self.audio_encode_args());
+                }
+            },
+            FunctionType::Gif => {
+                if let Some(plan) = gif {
+                    cmd.extend(["-filter_complex".to_string(), plan.filter_graph(false)]);
+                }
+                // Loop forever, as GIFs on the web are expected to
+                cmd.extend(["-an".to_string(), "-loop".to_string(), "0".to_string()]);
+            },
+            FunctionType::Spectrogram => {
+                // A single picture of the whole (possibly trimmed) audio
+                cmd.extend([
+                    "-lavfi".to_string(),
+                    format!("showspectrumpic=s={}x{}", self.spectrogram_size[0], self.spectrogram_size[1]),
+                    "-frames:v".to_string(), "1".to_string(),
                 ]);
             }
         }
         
+        if subtitle_mode == SubtitleMode::Mux {
+            cmd.extend(self.subtitle_mux_args(subtitle_input));
+        }
+        
+        cmd.extend(self.audio_tag_args(input));
+        cmd.extend(self.disposition_args(input));
+        cmd.extend(self.creation_time_args());
+        cmd.extend(self.reset_timestamp_args());
+        
+        if chapters {
+            cmd.extend(["-map_chapters".to_string(), "1".to_string()]);
+        }
+        
+        // End with the shortest timed input; a looped image would otherwise run forever,
+        // and a replacement soundtrack longer than the video would leave a frozen last frame
+        if self.uses_audio_input() && self.shortest {
+            cmd.push("-shortest".to_string());
+        }
+        
+        cmd.extend(ffmpeg_utils::split_args(&self.extra_options).unwrap_or_default());
+        
+        // Force the muxer when the extension alone doesn't identify it
+        let force_format = self.settings.force_format.trim();
+        if !force_format.is_empty() {
+            cmd.extend([
+                "-f".to_string(),
+                force_format.to_string(),
+            ]);
+        } else if output == PIPE_OUTPUT {
+            cmd.extend(self.pipe_format_args());
+        }
+        
+        // Add output file
+        cmd.push("-y".to_string()); // Overwrite output file if it exists
+        cmd.push(output.to_string());
+        
         cmd
     }
 }
@@ -0,0 +1,185 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::chapters::{self, ChapterPlan};
+use crate::ffmpeg_utils;
+use crate::gif::{self, GifPlan};
+use crate::presets::EncodeSettings;
+
+const STATE_FILE: &str = "batch_state.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ItemStatus {
+    Pending,
+    Running,
+    Done,
+    Failed,
+}
+
+impl ItemStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Pending => "Pending",
+            Self::Running => "Running",
+            Self::Done => "Done",
+            Self::Failed => "Failed",
+        }
+    }
+}
+
+/// A single file in a batch along with the exact arguments it will be encoded with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchItem {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub args: Vec<String>,        // Full FFmpeg arguments, writing to the temp output
+    pub status: ItemStatus,
+    #[serde(default)]
+    pub chapters: Option<ChapterPlan>, // Chapters to generate before encoding, if any
+    #[serde(default)]
+    pub palette: Option<GifPlan>, // GIF palette to generate before encoding, if any
+    #[serde(default)]
+    pub duration: Option<f64>,    // Output length when only part of the input is encoded, for progress
+    #[serde(default)]
+    pub queue_index: Option<usize>, // Position in the batch queue of the file it came from
+}
+
+impl BatchItem {
+    /// Path FFmpeg writes to while encoding; renamed to `output` on success
+    pub fn temp_output(&self) -> PathBuf {
+        ffmpeg_utils::temp_output_path(&self.output)
+    }
+}
+
+/// A file queued together with its own copy of the encode settings, so one source can be
+/// encoded several different ways in one run. The command is built when the job is added,
+/// so the trim, crop, audio and everything else loaded for that input at the time go with it.
+#[derive(Debug, Clone)]
+pub struct SettingsJob {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub settings: EncodeSettings,       // For showing and loading the job's settings again
+    pub args: Vec<String>,              // Full FFmpeg arguments, writing to the temp output of `output`
+    pub chapters: Option<ChapterPlan>,
+    pub palette: Option<GifPlan>,
+    pub duration: Option<f64>,
+}
+
+impl SettingsJob {
+    /// The batch item running this job into `output`, which an existing file may have made
+    /// different from the job's own. The files named after the output are renamed along.
+    pub fn batch_item(&self, output: PathBuf) -> BatchItem {
+        let (from, to) = (ffmpeg_utils::temp_output_path(&self.output), ffmpeg_utils::temp_output_path(&output));
+        let renames = [
+            (chapters::metadata_path(&from), chapters::metadata_path(&to)),
+            (gif::palette_path(&from), gif::palette_path(&to)),
+            (from, to),
+        ].map(|(from, to)| (from.display().to_string(), to.display().to_string()));
+        let args = self.args
+            .iter()
+            .map(|arg| renames.iter().find(|(from, _)| from == arg).map_or_else(|| arg.clone(), |(_, to)| to.clone()))
+            .collect();
+        BatchItem {
+            input: self.input.clone(),
+            output,
+            args,
+            status: ItemStatus::Pending,
+            chapters: self.chapters,
+            palette: self.palette.clone(),
+            duration: self.duration,
+            queue_index: None,
+        }
+    }
+}
+
+/// A batch run whose progress is persisted to disk so it can be resumed after a crash
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchJob {
+    pub items: Vec<BatchItem>,
+}
+
+impl BatchJob {
+    fn state_path() -> PathBuf {
+        ffmpeg_utils::app_file(STATE_FILE)
+    }
+
+    /// Load an unfinished batch left behind by a previous session
+    pub fn load_unfinished() -> Option<BatchJob> {
+        let data = std::fs::read_to_string(Self::state_path()).ok()?;
+        let mut job: BatchJob = serde_json::from_str(&data).ok()?;
+        job.reconcile();
+        if job.is_finished() { None } else { Some(job) }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        ffmpeg_utils::write_atomic(&Self::state_path(), &serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn discard() {
+        let _ = std::fs::remove_file(Self::state_path());
+    }
+
+    /// Bring statuses in line with what is on disk: an item that was mid-encode
+    /// when the app died goes back to pending, and a finished output counts as done
+    /// (temp outputs are never mistaken for finished ones since they use a different name)
+    pub fn reconcile(&mut self) {
+        for item in &mut self.items {
+            if item.status == ItemStatus::Running {
+                item.status = ItemStatus::Pending;
+            }
+            if item.status == ItemStatus::Pending && item.output.exists() {
+                item.status = ItemStatus::Done;
+            }
+        }
+    }
+
+    /// Whether every item planned for the file at `index` in the queue was finished
+    pub fn queued_done(&self, index: usize) -> bool {
+        let mut items = self.items.iter().filter(|item| item.queue_index == Some(index)).peekable();
+        items.peek().is_some() && items.all(|item| item.status == ItemStatus::Done)
+    }
+    
+    /// Status to show for the file at `index` in the queue: its running or first unfinished
+    /// item's, or Done once all are
+    pub fn queued_status(&self, index: usize) -> Option<ItemStatus> {
+        let statuses: Vec<ItemStatus> = self.items.iter()
+            .filter(|item| item.queue_index == Some(index))
+            .map(|item| item.status)
+            .collect();
+        [ItemStatus::Running, ItemStatus::Failed, ItemStatus::Pending]
+            .into_iter()
+            .find(|status| statuses.contains(status))
+            .or_else(|| statuses.first().copied())
+    }
+    
+    /// Follow the queue being edited: `new_index` gives each file's new position, or None
+    /// for a file taken out
+    pub fn move_queued(&mut self, new_index: impl Fn(usize) -> Option<usize>) {
+        for item in &mut self.items {
+            item.queue_index = item.queue_index.and_then(&new_index);
+        }
+    }
+    
+    /// The queued files the batch was planned from, in queue order
+    pub fn queued_inputs(&self) -> Vec<PathBuf> {
+        let mut inputs: Vec<(usize, PathBuf)> = Vec::new();
+        for item in &self.items {
+            if let Some(index) = item.queue_index
+                && !inputs.iter().any(|(existing, _)| *existing == index)
+            {
+                inputs.push((index, item.input.clone()));
+            }
+        }
+        inputs.sort_by_key(|(index, _)| *index);
+        inputs.into_iter().map(|(_, input)| input).collect()
+    }
+    
+    pub fn completed(&self) -> usize {
+        self.items.iter().filter(|i| i.status == ItemStatus::Done).count()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.items.iter().all(|i| matches!(i.status, ItemStatus::Done | ItemStatus::Failed))
+    }
+}
@@ -0,0 +1,86 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use crate::enums::VideoEncoder;
+
+/// Outcome of one short benchmark encode
+#[derive(Debug, Clone)]
+pub struct BenchmarkResult {
+    pub encoder: VideoEncoder,
+    pub preset: String,
+    pub elapsed: Duration,
+    pub size: Option<u64>,          // Output size in bytes, None if the encode failed
+    pub psnr: Option<f32>,          // Average PSNR against the source in dB, higher is better
+}
+
+/// Outcome of one encode in a CRF (or QP, for hardware encoders) A/B comparison
+#[derive(Debug, Clone)]
+pub struct AbResult {
+    pub value: u8,
+    pub elapsed: Duration,
+    pub size: Option<u64>,          // Output size in bytes, None if the encode failed
+    pub psnr: Option<f32>,
+    pub ssim: Option<f32>,          // Overall SSIM, 1.0 is identical
+}
+
+/// Where a benchmark encode is written; one file per encoder, overwritten on every run.
+/// Matroska, since it's the one container every encoder's output fits in.
+pub fn temp_output(encoder: VideoEncoder) -> PathBuf {
+    std::env::temp_dir().join(format!("ffmpeg_gui_bench_{}.mkv", encoder.codec()))
+}
+
+/// Where an A/B encode at `value` is written, overwritten on every run
+pub fn ab_temp_output(value: u8) -> PathBuf {
+    std::env::temp_dir().join(format!("ffmpeg_gui_ab_{}.mkv", value))
+}
+
+/// Compare `output` with the source using FFmpeg's psnr filter. `segment` is the
+/// (start, length) in seconds of the source that `output` was encoded from, if not all of it.
+pub fn measure_psnr(output: &Path, source: &Path, segment: Option<(f64, f64)>) -> Option<f32> {
+    // The summary line looks like "[Parsed_psnr_1 @ ...] PSNR y:41.2 u:45.0 v:45.3 average:42.10 min:..."
+    let stderr = compare_with_source(output, source, segment, "psnr")?;
+    summary_value(&stderr, "PSNR", "average:")
+}
+
+/// Like `measure_psnr`, using the ssim filter
+pub fn measure_ssim(output: &Path, source: &Path, segment: Option<(f64, f64)>) -> Option<f32> {
+    // The summary line looks like "[Parsed_ssim_1 @ ...] SSIM Y:0.98 U:0.99 V:0.99 All:0.985 (18.2)"
+    let stderr = compare_with_source(output, source, segment, "ssim")?;
+    summary_value(&stderr, "SSIM", "All:")
+}
+
+/// Like `measure_psnr`, using the libvmaf filter, which only some FFmpeg builds include
+pub fn measure_vmaf(output: &Path, source: &Path, segment: Option<(f64, f64)>) -> Option<f32> {
+    // The summary line looks like "[Parsed_libvmaf_1 @ ...] VMAF score: 95.123456"
+    let stderr = compare_with_source(output, source, segment, "libvmaf")?;
+    summary_value(&stderr, "VMAF score", "VMAF score:")
+}
+
+/// Run a comparison filter over `output` and the matching part of the source, returning
+/// FFmpeg's log. The output is scaled to the source's size first, so a downscaled encode
+/// is judged against the full-resolution original.
+fn compare_with_source(output: &Path, source: &Path, segment: Option<(f64, f64)>, filter: &str) -> Option<String> {
+    let mut command = Command::new("ffmpeg");
+    command.arg("-hide_banner");
+    if let Some((_, length)) = segment {
+        command.args(["-t", &length.to_string()]);
+    }
+    command.arg("-i").arg(output);
+    if let Some((start, length)) = segment {
+        command.args(["-ss", &start.to_string(), "-t", &length.to_string()]);
+    }
+    let result = command
+        .arg("-i")
+        .arg(source)
+        .args(["-lavfi", &format!("[0:v][1:v]scale2ref[out][ref];[out][ref]{}", filter), "-f", "null", "-"])
+        .output()
+        .ok()?;
+    Some(String::from_utf8_lossy(&result.stderr).into_owned())
+}
+
+fn summary_value(stderr: &str, marker: &str, key: &str) -> Option<f32> {
+    let line = stderr.lines().rev().find(|line| line.contains(marker))?;
+    let start = line.find(key)? + key.len();
+    line[start..].split_whitespace().next()?.parse().ok()
+}
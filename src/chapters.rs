@@ -0,0 +1,159 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::enums::{ChapterMode, TrimUnit};
+use crate::ffmpeg_utils;
+
+/// Scene cuts closer together than this are merged into one chapter
+const MIN_SCENE_CHAPTER_SECS: f64 = 10.0;
+
+/// How chapters are generated for an encode, resolved from the UI when the job is built
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ChapterPlan {
+    pub mode: ChapterMode,
+    pub interval_secs: f64,
+    pub scene_threshold: f32,
+    #[serde(default)]
+    pub trim: Option<(Option<f64>, Option<f64>)>, // The same section the encode reads
+}
+
+/// FFmetadata file carrying the chapters for an output, passed to FFmpeg as a second input
+pub fn metadata_path(output: &Path) -> PathBuf {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    std::env::temp_dir().join(format!("{}.chapters.txt", stem))
+}
+
+impl ChapterPlan {
+    /// A pass logging the frames of the encoded section that the scene score puts above the
+    /// threshold. Seeking before the input starts its times at zero, as in the output.
+    fn scene_args(&self, input: &Path) -> Vec<String> {
+        let mut args = vec!["-nostdin".to_string()];
+        args.extend(ffmpeg_utils::trim_args(self.trim));
+        args.extend([
+            "-i".to_string(), input.display().to_string(),
+            "-an".to_string(),
+            "-vf".to_string(), format!("select='gt(scene,{})',showinfo", self.scene_threshold),
+            "-f".to_string(), "null".to_string(), "-".to_string(),
+        ]);
+        args
+    }
+    
+    /// Work out the chapter start times for `input` and write them to the metadata file
+    /// for `output`. Returns the number of chapters written. `run` runs an FFmpeg pass
+    /// and returns its log.
+    pub fn prepare(
+        &self,
+        input: &Path,
+        output: &Path,
+        duration: f64,
+        run: impl FnOnce(&[String]) -> anyhow::Result<String>,
+    ) -> anyhow::Result<usize> {
+        let starts = match self.mode {
+            ChapterMode::Off => return Ok(0),
+            ChapterMode::Interval => interval_starts(duration, self.interval_secs),
+            ChapterMode::Scene => {
+                let mut starts = scene_starts(&run(&self.scene_args(input))?);
+                starts.retain(|&start| start < duration);
+                starts
+            },
+        };
+        write_ffmetadata(&metadata_path(output), &starts, duration)?;
+        Ok(starts.len())
+    }
+}
+
+/// Chapter starts every `interval` seconds, beginning at zero
+fn interval_starts(duration: f64, interval: f64) -> Vec<f64> {
+    let interval = interval.max(1.0);
+    (0..)
+        .map(|i| i as f64 * interval)
+        .take_while(|&t| t < duration)
+        .collect()
+}
+
+
+/// Chapter starts at the scene changes in the log of the `scene_args` pass
+fn scene_starts(stderr: &str) -> Vec<f64> {
+    let mut starts = vec![0.0];
+    for line in stderr.lines().filter(|line| line.contains("Parsed_showinfo")) {
+        let Some(time) = line
+            .split_once("pts_time:")
+            .and_then(|(_, rest)| rest.split_whitespace().next())
+            .and_then(|t| t.parse::<f64>().ok())
+        else {
+            continue;
+        };
+        if time - starts[starts.len() - 1] >= MIN_SCENE_CHAPTER_SECS {
+            starts.push(time);
+        }
+    }
+    starts
+}
+
+fn write_ffmetadata(path: &Path, starts: &[f64], duration: f64) -> std::io::Result<()> {
+    let mut text = String::from(";FFMETADATA1\n");
+    for (i, &start) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).copied().unwrap_or(duration);
+        text.push_str(&format!(
+            "\n[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle=Chapter {}\n",
+            (start * 1000.0) as u64,
+            (end * 1000.0) as u64,
+            i + 1
+        ));
+    }
+    std::fs::write(path, text)
+}
+
+/// Track starts and titles read from a cue sheet or a timestamp list, for splitting a
+/// recording into one file per track
+pub fn parse_split_points(text: &str) -> anyhow::Result<Vec<(f64, Option<String>)>> {
+    let points = if text.lines().any(|line| line.trim_start().starts_with("TRACK ")) {
+        parse_cue(text)
+    } else {
+        parse_timestamp_list(text)
+    };
+    if points.is_empty() {
+        anyhow::bail!("no track start times found");
+    }
+    if points.windows(2).any(|pair| pair[1].0 <= pair[0].0) {
+        anyhow::bail!("track start times must be in increasing order");
+    }
+    Ok(points)
+}
+
+/// Each TRACK's INDEX 01 (MM:SS:FF, 75 frames a second) and TITLE
+fn parse_cue(text: &str) -> Vec<(f64, Option<String>)> {
+    let mut points: Vec<(f64, Option<String>)> = Vec::new();
+    let mut title = None;
+    let mut in_track = false;
+    for line in text.lines().map(str::trim) {
+        if line.starts_with("TRACK ") {
+            in_track = true;
+            title = None;
+        } else if in_track && let Some(value) = line.strip_prefix("TITLE ") {
+            title = Some(value.trim().trim_matches('"').to_string()).filter(|t| !t.is_empty());
+        } else if in_track && let Some(time) = line.strip_prefix("INDEX 01 ") {
+            let parts: Vec<f64> = time.trim().split(':').filter_map(|p| p.parse().ok()).collect();
+            if let [minutes, seconds, frames] = parts[..] {
+                points.push((minutes * 60.0 + seconds + frames / 75.0, title.take()));
+                in_track = false;
+            }
+        }
+    }
+    points
+}
+
+/// Lines like "12:34 Title", "1:02:03 - Title" or "[12:34] Title"; lines without a
+/// leading time are skipped
+fn parse_timestamp_list(text: &str) -> Vec<(f64, Option<String>)> {
+    text.lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let (time, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let time = time.trim_start_matches(['[', '(']).trim_end_matches([']', ')']);
+            let start = ffmpeg_utils::parse_trim_point(time, TrimUnit::Timecode, 0.0)?;
+            let title = rest.trim().trim_start_matches(['-', '–', '—', ':', '|']).trim();
+            Some((start, (!title.is_empty()).then(|| title.to_string())))
+        })
+        .collect()
+}
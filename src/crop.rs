@@ -0,0 +1,127 @@
+use std::cmp::Reverse;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use parking_lot::RwLock;
+
+use anyhow::{Context, bail};
+
+/// The part of the frame to keep, in source pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crop {
+    pub width: u32,
+    pub height: u32,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl Crop {
+    pub fn filter(&self) -> String {
+        format!("crop={}:{}:{}:{}", self.width, self.height, self.x, self.y)
+    }
+
+    pub fn label(&self) -> String {
+        format!("{}×{} at {},{}", self.width, self.height, self.x, self.y)
+    }
+
+    /// The smallest rectangle holding both, so nothing either of them keeps is cut off
+    pub fn union(&self, other: &Crop) -> Crop {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Crop {
+            width: (self.x + self.width).max(other.x + other.width) - x,
+            height: (self.y + self.height).max(other.y + other.height) - y,
+            x,
+            y,
+        }
+    }
+}
+
+/// How often cropdetect suggested each crop, most common first
+#[derive(Debug, Clone, Default)]
+pub struct CropTally {
+    pub counts: Vec<(Crop, u32)>,
+}
+
+impl CropTally {
+    pub fn add(&mut self, crop: Crop) {
+        match self.counts.iter_mut().find(|(seen, _)| *seen == crop) {
+            Some((_, count)) => *count += 1,
+            None => self.counts.push((crop, 1)),
+        }
+        // Stable, so ties keep the order they were first seen in
+        self.counts.sort_by_key(|(_, count)| Reverse(*count));
+    }
+
+    pub fn total(&self) -> u32 {
+        self.counts.iter().map(|(_, count)| count).sum()
+    }
+
+    pub fn most_common(&self) -> Option<Crop> {
+        self.counts.first().map(|(crop, _)| *crop)
+    }
+
+    /// Every suggestion combined: a dark scene that looks like all bars can't cut into the picture
+    pub fn union(&self) -> Option<Crop> {
+        self.counts.iter().map(|(crop, _)| *crop).reduce(|a, b| a.union(&b))
+    }
+}
+
+/// Read the suggestion and timestamp off a cropdetect log line, e.g.
+/// `[Parsed_cropdetect_0 @ 0x...] x1:0 x2:1919 y1:140 y2:939 w:1920 h:800 x:0 y:140 pts:... t:12.5 ... crop=1920:800:0:140`
+fn parse_line(line: &str) -> Option<(Crop, Option<f64>)> {
+    let mut crop = None;
+    let mut time = None;
+    for token in line.split_whitespace() {
+        if let Some(value) = token.strip_prefix("crop=") {
+            // A frame with nothing but black gives negative sizes, which don't parse
+            let parts: Vec<u32> = value.split(':').map(str::parse).collect::<Result<_, _>>().ok()?;
+            let &[width, height, x, y] = parts.as_slice() else {
+                return None;
+            };
+            crop = Some(Crop { width, height, x, y });
+        } else if let Some(value) = token.strip_prefix("t:") {
+            time = value.parse().ok();
+        }
+    }
+    crop.filter(|crop| crop.width > 0 && crop.height > 0).map(|crop| (crop, time))
+}
+
+/// Run cropdetect over the keyframes of `path`, adding each suggestion to `tally` as it
+/// comes in. Returns false if cancelled.
+pub fn detect(path: &Path, duration: Option<f64>, tally: &RwLock<CropTally>, progress: &RwLock<f32>, cancel: &AtomicBool) -> anyhow::Result<bool> {
+    // Keyframes alone sample the whole file in a fraction of a full decode, and reset=1
+    // makes every suggestion about its own frame rather than everything seen so far
+    let mut child = Command::new("ffmpeg")
+        .args(["-nostdin", "-hide_banner", "-nostats", "-skip_frame", "nokey", "-i"])
+        .arg(path)
+        .args(["-map", "0:v:0", "-vf", "cropdetect=round=2:reset=1", "-f", "null", "-"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("could not run ffmpeg")?;
+
+    let stderr = child.stderr.take().context("no ffmpeg output")?;
+    for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+        if cancel.load(Ordering::Relaxed) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(false);
+        }
+        let Some((crop, time)) = parse_line(&line) else {
+            continue;
+        };
+        tally.write().add(crop);
+        if let (Some(time), Some(duration)) = (time, duration) {
+            *progress.write() = (time / duration).clamp(0.0, 1.0) as f32;
+        }
+    }
+
+    let status = child.wait()?;
+    if !status.success() {
+        bail!("FFmpeg could not decode the video ({})", status);
+    }
+    Ok(true)
+}
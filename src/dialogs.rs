@@ -0,0 +1,79 @@
+use eframe::egui;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+/// Which field a file dialog fills in once it returns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DialogTarget {
+    Input,
+    AudioInput,
+    Output,
+    Cover,
+    Subtitle,
+    SplitPoints,
+    BatchFiles,
+    BatchScript,
+}
+
+impl DialogTarget {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Input => "input file",
+            Self::AudioInput => "audio file",
+            Self::Output => "output file",
+            Self::Cover => "cover image",
+            Self::Subtitle => "subtitle file",
+            Self::SplitPoints => "track list",
+            Self::BatchFiles => "batch files",
+            Self::BatchScript => "batch script",
+        }
+    }
+}
+
+pub enum DialogOutcome {
+    Picked(Vec<PathBuf>),
+    Cancelled,
+    Failed(String),
+}
+
+/// A native file dialog running on its own thread, so a dialog that never shows up
+/// (e.g. over remote X without a portal) can't freeze the window
+pub struct PendingDialog {
+    pub target: DialogTarget,
+    receiver: Receiver<DialogOutcome>,
+}
+
+impl PendingDialog {
+    /// Run `show` (which builds and shows an `rfd::FileDialog`) in the background
+    pub fn open<F>(target: DialogTarget, ctx: egui::Context, show: F) -> Self
+    where
+        F: FnOnce() -> Option<Vec<PathBuf>> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let outcome = match std::panic::catch_unwind(std::panic::AssertUnwindSafe(show)) {
+                Ok(Some(paths)) => DialogOutcome::Picked(paths),
+                Ok(None) => DialogOutcome::Cancelled,
+                Err(_) => DialogOutcome::Failed("the dialog crashed".to_string()),
+            };
+            let _ = sender.send(outcome);
+            ctx.request_repaint();
+        });
+        Self { target, receiver }
+    }
+
+    /// The dialog's result, once it has closed
+    pub fn poll(&self) -> Option<DialogOutcome> {
+        match self.receiver.try_recv() {
+            Ok(outcome) => Some(outcome),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(DialogOutcome::Failed("the dialog closed without a result".to_string())),
+        }
+    }
+}
+
+/// Why no native dialog can be shown here, if that's known up front
+pub fn unavailable_reason() -> Option<&'static str> {
+    let no_display = std::env::var_os("DISPLAY").is_none() && std::env::var_os("WAYLAND_DISPLAY").is_none();
+    (cfg!(all(unix, not(target_os = "macos"))) && no_display).then_some("no X11 or Wayland display found")
+}
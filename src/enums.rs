@@ -1,20 +1,48 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum FunctionType { 
+    #[default]
     ExtractAudio, 
     CompressVideo, 
-    ConvertToMp4 
-}
-
-impl Default for FunctionType { 
-    fn default() -> Self { FunctionType::ExtractAudio } 
+    ConvertToMp4,
+    ImageToVideo,
+    Spectrogram,
+    LoopClip,
+    Gif,
 }
 
 impl FunctionType { 
+    pub fn all() -> [FunctionType; 7] {
+        [
+            FunctionType::ExtractAudio,
+            FunctionType::CompressVideo,
+            FunctionType::ConvertToMp4,
+            FunctionType::ImageToVideo,
+            FunctionType::Spectrogram,
+            FunctionType::LoopClip,
+            FunctionType::Gif,
+        ]
+    }
+    
     /// Check if audio options should be shown for this function type
     pub fn show_audio_options(&self) -> bool {
-        matches!(self, Self::ExtractAudio | Self::CompressVideo)
+        matches!(self, Self::ExtractAudio | Self::CompressVideo | Self::ImageToVideo | Self::LoopClip)
+    }
+    
+    /// Whether the function takes a separate audio file as a second input
+    pub fn needs_audio_input(&self) -> bool {
+        matches!(self, Self::ImageToVideo)
+    }
+
+    /// Functions that only make sense for an input with a video stream
+    pub fn needs_video_stream(&self) -> bool {
+        matches!(self, Self::CompressVideo | Self::ConvertToMp4 | Self::LoopClip | Self::Gif)
+    }
+    
+    /// Functions that work on the input's own audio
+    pub fn needs_audio_stream(&self) -> bool {
+        matches!(self, Self::ExtractAudio | Self::Spectrogram)
     }
 
     /// Check if video options should be shown for this function type
@@ -24,7 +52,33 @@ impl FunctionType {
 
     /// Check if output format selection should be shown
     pub fn show_output_format(&self) -> bool {
-        !matches!(self, Self::ExtractAudio)
+        !matches!(self, Self::ExtractAudio | Self::Spectrogram | Self::Gif)
+    }
+    
+    /// Functions whose input can be trimmed; a looped still image has no timeline to cut
+    pub fn supports_trim(&self) -> bool {
+        !matches!(self, Self::ImageToVideo)
+    }
+    
+    /// Functions that can take their audio from a separate file instead of the input's own
+    pub fn supports_audio_replacement(&self) -> bool {
+        matches!(self, Self::CompressVideo | Self::ConvertToMp4)
+    }
+    
+    /// Functions that can split the input into one file per track: audio extraction, and
+    /// remuxing for a stream copy of each section
+    pub fn supports_splitting(&self) -> bool {
+        matches!(self, Self::ExtractAudio | Self::ConvertToMp4)
+    }
+    
+    /// Functions that can write generated chapters into the output
+    pub fn supports_chapters(&self) -> bool {
+        matches!(self, Self::CompressVideo | Self::ConvertToMp4)
+    }
+    
+    /// Functions whose video output can be rotated
+    pub fn show_rotation(&self) -> bool {
+        matches!(self, Self::CompressVideo | Self::ConvertToMp4)
     }
     
     pub fn description(&self) -> &'static str { 
@@ -32,24 +86,25 @@ impl FunctionType {
             Self::ExtractAudio => "Extract audio from video file.", 
             Self::CompressVideo => "Compress video with advanced options.", 
             Self::ConvertToMp4 => "Convert video to MP4/MKV without re-encoding.", 
+            Self::ImageToVideo => "Make a video from a still image and an audio file.",
+            Self::Spectrogram => "Render a spectrogram of the audio as a PNG image.",
+            Self::LoopClip => "Make a seamlessly looping clip by crossfading its end into its start.",
+            Self::Gif => "Make an animated GIF from a video clip.",
         } 
     }
 }
 
 /// Supported audio formats with their file extensions and FFmpeg codecs
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum AudioFormat {
+    #[default]
     MP3,
     WAV,
     FLAC,
     AAC,
     OPUS,
-}
-
-impl Default for AudioFormat {
-    fn default() -> Self {
-        Self::MP3
-    }
+    VORBIS,
 }
 
 impl AudioFormat {
@@ -61,6 +116,20 @@ impl AudioFormat {
             Self::FLAC => "flac",
             Self::AAC => "m4a",  // AAC is commonly stored in .m4a containers
             Self::OPUS => "opus",
+            Self::VORBIS => "ogg",
+        }
+    }
+    
+    /// FFmpeg muxer that streams the codec on its own. AAC goes out as raw ADTS frames,
+    /// since the .m4a container has to seek back to write its index.
+    pub fn stream_muxer(&self) -> &'static str {
+        match self {
+            Self::MP3 => "mp3",
+            Self::WAV => "wav",
+            Self::FLAC => "flac",
+            Self::AAC => "adts",
+            Self::OPUS => "opus",
+            Self::VORBIS => "ogg",
         }
     }
     
@@ -72,9 +141,20 @@ impl AudioFormat {
             Self::FLAC => "flac",
             Self::AAC => "aac",
             Self::OPUS => "libopus",
+            Self::VORBIS => "libvorbis",
         }
     }
     
+    /// Containers that can carry an attached cover picture
+    pub fn supports_cover_art(&self) -> bool {
+        matches!(self, Self::MP3 | Self::FLAC | Self::AAC)
+    }
+    
+    /// MP3 tops out at two channels
+    pub fn supports_surround(&self) -> bool {
+        !matches!(self, Self::MP3)
+    }
+    
     /// Get a display name for this audio format
     pub fn display_name(&self) -> &'static str {
         match self {
@@ -83,43 +163,791 @@ impl AudioFormat {
             Self::FLAC => "FLAC",
             Self::AAC => "AAC",
             Self::OPUS => "Opus",
+            Self::VORBIS => "Vorbis",
         }
     }
     
-    pub fn all() -> [AudioFormat; 5] {
+    pub fn all() -> [AudioFormat; 6] {
         [
             AudioFormat::MP3,
             AudioFormat::WAV,
             AudioFormat::FLAC,
             AudioFormat::AAC,
             AudioFormat::OPUS,
+            AudioFormat::VORBIS,
         ]
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum FrameRateMode { CFR, VFR }
-impl Default for FrameRateMode { fn default() -> Self { FrameRateMode::CFR } }
+/// Container for extracted audio, chosen separately from the codec
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AudioContainer {
+    #[default]
+    Native,     // The codec's usual file type, e.g. .opus for Opus
+    Matroska,
+    Ogg,
+    Mp4,
+}
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum OutputFormat { Mp4, Mkv }
-impl Default for OutputFormat { fn default() -> Self { OutputFormat::Mp4 } }
+impl AudioContainer {
+    pub fn ext(&self, format: AudioFormat) -> &'static str {
+        match self {
+            Self::Native => format.ext(),
+            Self::Matroska => "mka",
+            Self::Ogg => "ogg",
+            Self::Mp4 => "m4a",
+        }
+    }
+    
+    /// Whether this container can carry the given codec
+    pub fn supports(&self, format: AudioFormat) -> bool {
+        match self {
+            Self::Native | Self::Matroska => true,
+            Self::Ogg => matches!(format, AudioFormat::FLAC | AudioFormat::OPUS | AudioFormat::VORBIS),
+            Self::Mp4 => matches!(format, AudioFormat::AAC | AudioFormat::MP3 | AudioFormat::FLAC | AudioFormat::OPUS),
+        }
+    }
+    
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Native => "Codec default",
+            Self::Matroska => "Matroska (.mka)",
+            Self::Ogg => "Ogg (.ogg)",
+            Self::Mp4 => "MP4 (.m4a)",
+        }
+    }
+    
+    pub fn all() -> [AudioContainer; 4] {
+        [
+            AudioContainer::Native,
+            AudioContainer::Matroska,
+            AudioContainer::Ogg,
+            AudioContainer::Mp4,
+        ]
+    }
+}
+
+/// Channel layout of the encoded audio
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ChannelLayout {
+    #[default]
+    Source,     // Whatever the input has, as far as the encoder allows
+    Mono,
+    Stereo,
+    Surround51,
+    Surround71,
+}
+
+impl ChannelLayout {
+    /// Channel count and FFmpeg layout name, or None to keep the source's
+    pub fn layout(&self) -> Option<(u32, &'static str)> {
+        match self {
+            Self::Source => None,
+            Self::Mono => Some((1, "mono")),
+            Self::Stereo => Some((2, "stereo")),
+            Self::Surround51 => Some((6, "5.1")),
+            Self::Surround71 => Some((8, "7.1")),
+        }
+    }
+    
+    pub fn is_surround(&self) -> bool {
+        matches!(self, Self::Surround51 | Self::Surround71)
+    }
+    
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Source => "Keep source",
+            Self::Mono => "Mono",
+            Self::Stereo => "Stereo",
+            Self::Surround51 => "5.1 surround",
+            Self::Surround71 => "7.1 surround",
+        }
+    }
+    
+    pub fn all() -> [ChannelLayout; 5] {
+        [
+            ChannelLayout::Source,
+            ChannelLayout::Mono,
+            ChannelLayout::Stereo,
+            ChannelLayout::Surround51,
+            ChannelLayout::Surround71,
+        ]
+    }
+}
+
+/// Which FFmpeg encoder produces AAC audio
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AacEncoder {
+    #[default]
+    Native,     // FFmpeg's built-in encoder, always available
+    Fdk,        // Fraunhofer FDK AAC, better at low bitrates but only in some builds
+}
+
+impl AacEncoder {
+    pub fn codec(&self) -> &'static str {
+        match self {
+            Self::Native => "aac",
+            Self::Fdk => "libfdk_aac",
+        }
+    }
+    
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Native => "Native (aac)",
+            Self::Fdk => "Fraunhofer FDK (libfdk_aac)",
+        }
+    }
+    
+    pub fn all() -> [AacEncoder; 2] {
+        [AacEncoder::Native, AacEncoder::Fdk]
+    }
+}
+
+/// Video encoders offered for CompressVideo, software and hardware
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum VideoEncoder {
+    #[default]
+    X264,
+    X265,
+    NvencH264,
+    NvencHevc,
+    QsvH264,
+    QsvHevc,
+    AmfH264,
+    AmfHevc,
+    Vp8,
+    Theora,
+}
+
+const SOFTWARE_PRESETS: &[&str] = &["ultrafast", "superfast", "veryfast", "faster", "fast", "medium", "slow", "slower", "veryslow"];
+const NVENC_PRESETS: &[&str] = &["p1", "p2", "p3", "p4", "p5", "p6", "p7"];
+const QSV_PRESETS: &[&str] = &["veryfast", "faster", "fast", "medium", "slow", "slower", "veryslow"];
+const AMF_QUALITIES: &[&str] = &["speed", "balanced", "quality"];
+const VPX_DEADLINES: &[&str] = &["realtime", "good", "best"];
+
+impl VideoEncoder {
+    pub fn codec(&self) -> &'static str {
+        match self {
+            Self::X264 => "libx264",
+            Self::X265 => "libx265",
+            Self::NvencH264 => "h264_nvenc",
+            Self::NvencHevc => "hevc_nvenc",
+            Self::QsvH264 => "h264_qsv",
+            Self::QsvHevc => "hevc_qsv",
+            Self::AmfH264 => "h264_amf",
+            Self::AmfHevc => "hevc_amf",
+            Self::Vp8 => "libvpx",
+            Self::Theora => "libtheora",
+        }
+    }
+    
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::X264 => "H.264 (x264)",
+            Self::X265 => "H.265 (x265)",
+            Self::NvencH264 => "H.264 (NVIDIA NVENC)",
+            Self::NvencHevc => "H.265 (NVIDIA NVENC)",
+            Self::QsvH264 => "H.264 (Intel Quick Sync)",
+            Self::QsvHevc => "H.265 (Intel Quick Sync)",
+            Self::AmfH264 => "H.264 (AMD AMF)",
+            Self::AmfHevc => "H.265 (AMD AMF)",
+            Self::Vp8 => "VP8 (legacy WebM)",
+            Self::Theora => "Theora (legacy Ogg)",
+        }
+    }
+    
+    /// Codec name ffprobe reports for streams this encoder produces
+    pub fn stream_codec(&self) -> &'static str {
+        match self {
+            Self::X264 | Self::NvencH264 | Self::QsvH264 | Self::AmfH264 => "h264",
+            Self::X265 | Self::NvencHevc | Self::QsvHevc | Self::AmfHevc => "hevc",
+            Self::Vp8 => "vp8",
+            Self::Theora => "theora",
+        }
+    }
+    
+    pub fn is_hardware(&self) -> bool {
+        !matches!(self, Self::X264 | Self::X265 | Self::Vp8 | Self::Theora)
+    }
+    
+    /// Whether FFmpeg's -pass and -passlogfile work with the encoder. libx265 takes its
+    /// passes through -x265-params instead, and the hardware encoders have none.
+    pub fn supports_two_pass(&self) -> bool {
+        matches!(self, Self::X264 | Self::Vp8 | Self::Theora)
+    }
+    
+    /// The container the encoder's output normally goes in
+    pub fn native_format(&self) -> OutputFormat {
+        match self {
+            Self::Vp8 => OutputFormat::WebM,
+            Self::Theora => OutputFormat::Ogg,
+            _ => OutputFormat::Mp4,
+        }
+    }
+    
+    /// Quality modes the encoder honours; hardware encoders ignore -crf, and Theora
+    /// only has a 0-10 quality scale, offered as its quantizer
+    pub fn quality_modes(&self) -> &'static [QualityMode] {
+        if self.is_hardware() || *self == Self::Theora {
+            &[QualityMode::Cqp, QualityMode::Bitrate]
+        } else {
+            &[QualityMode::Crf, QualityMode::Bitrate]
+        }
+    }
+    
+    pub fn crf_range(&self) -> std::ops::RangeInclusive<u8> {
+        match self {
+            Self::Vp8 => 4..=63,
+            _ => 0..=51,
+        }
+    }
+    
+    /// Quick-pick CRF values, best quality first
+    pub fn crf_steps(&self) -> &'static [u8] {
+        match self {
+            Self::Vp8 => &[10, 20, 30, 40],
+            _ => &[18, 23, 28, 35],
+        }
+    }
+    
+    pub fn qp_range(&self) -> std::ops::RangeInclusive<u8> {
+        match self {
+            Self::Theora => 0..=10,
+            _ => 0..=51,
+        }
+    }
+    
+    /// Quick-pick quantizer values, best quality first
+    pub fn qp_steps(&self) -> &'static [u8] {
+        match self {
+            Self::Theora => &[10, 8, 6, 4],
+            _ => &[18, 23, 28, 35],
+        }
+    }
+    
+    /// Arguments for constant rate factor at `crf`. VP8 treats the bitrate as a ceiling in
+    /// this mode and falls back to a low default without one, so it gets `max_kbps`.
+    pub fn crf_args(&self, crf: u8, max_kbps: u32) -> Vec<String> {
+        let mut args = vec!["-crf".to_string(), crf.to_string()];
+        if *self == Self::Vp8 {
+            args.extend(["-b:v".to_string(), format!("{}k", max_kbps)]);
+        }
+        args
+    }
+    
+    /// Arguments for an average bitrate of `kbps`
+    pub fn bitrate_args(&self, kbps: u32) -> Vec<String> {
+        let mut args = vec!["-b:v".to_string(), format!("{}k", kbps)];
+        if *self == Self::Vp8 {
+            // libvpx's defaults let the quantizer swing far enough to smear detail
+            args.extend(["-qmin".to_string(), "10".to_string(), "-qmax".to_string(), "42".to_string()]);
+        }
+        args
+    }
+    
+    /// Valid values for the speed/quality preset, fastest first
+    pub fn presets(&self) -> &'static [&'static str] {
+        match self {
+            Self::X264 | Self::X265 => SOFTWARE_PRESETS,
+            Self::NvencH264 | Self::NvencHevc => NVENC_PRESETS,
+            Self::QsvH264 | Self::QsvHevc => QSV_PRESETS,
+            Self::AmfH264 | Self::AmfHevc => AMF_QUALITIES,
+            Self::Vp8 => VPX_DEADLINES,
+            Self::Theora => &[],
+        }
+    }
+    
+    pub fn default_preset(&self) -> &'static str {
+        match self {
+            Self::NvencH264 | Self::NvencHevc => "p4",
+            Self::AmfH264 | Self::AmfHevc => "balanced",
+            Self::Vp8 => "good",
+            Self::Theora => "",
+            _ => "medium",
+        }
+    }
+    
+    /// The speed/quality preset as arguments. AMF calls it "-quality", libvpx "-deadline",
+    /// and Theora has none.
+    pub fn preset_args(&self, preset: &str) -> Vec<String> {
+        let flag = match self {
+            Self::AmfH264 | Self::AmfHevc => "-quality",
+            Self::Vp8 => "-deadline",
+            Self::Theora => return Vec::new(),
+            _ => "-preset",
+        };
+        vec![flag.to_string(), preset.to_string()]
+    }
+    
+    /// Arguments selecting constant quantizer rate control at the given QP
+    pub fn cqp_args(&self, qp: u8) -> Vec<String> {
+        let qp = qp.to_string();
+        match self {
+            Self::NvencH264 | Self::NvencHevc => vec!["-rc".into(), "constqp".into(), "-qp".into(), qp],
+            Self::QsvH264 | Self::QsvHevc => vec!["-q:v".into(), qp],  // QSV switches to CQP when a qscale is set
+            Self::AmfH264 | Self::AmfHevc => vec![
+                "-rc".into(), "cqp".into(),
+                "-qp_i".into(), qp.clone(),
+                "-qp_p".into(), qp.clone(),
+                "-qp_b".into(), qp,
+            ],
+            Self::X264 | Self::X265 => vec!["-qp".into(), qp],
+            Self::Theora => vec!["-q:v".into(), qp],  // 0-10, higher is better
+            Self::Vp8 => vec!["-qmin".into(), qp.clone(), "-qmax".into(), qp],
+        }
+    }
+    
+    /// Encoder-specific switch to true constant bitrate, on top of minrate = maxrate = bitrate
+    pub fn cbr_args(&self) -> Vec<String> {
+        match self {
+            Self::X264 => vec!["-nal-hrd".into(), "cbr".into()],
+            Self::X265 => Vec::new(),   // hrd=1 goes into MyApp::x265_params
+            Self::NvencH264 | Self::NvencHevc | Self::AmfH264 | Self::AmfHevc => vec!["-rc".into(), "cbr".into()],
+            Self::QsvH264 | Self::QsvHevc => Vec::new(), // QSV picks CBR when minrate equals maxrate
+            Self::Vp8 | Self::Theora => Vec::new(),    // Likewise for libvpx and libtheora
+        }
+    }
+    
+    /// Encoder-specific switches for a fixed `gop`-frame keyframe interval that scene
+    /// cuts don't stretch or shorten
+    pub fn fixed_gop_args(&self, gop: u32) -> Vec<String> {
+        let mut args = vec!["-g".to_string(), gop.to_string()];
+        match self {
+            Self::X264 => args.extend(["-sc_threshold".to_string(), "0".to_string()]),
+            Self::X265 => {},   // scenecut=0 goes into MyApp::x265_params
+            Self::NvencH264 | Self::NvencHevc => args.extend(["-no-scenecut".to_string(), "1".to_string()]),
+            Self::QsvH264 | Self::QsvHevc | Self::AmfH264 | Self::AmfHevc => {}, // Keep to -g unless told otherwise
+            Self::Vp8 | Self::Theora => {},
+        }
+        args
+    }
+    
+    /// Whether the encoder honours the region-of-interest side data added by `addroi`
+    pub fn supports_roi(&self) -> bool {
+        matches!(self, Self::X264 | Self::X265)
+    }
+    
+    pub fn all() -> [VideoEncoder; 10] {
+        [
+            VideoEncoder::X264,
+            VideoEncoder::X265,
+            VideoEncoder::NvencH264,
+            VideoEncoder::NvencHevc,
+            VideoEncoder::QsvH264,
+            VideoEncoder::QsvHevc,
+            VideoEncoder::AmfH264,
+            VideoEncoder::AmfHevc,
+            VideoEncoder::Vp8,
+            VideoEncoder::Theora,
+        ]
+    }
+}
+
+/// How the video encoder is told what quality to aim for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum QualityMode {
+    #[default]
+    Crf,        // Constant rate factor (software encoders)
+    Cqp,        // Constant quantizer (hardware encoders)
+    Bitrate,    // Average bitrate
+}
+
+impl QualityMode {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Crf => "Constant Rate Factor (CRF)",
+            Self::Cqp => "Constant Quantizer (CQP)",
+            Self::Bitrate => "Bitrate",
+        }
+    }
+    
+    /// Constant-quality modes aren't offered with variable frame rate output
+    pub fn needs_cfr(&self) -> bool {
+        !matches!(self, Self::Bitrate)
+    }
+}
+
+/// Clockwise rotation applied to the video
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Rotation {
+    #[default]
+    None,
+    Cw90,
+    Rotate180,
+    Ccw90,
+}
+
+impl Rotation {
+    pub fn degrees(&self) -> u32 {
+        match self {
+            Self::None => 0,
+            Self::Cw90 => 90,
+            Self::Rotate180 => 180,
+            Self::Ccw90 => 270,
+        }
+    }
+    
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Cw90 => "90° clockwise",
+            Self::Rotate180 => "180°",
+            Self::Ccw90 => "90° counter-clockwise",
+        }
+    }
+    
+    /// Filter that rotates the pixels themselves, for when the video is re-encoded anyway
+    pub fn filter(&self) -> Option<&'static str> {
+        match self {
+            Self::None => None,
+            Self::Cw90 => Some("transpose=clock"),
+            Self::Rotate180 => Some("hflip,vflip"),
+            Self::Ccw90 => Some("transpose=cclock"),
+        }
+    }
+    
+    /// Where a rectangle `[x, y, width, height]`, in fractions of the frame, ends up after `filter`
+    pub fn rotate_rect(&self, [x, y, w, h]: [f32; 4]) -> [f32; 4] {
+        match self {
+            Self::None => [x, y, w, h],
+            Self::Cw90 => [1.0 - y - h, x, h, w],
+            Self::Rotate180 => [1.0 - x - w, 1.0 - y - h, w, h],
+            Self::Ccw90 => [y, 1.0 - x - w, h, w],
+        }
+    }
+    
+    pub fn all() -> [Rotation; 4] {
+        [Rotation::None, Rotation::Cw90, Rotation::Rotate180, Rotation::Ccw90]
+    }
+}
+
+/// Where the cover picture embedded in extracted audio comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CoverArt {
+    #[default]
+    None,
+    FirstFrame, // The first frame of the source video
+    File,       // An image picked by the user
+}
+
+impl CoverArt {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::FirstFrame => "First video frame",
+            Self::File => "Image file",
+        }
+    }
+    
+    pub fn all() -> [CoverArt; 3] {
+        [CoverArt::None, CoverArt::FirstFrame, CoverArt::File]
+    }
+}
+
+/// How much of the UI is shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum UiMode {
+    Simple,     // Function, format and quality only
+    #[default]
+    Advanced,   // Every option
+}
+
+impl UiMode {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Simple => "Simple",
+            Self::Advanced => "Advanced",
+        }
+    }
+    
+    pub fn all() -> [UiMode; 2] {
+        [UiMode::Simple, UiMode::Advanced]
+    }
+}
+
+/// What the output's creation_time metadata is set to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CreationTime {
+    #[default]
+    Source,     // Whatever FFmpeg copies over from the input
+    Now,        // The time of the encode
+    Custom,     // A timestamp typed by the user
+    Clear,      // No creation time at all
+}
+
+impl CreationTime {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Source => "Use source",
+            Self::Now => "Now",
+            Self::Custom => "Custom",
+            Self::Clear => "Clear",
+        }
+    }
+    
+    pub fn all() -> [CreationTime; 4] {
+        [CreationTime::Source, CreationTime::Now, CreationTime::Custom, CreationTime::Clear]
+    }
+}
+
+/// How `paletteuse` spreads the colour error when mapping frames onto a GIF's palette
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum GifDither {
+    #[default]
+    Sierra2,    // Error diffusion: smooth gradients, some crawling noise between frames
+    Bayer,      // Ordered pattern: stable between frames and compresses better
+    None,       // Flat colour bands, smallest files
+}
+
+impl GifDither {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Sierra2 => "Sierra-2 (smoothest)",
+            Self::Bayer => "Bayer (stable pattern)",
+            Self::None => "None (banding)",
+        }
+    }
+    
+    pub fn filter_value(&self) -> &'static str {
+        match self {
+            Self::Sierra2 => "sierra2",
+            Self::Bayer => "bayer",
+            Self::None => "none",
+        }
+    }
+    
+    pub fn all() -> [GifDither; 3] {
+        [GifDither::Sierra2, GifDither::Bayer, GifDither::None]
+    }
+}
+
+/// Luma/chroma range of the output: limited (16-235, most video) or full (0-255, JPEG-style)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ColorRange {
+    #[default]
+    Auto,       // Keep the source's range
+    Limited,
+    Full,
+}
+
+impl ColorRange {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Auto => "Same as source",
+            Self::Limited => "Limited (TV)",
+            Self::Full => "Full (PC)",
+        }
+    }
+    
+    /// FFmpeg's name for the range, as taken by -color_range and scale's in_range/out_range
+    pub fn value(&self) -> Option<&'static str> {
+        match self {
+            Self::Auto => None,
+            Self::Limited => Some("tv"),
+            Self::Full => Some("pc"),
+        }
+    }
+    
+    pub fn all() -> [ColorRange; 3] {
+        [ColorRange::Auto, ColorRange::Limited, ColorRange::Full]
+    }
+}
+
+/// What to do with an external subtitle file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SubtitleMode {
+    #[default]
+    Off,
+    Mux,        // Add as a selectable subtitle track
+    Burn,       // Render into the picture (needs a re-encode)
+}
+
+impl SubtitleMode {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Off => "Off",
+            Self::Mux => "Add as track",
+            Self::Burn => "Burn in",
+        }
+    }
+    
+    pub fn all() -> [SubtitleMode; 3] {
+        [SubtitleMode::Off, SubtitleMode::Mux, SubtitleMode::Burn]
+    }
+}
+
+/// How trim points are typed in the Trim section
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TrimUnit {
+    #[default]
+    Timecode,   // HH:MM:SS.mmm or seconds
+    Frame,      // Frame numbers, converted with the source frame rate
+}
+
+/// How chapters are generated for long recordings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ChapterMode {
+    #[default]
+    Off,
+    Interval,   // Every N minutes
+    Scene,      // At detected scene changes
+}
+
+impl ChapterMode {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Off => "Off",
+            Self::Interval => "Fixed interval",
+            Self::Scene => "Scene changes",
+        }
+    }
+    
+    pub fn all() -> [ChapterMode; 3] {
+        [ChapterMode::Off, ChapterMode::Interval, ChapterMode::Scene]
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FrameRateMode { #[default] CFR, VFR, Source }   // Source: no -r, keep the input's cadence
+
+/// How a constant frame rate is reached, which matters for sources with irregular timestamps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CfrMethod {
+    #[default]
+    OutputRate, // -r on the output
+    Vsync,      // -r plus -vsync cfr
+    FpsFilter,  // the fps filter at the start of the filter chain
+}
+
+impl CfrMethod {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::OutputRate => "Output rate (-r)",
+            Self::Vsync => "Strict CFR (-vsync cfr)",
+            Self::FpsFilter => "fps filter",
+        }
+    }
+    
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::OutputRate => "Set the output rate and let FFmpeg drop or duplicate frames to match. Fine for sources that are already close to constant.",
+            Self::Vsync => "Force exactly evenly spaced timestamps, duplicating and dropping frames as needed. The most compatible result for editors, but jittery phone recordings can come out with visible duplicate frames.",
+            Self::FpsFilter => "Resample the frames by their timestamps before any other filter, picking the nearest frame for each output slot. Usually the smoothest on variable-rate phone recordings, and later filters only process the frames kept.",
+        }
+    }
+    
+    pub fn all() -> [CfrMethod; 3] {
+        [CfrMethod::OutputRate, CfrMethod::Vsync, CfrMethod::FpsFilter]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum OutputFormat { #[default] Mp4, Mkv, WebM, Ogg }
 impl OutputFormat { 
     pub fn ext(&self) -> &'static str { 
         match self { 
             OutputFormat::Mp4 => "mp4", 
-            OutputFormat::Mkv => "mkv" 
+            OutputFormat::Mkv => "mkv",
+            OutputFormat::WebM => "webm",
+            OutputFormat::Ogg => "ogv",
         } 
     }
     
     pub fn display_name(&self) -> &'static str {
         match self {
             Self::Mp4 => "MP4",
-            Self::Mkv => "MKV"
+            Self::Mkv => "MKV",
+            Self::WebM => "WebM",
+            Self::Ogg => "Ogg",
+        }
+    }
+    
+    /// Whether the container can carry video from `encoder`
+    pub fn supports_encoder(&self, encoder: VideoEncoder) -> bool {
+        match self {
+            Self::Mp4 => !matches!(encoder, VideoEncoder::Vp8 | VideoEncoder::Theora),
+            Self::Mkv => true,
+            Self::WebM => encoder == VideoEncoder::Vp8,
+            Self::Ogg => encoder == VideoEncoder::Theora,
+        }
+    }
+    
+    /// Whether the container can carry `format` audio
+    pub fn supports_audio(&self, format: AudioFormat) -> bool {
+        match self {
+            Self::Mp4 => format != AudioFormat::VORBIS,
+            Self::Mkv => true,
+            Self::WebM => matches!(format, AudioFormat::VORBIS | AudioFormat::OPUS),
+            Self::Ogg => matches!(format, AudioFormat::VORBIS | AudioFormat::OPUS | AudioFormat::FLAC),
+        }
+    }
+    
+    /// WebM only takes WebVTT subtitles and Ogg none, so the source's are dropped for both
+    pub fn supports_subtitles(&self) -> bool {
+        matches!(self, Self::Mp4 | Self::Mkv)
+    }
+    
+    pub fn all() -> [OutputFormat; 4] {
+        [OutputFormat::Mp4, OutputFormat::Mkv, OutputFormat::WebM, OutputFormat::Ogg]
+    }
+}
+
+/// Common upload size limits that Compress Video can aim just under
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SizeLimit {
+    #[default]
+    Discord,
+    Email,
+    WhatsApp,
+    Forum,
+    DiscordNitro,
+}
+
+impl SizeLimit {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Discord => "Discord (25 MB)",
+            Self::Email => "Email attachment (25 MB)",
+            Self::WhatsApp => "WhatsApp (16 MB)",
+            Self::Forum => "Forum upload (50 MB)",
+            Self::DiscordNitro => "Discord Nitro (500 MB)",
+        }
+    }
+    
+    pub fn megabytes(&self) -> f64 {
+        match self {
+            Self::Discord | Self::Email => 25.0,
+            Self::WhatsApp => 16.0,
+            Self::Forum => 50.0,
+            Self::DiscordNitro => 500.0,
+        }
+    }
+    
+    pub fn all() -> [SizeLimit; 5] {
+        [SizeLimit::Discord, SizeLimit::Email, SizeLimit::WhatsApp, SizeLimit::Forum, SizeLimit::DiscordNitro]
+    }
+}
+
+/// How `unique_path` numbers a file name that is already taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum NamingScheme {
+    #[default]
+    Parenthesis,        // name(1).mp4
+    UnderscorePadded,   // name_001.mp4
+    DashCopy,           // name - Copy.mp4, name - Copy (2).mp4
+}
+
+impl NamingScheme {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Parenthesis => "name(1)",
+            Self::UnderscorePadded => "name_001",
+            Self::DashCopy => "name - Copy",
         }
     }
     
-    pub fn all() -> [OutputFormat; 2] {
-        [OutputFormat::Mp4, OutputFormat::Mkv]
+    pub fn all() -> [NamingScheme; 3] {
+        [NamingScheme::Parenthesis, NamingScheme::UnderscorePadded, NamingScheme::DashCopy]
     }
 }
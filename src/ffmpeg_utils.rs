@@ -1,39 +1,910 @@
 use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
 
-pub fn parse_timecode(tc: &str) -> f32 {
+use crate::enums::{NamingScheme, TrimUnit};
+
+pub fn parse_timecode(tc: &str) -> f64 {
     let parts: Vec<&str> = tc.split(':').collect();
     if parts.len() == 3 {
-        parts[0].parse::<f32>().unwrap_or(0.0) * 3600.0 + parts[1].parse::<f32>().unwrap_or(0.0) * 60.0 + parts[2].parse::<f32>().unwrap_or(0.0)
+        parts[0].parse::<f64>().unwrap_or(0.0) * 3600.0 + parts[1].parse::<f64>().unwrap_or(0.0) * 60.0 + parts[2].parse::<f64>().unwrap_or(0.0)
     } else { 0.0 }
 }
 
-pub fn unique_path(path: PathBuf) -> PathBuf {
+/// Timestamp of a frame at the given frame rate
+pub fn frame_to_seconds(frame: u64, fps: f32) -> f64 {
+    if fps <= 0.0 { 0.0 } else { frame as f64 / fps as f64 }
+}
+
+/// Parse a trim point typed by the user: a frame number in frame mode, otherwise
+/// HH:MM:SS.mmm, MM:SS or plain seconds. Empty or invalid input gives None.
+pub fn parse_trim_point(text: &str, unit: TrimUnit, fps: f32) -> Option<f64> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    match unit {
+        TrimUnit::Frame => text.parse::<u64>().ok().map(|frame| frame_to_seconds(frame, fps)),
+        TrimUnit::Timecode => {
+            let mut seconds = 0.0;
+            for part in text.split(':') {
+                seconds = seconds * 60.0 + part.parse::<f64>().ok().filter(|v| *v >= 0.0)?;
+            }
+            Some(seconds)
+        }
+    }
+}
+
+/// Format seconds as HH:MM:SS.mmm for -ss/-to
+pub fn format_timestamp(seconds: f64) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// Input options that read only the trimmed section of the next input
+pub fn trim_args(trim: Option<(Option<f64>, Option<f64>)>) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some((start, end)) = trim {
+        if let Some(start) = start {
+            args.extend(["-ss".to_string(), format_timestamp(start)]);
+        }
+        if let Some(end) = end {
+            args.extend(["-to".to_string(), format_timestamp(end)]);
+        }
+    }
+    args
+}
+
+/// Where in the first input a command starts reading, from the -ss `trim_args` puts before it
+pub fn input_start(args: &[String]) -> f64 {
+    let first_input = args.iter().position(|arg| arg == "-i").unwrap_or(args.len());
+    args[..first_input]
+        .windows(2)
+        .find(|pair| pair[0] == "-ss")
+        .map(|pair| parse_timecode(&pair[1]))
+        .unwrap_or(0.0)
+}
+
+/// Whether `text` is an ISO 8601 date-time FFmpeg accepts for creation_time, e.g.
+/// "2024-05-01T12:30:00Z"; fractional seconds and the trailing Z are optional
+pub fn is_iso8601(text: &str) -> bool {
+    let text = text.strip_suffix('Z').unwrap_or(text);
+    let Some((date, time)) = text.split_once(['T', ' ']) else {
+        return false;
+    };
+    let time = time.split_once('.').map_or(time, |(whole, fraction)| {
+        if !fraction.is_empty() && fraction.chars().all(|c| c.is_ascii_digit()) { whole } else { "" }
+    });
+    let fields_ok = |text: &str, sep: char, widths: [usize; 3], max: [u32; 3]| {
+        let parts: Vec<&str> = text.split(sep).collect();
+        parts.len() == 3 && parts.iter().zip(widths).zip(max).all(|((part, width), max)| {
+            part.len() == width && part.chars().all(|c| c.is_ascii_digit()) && part.parse::<u32>().is_ok_and(|v| v <= max)
+        })
+    };
+    fields_ok(date, '-', [4, 2, 2], [9999, 12, 31]) && fields_ok(time, ':', [2, 2, 2], [23, 59, 60])
+}
+
+pub fn unique_path(path: PathBuf, scheme: NamingScheme) -> PathBuf {
     if !path.exists() { return path; }
     
     // Extract the base stem without any existing numbering
     let parent = path.parent().unwrap_or(Path::new(".")).to_path_buf();
-    let mut stem = path.file_stem().unwrap().to_string_lossy().to_string();
+    let stem = path.file_stem().unwrap().to_string_lossy().to_string();
     let ext = path.extension().map(|s| s.to_string_lossy()).unwrap_or_else(|| "".into());
     
-    // Remove any existing (N) suffix to avoid (1)(2) patterns
-    if let Some(pos) = stem.rfind('(') {
-        if let Some(end_pos) = stem[pos..].find(')') {
-            if stem[pos+1..pos+end_pos].chars().all(|c| c.is_digit(10)) {
-                stem = stem[0..pos].trim().to_string();
-            }
-        }
-    }
+    let with_ext = |stem: &str| if ext.is_empty() { stem.to_string() } else { format!("{}.{}", stem, ext) };
+    
+    // Remove an existing suffix to avoid (1)(2) patterns, but only when the unnumbered file
+    // is there too; otherwise the number is part of the name, like holiday_2024's
+    let stripped = strip_numbering(&stem, scheme);
+    let stem = if stripped != stem && parent.join(with_ext(stripped)).exists() { stripped } else { &stem };
     
     // Try with incrementing numbers indefinitely
     let mut i = 1;
     loop {
-        let file_name = if ext.is_empty() {
-            format!("{}({})", stem, i)
-        } else {
-            format!("{}({}).{}", stem, i, ext)
-        };
-        let candidate = parent.join(&file_name);
+        let candidate = parent.join(with_ext(&numbered_stem(stem, i, scheme)));
         if !candidate.exists() { return candidate; }
         i += 1;
     }
 }
+
+fn numbered_stem(stem: &str, n: u32, scheme: NamingScheme) -> String {
+    match scheme {
+        NamingScheme::Parenthesis => format!("{}({})", stem, n),
+        NamingScheme::UnderscorePadded => format!("{}_{:03}", stem, n),
+        NamingScheme::DashCopy if n == 1 => format!("{} - Copy", stem),
+        NamingScheme::DashCopy => format!("{} - Copy ({})", stem, n),
+    }
+}
+
+/// Remove a suffix previously added by `numbered_stem` with the same scheme
+fn strip_numbering(stem: &str, scheme: NamingScheme) -> &str {
+    let all_digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    match scheme {
+        NamingScheme::Parenthesis => {
+            if let Some(pos) = stem.rfind('(')
+                && let Some(end_pos) = stem[pos..].find(')')
+                && stem[pos+1..pos+end_pos].chars().all(|c| c.is_ascii_digit())
+            {
+                return stem[0..pos].trim();
+            }
+            stem
+        },
+        NamingScheme::UnderscorePadded => match stem.rsplit_once('_') {
+            Some((base, n)) if n.len() == 3 && all_digits(n) => base,
+            _ => stem,
+        },
+        NamingScheme::DashCopy => {
+            let base = match stem.strip_suffix(')').and_then(|s| s.rsplit_once(" (")) {
+                Some((base, n)) if all_digits(n) => base,
+                _ => stem,
+            };
+            base.strip_suffix(" - Copy").unwrap_or(stem)
+        }
+    }
+}
+
+/// Insert `suffix` between the file stem and extension, e.g. "clip.mp4" -> "clip_720p.mp4"
+pub fn with_stem_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match path.extension() {
+        Some(ext) => format!("{}{}.{}", stem, suffix, ext.to_string_lossy()),
+        None => format!("{}{}", stem, suffix),
+    };
+    path.with_file_name(file_name)
+}
+
+/// Groups of indices into `outputs` that name the same file. Compared ignoring case, as
+/// the file systems of Windows and macOS do.
+pub fn colliding_outputs(outputs: &[&Path]) -> Vec<Vec<usize>> {
+    let mut groups: Vec<(String, Vec<usize>)> = Vec::new();
+    for (i, path) in outputs.iter().enumerate() {
+        let key = path.to_string_lossy().to_lowercase();
+        match groups.iter_mut().find(|(existing, _)| *existing == key) {
+            Some((_, group)) => group.push(i),
+            None => groups.push((key, vec![i])),
+        }
+    }
+    groups.into_iter().map(|(_, group)| group).filter(|group| group.len() > 1).collect()
+}
+
+/// Make free text (e.g. a chapter title) safe to use in a file name on any platform
+pub fn safe_file_name(text: &str) -> String {
+    let cleaned: String = text
+        .chars()
+        .map(|c| if c.is_control() || "<>:\"/\\|?*".contains(c) { '_' } else { c })
+        .collect();
+    // Windows drops trailing dots and spaces, which would make the name collide
+    cleaned.trim().trim_end_matches('.').to_string()
+}
+
+/// Path of the in-progress file FFmpeg writes to before it is renamed into place,
+/// e.g. `clip.mp4` -> `clip.part.mp4` (the real extension is kept so FFmpeg can pick the muxer)
+pub fn temp_output_path(output: &Path) -> PathBuf {
+    with_stem_suffix(output, ".part")
+}
+
+/// Tidy a path that was typed, pasted or dropped: surrounding whitespace and one pair of
+/// matching quotes (as added by terminals and "Copy as path" in file managers) are removed,
+/// and a file:// URI (as dropped from some Linux file managers) becomes a plain path
+pub fn clean_path(text: &str) -> String {
+    let text = text.trim();
+    let unquoted = ['"', '\'']
+        .iter()
+        .find_map(|&q| text.strip_prefix(q).and_then(|rest| rest.strip_suffix(q)))
+        .unwrap_or(text)
+        .trim();
+    let Some(rest) = unquoted.strip_prefix("file://") else {
+        return unquoted.to_string();
+    };
+    let path = percent_decode(rest.strip_prefix("localhost").unwrap_or(rest));
+    // A Windows drive comes after a slash: file:///C:/Videos
+    match path.strip_prefix('/') {
+        Some(drive) if drive.as_bytes().get(1) == Some(&b':') && drive.starts_with(|c: char| c.is_ascii_alphabetic()) => {
+            drive.to_string()
+        },
+        _ => path,
+    }
+}
+
+/// Decode %XX escapes in a URI path; a % not followed by two hex digits is kept as it is
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            },
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            },
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Quote one argument for a POSIX shell, or for cmd.exe when `windows` is set. A batch file
+/// also expands %VAR% inside quotes, which `batch_file_line` takes care of.
+pub fn quote_arg(arg: &str, windows: bool) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "_-.,/:=+@".contains(c);
+    if !arg.is_empty() && arg.chars().all(|c| is_safe(c) || (windows && c == '\\')) {
+        return arg.to_string();
+    }
+    if windows {
+        // cmd.exe doubles quotes inside a quoted string
+        format!("\"{}\"", arg.replace('"', "\"\""))
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Split user-typed options into arguments at whitespace, keeping "double" or 'single'
+/// quoted parts together
+pub fn split_args(text: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quote = None;
+    for c in text.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => current.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_arg = true;
+            },
+            (None, c) if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            },
+            (None, c) => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if let Some(q) = quote {
+        return Err(format!("unclosed {} quote", q));
+    }
+    if in_arg {
+        args.push(current);
+    }
+    Ok(args)
+}
+
+/// Join arguments back into text `split_args` reads the same way
+pub fn join_args(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| match (arg.contains(char::is_whitespace) || arg.is_empty(), arg.contains('"')) {
+            (false, _) if !arg.contains('\'') => arg.clone(),
+            (_, false) => format!("\"{}\"", arg),
+            _ => format!("'{}'", arg),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// What `parse_command` recognised in a pasted FFmpeg command
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedCommand {
+    pub input: Option<String>,
+    pub output: Option<String>,
+    pub video_codec: Option<String>,
+    pub audio_codec: Option<String>,
+    pub crf: Option<u8>,
+    pub qp: Option<u8>,
+    pub video_bitrate_kbps: Option<u32>,
+    pub audio_bitrate_kbps: Option<u32>,
+    pub preset: Option<String>,
+    pub scale_height: Option<u32>,
+    pub frame_rate: Option<f32>,
+    pub start: Option<f64>,
+    pub end: Option<f64>,
+    pub duration: Option<f64>,
+    pub no_audio: bool,
+    pub no_video: bool,
+    pub extra: Vec<String>,     // Unrecognised output options with their values, kept as extra options
+    pub ignored: Vec<String>,   // Options that can't be carried over, e.g. a second input or a filtergraph
+}
+
+/// `2M`, `2500k` or `2500000` as kbps
+fn parse_bitrate_kbps(value: &str) -> Option<u32> {
+    let (number, scale) = match value.chars().last()? {
+        'k' | 'K' => (&value[..value.len() - 1], 1.0),
+        'm' | 'M' => (&value[..value.len() - 1], 1000.0),
+        _ => (value, 0.001),
+    };
+    number.parse::<f32>().ok().map(|n| (n * scale).round() as u32).filter(|&kbps| kbps > 0)
+}
+
+/// The output height of a `-vf` that is only a scale filter, such as `scale=-2:720` or `scale=w=1280:h=720`
+fn parse_scale_height(filter: &str) -> Option<u32> {
+    let options = filter.strip_prefix("scale=")?;
+    if options.contains(',') {
+        return None;
+    }
+    let height = options.split(':').enumerate().find_map(|(i, option)| match option.split_once('=') {
+        Some(("h" | "height", height)) => Some(height),
+        None if i == 1 => Some(option),
+        _ => None,
+    })?;
+    height.parse().ok()
+}
+
+/// Best-effort reading of an FFmpeg command line back into the settings the GUI has
+/// controls for; the inverse of `MyApp::build_command`. A leading `ffmpeg` is optional.
+pub fn parse_command(text: &str) -> Result<ParsedCommand, String> {
+    let mut args = split_args(text.trim())?;
+    if args.first().and_then(|first| Path::new(first).file_stem()).is_some_and(|stem| stem.eq_ignore_ascii_case("ffmpeg")) {
+        args.remove(0);
+    }
+    let mut parsed = ParsedCommand::default();
+    // The output file comes last, so an unknown switch right before it can't be taken for its value
+    if args.last().is_some_and(|last| !last.starts_with('-')) {
+        parsed.output = args.pop();
+    }
+    
+    let mut args = args.into_iter().peekable();
+    while let Some(flag) = args.next() {
+        // Options that switch something off, or that the GUI always adds itself
+        match flag.as_str() {
+            "-an" => { parsed.no_audio = true; continue; },
+            "-vn" => { parsed.no_video = true; continue; },
+            "-y" | "-n" | "-nostdin" | "-hide_banner" => continue,
+            _ => {},
+        }
+        let value = args.next_if(|value| !value.starts_with('-') || value.parse::<f64>().is_ok());
+        let Some(value) = value else {
+            if parsed.input.is_some() {
+                parsed.extra.push(flag);
+            } else {
+                parsed.ignored.push(flag);
+            }
+            continue;
+        };
+        let time = |value: &str| parse_trim_point(value, TrimUnit::Timecode, 0.0);
+        let recognised = match flag.as_str() {
+            "-i" if parsed.input.is_none() => { parsed.input = Some(value.clone()); true },
+            "-c:v" | "-codec:v" | "-vcodec" => { parsed.video_codec = Some(value.clone()); true },
+            "-c:a" | "-codec:a" | "-acodec" => { parsed.audio_codec = Some(value.clone()); true },
+            // For every stream; a -c:v or -c:a given as well is more specific, so it wins
+            "-c" | "-codec" => {
+                parsed.video_codec.get_or_insert_with(|| value.clone());
+                parsed.audio_codec.get_or_insert_with(|| value.clone());
+                true
+            },
+            "-crf" => { parsed.crf = value.parse().ok(); parsed.crf.is_some() },
+            "-qp" | "-cq" | "-q:v" | "-global_quality" => { parsed.qp = value.parse().ok(); parsed.qp.is_some() },
+            "-b:v" => { parsed.video_bitrate_kbps = parse_bitrate_kbps(&value); parsed.video_bitrate_kbps.is_some() },
+            "-b:a" => { parsed.audio_bitrate_kbps = parse_bitrate_kbps(&value); parsed.audio_bitrate_kbps.is_some() },
+            "-preset" | "-quality" | "-deadline" => { parsed.preset = Some(value.clone()); true },
+            "-vf" | "-filter:v" => { parsed.scale_height = parse_scale_height(&value); parsed.scale_height.is_some() },
+            "-r" => { parsed.frame_rate = value.parse().ok().filter(|&fps: &f32| fps > 0.0); parsed.frame_rate.is_some() },
+            "-ss" => { parsed.start = time(&value); parsed.start.is_some() },
+            "-to" => { parsed.end = time(&value); parsed.end.is_some() },
+            "-t" => { parsed.duration = time(&value); parsed.duration.is_some() },
+            _ => false,
+        };
+        if recognised {
+            continue;
+        }
+        // Anything else before the input is a global or input option, which extra output options can't hold.
+        // Maps are left out too: each function maps the streams itself, and a second -map 0 doubles them.
+        let keep_as_extra = parsed.input.is_some()
+            && !matches!(flag.as_str(), "-i" | "-vf" | "-filter:v" | "-filter_complex" | "-lavfi" | "-map");
+        if keep_as_extra {
+            parsed.extra.extend([flag, value]);
+        } else {
+            parsed.ignored.push(format!("{} {}", flag, value));
+        }
+    }
+    
+    if parsed.input.is_none() && parsed.output.is_none() && parsed.extra.is_empty() {
+        return Err("no FFmpeg options found".to_string());
+    }
+    Ok(parsed)
+}
+
+/// A full `ffmpeg ...` line with every argument quoted for the target shell
+pub fn command_line(args: &[String], windows: bool) -> String {
+    std::iter::once("ffmpeg".to_string())
+        .chain(args.iter().map(|arg| quote_arg(arg, windows)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `command_line` for a line of a script, where a .bat file needs its % signs doubled
+pub fn batch_file_line(args: &[String], windows: bool) -> String {
+    let line = command_line(args, windows);
+    if windows { line.replace('%', "%%") } else { line }
+}
+
+/// `command_line` laid out for reading: each option and its value on its own line, indented
+/// under `ffmpeg`. Only for display, since the line breaks aren't escaped for any shell.
+pub fn wrapped_command_line(args: &[String], windows: bool) -> String {
+    let mut lines = vec!["ffmpeg".to_string()];
+    let mut args = args.iter().peekable();
+    while let Some(arg) = args.next() {
+        let mut line = format!("  {}", quote_arg(arg, windows));
+        // An option's value is the next argument unless that is itself an option (e.g. `-an -sn`)
+        if arg.starts_with('-') && arg.len() > 1
+            && let Some(value) = args.next_if(|value| !value.starts_with('-') || value.parse::<f64>().is_ok())
+        {
+            line.push(' ');
+            line.push_str(&quote_arg(value, windows));
+        }
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+/// Fill in the `{input}` and `{output}` placeholders of a post-encode command, quoting the
+/// paths for the shell. Any other `{...}` is rejected so a typo doesn't run half a command.
+pub fn expand_post_command(template: &str, input: &Path, output: &Path, windows: bool) -> Result<String, String> {
+    let mut expanded = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        expanded.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find('}') else {
+            return Err("unclosed '{' in command".to_string());
+        };
+        let path = match &rest[start + 1..start + len] {
+            "input" => input,
+            "output" => output,
+            other => return Err(format!("unknown placeholder {{{}}}, use {{input}} or {{output}}", other)),
+        };
+        expanded.push_str(&quote_arg(&path.display().to_string(), windows));
+        rest = &rest[start + len + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// A command that runs `command_line` through the platform shell
+pub fn shell_command(command_line: &str) -> Command {
+    #[cfg(windows)]
+    {
+        // cmd parses its own command line, so the line goes in as it is rather than escaped
+        // for argv; /S takes the outer quotes off and leaves the line's own quotes alone
+        use std::os::windows::process::CommandExt;
+        let mut command = Command::new("cmd");
+        command.raw_arg(format!("/S /C \"{}\"", command_line));
+        command
+    }
+    #[cfg(not(windows))]
+    {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(command_line);
+        command
+    }
+}
+
+/// An `ffmpeg` command, started below normal CPU priority when `background` is set so an
+/// encode doesn't slow down everything else: via `nice` on Unix, a creation flag on Windows
+pub fn ffmpeg_command(background: bool) -> Command {
+    if !background {
+        return Command::new("ffmpeg");
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const BELOW_NORMAL_PRIORITY_CLASS: u32 = 0x0000_4000;
+        let mut command = Command::new("ffmpeg");
+        command.creation_flags(BELOW_NORMAL_PRIORITY_CLASS);
+        command
+    }
+    #[cfg(not(windows))]
+    {
+        // nice execs ffmpeg in place, so stopping the child still stops the encode
+        let mut command = Command::new("nice");
+        command.args(["-n", "10", "ffmpeg"]);
+        command
+    }
+}
+
+/// Ask FFmpeg to wrap up as if Ctrl+C was pressed, so it writes the trailer and the output
+/// plays up to where it stopped. Windows has no SIGINT to send, so there it's told to quit
+/// with a "q" on its stdin, which has to have been piped (see `stop_stdin`).
+/// Returns false if the request couldn't be sent
+pub fn interrupt(child: &mut Child) -> bool {
+    #[cfg(unix)]
+    {
+        let Ok(pid) = libc::pid_t::try_from(child.id()) else {
+            return false;
+        };
+        // SAFETY: kill only sends a signal; the pid is our own child, not yet reaped
+        unsafe { libc::kill(pid, libc::SIGINT) == 0 }
+    }
+    #[cfg(not(unix))]
+    {
+        use std::io::Write;
+        child.stdin.as_mut().is_some_and(|stdin| stdin.write_all(b"q").and_then(|()| stdin.flush()).is_ok())
+    }
+}
+
+/// Arguments and stdin for an encode that `interrupt` may stop. On Windows FFmpeg has to
+/// read its stdin for that, so -nostdin is left out and the stdin is piped.
+pub fn stop_stdin(args: &[String]) -> (Vec<String>, Stdio) {
+    if cfg!(windows) {
+        (args.iter().filter(|arg| *arg != "-nostdin").cloned().collect(), Stdio::piped())
+    } else {
+        (args.to_vec(), Stdio::null())
+    }
+}
+
+/// Run a command line through the platform shell, capturing its output
+pub fn run_shell(command_line: &str) -> std::io::Result<std::process::Output> {
+    shell_command(command_line).output()
+}
+
+/// Check that files can be created in `dir` by writing and removing a small test file.
+/// Catches read-only media, full disks and network shares that have gone away.
+pub fn check_writable(dir: &Path) -> std::io::Result<()> {
+    let probe = dir.join(format!(".ffmpeg_gui_write_test_{}", std::process::id()));
+    let result = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+        .and_then(|mut file| std::io::Write::write_all(&mut file, b"test"));
+    let _ = std::fs::remove_file(&probe);
+    result
+}
+
+/// Replace `path` with `contents` by writing a temp file and renaming it over the original,
+/// so a crash mid-write leaves the old file intact rather than a truncated one
+pub fn write_atomic(path: &Path, contents: &str) -> std::io::Result<()> {
+    let temp = with_stem_suffix(path, ".tmp");
+    std::fs::write(&temp, contents)?;
+    std::fs::rename(&temp, path)
+}
+
+/// Open a file with the system's default application for it
+pub fn open_in_default_app(path: &Path) -> std::io::Result<()> {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else {
+        Command::new("xdg-open")
+    };
+    command.arg(path).spawn().map(|_| ())
+}
+
+/// Location for files the app keeps between sessions. This is a portable tool,
+/// so they live next to the executable rather than in a per-user directory.
+pub fn app_file(name: &str) -> PathBuf {
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(name)
+}
+
+/// Query the container duration of a media file in seconds
+pub fn probe_duration(path: &Path) -> Option<f64> {
+    let output = Command::new("ffprobe")
+        .args(["-v", "error", "-show_entries", "format=duration", "-of", "default=noprint_wrappers=1:nokey=1"])
+        .arg(path)
+        .output()
+        .ok()?;
+    String::from_utf8(output.stdout).ok()?.trim().parse::<f64>().ok()
+}
+
+/// List the encoder names the installed FFmpeg build supports (`ffmpeg -encoders`)
+pub fn list_encoders() -> Vec<String> {
+    let Ok(output) = Command::new("ffmpeg").args(["-hide_banner", "-encoders"]).output() else {
+        return Vec::new();
+    };
+    
+    // Entries follow a "------" separator line and look like " A....D aac    AAC (Advanced Audio Coding)"
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .skip_while(|line| !line.trim_start().starts_with("---"))
+        .skip(1)
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Whether the installed FFmpeg takes -display_rotation, added in 6.0 when the
+/// rotate tag stopped being read
+pub fn has_display_rotation() -> bool {
+    Command::new("ffmpeg")
+        .args(["-hide_banner", "-h", "long"])
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains("-display_rotation"))
+}
+
+/// Names of the filters the installed FFmpeg was built with
+pub fn list_filters() -> Vec<String> {
+    let Ok(output) = Command::new("ffmpeg").args(["-hide_banner", "-filters"]).output() else {
+        return Vec::new();
+    };
+    
+    // Entries look like " TSC libvmaf  VV->V  Calculate the VMAF between two video streams.";
+    // the legend above them has no "->" column
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace().skip(1);
+            let name = fields.next()?;
+            fields.next()?.contains("->").then(|| name.to_string())
+        })
+        .collect()
+}
+
+/// Below this many bits per pixel per frame, H.264-class encoders start to look blocky
+pub const LOW_BITS_PER_PIXEL: f32 = 0.05;
+
+/// Bits spent on each pixel of each frame, the usual yardstick for whether a bitrate suits a resolution
+pub fn bits_per_pixel(bitrate_kbps: u32, width: u32, height: u32, fps: f32) -> f32 {
+    let pixels_per_second = width as f32 * height as f32 * fps;
+    if pixels_per_second <= 0.0 {
+        return 0.0;
+    }
+    bitrate_kbps as f32 * 1000.0 / pixels_per_second
+}
+
+/// How a bits-per-pixel figure is likely to look, for typical H.264/H.265 content
+pub fn bits_per_pixel_rating(bpp: f32) -> &'static str {
+    if bpp < LOW_BITS_PER_PIXEL {
+        "low - expect blockiness"
+    } else if bpp < 0.1 {
+        "moderate - fine for most content"
+    } else if bpp < 0.2 {
+        "good"
+    } else {
+        "high - a lower bitrate would likely look the same"
+    }
+}
+
+/// Common output heights, largest first
+pub const STANDARD_HEIGHTS: [u32; 6] = [2160, 1440, 1080, 720, 480, 360];
+
+/// Short name for a resolution by its height, e.g. "4K" or "720p"
+pub fn resolution_label(height: u32) -> String {
+    match height {
+        2160 => "4K".to_string(),
+        h => format!("{}p", h),
+    }
+}
+
+/// Advise scaling down when the bitrate is too low for the source resolution,
+/// suggesting the largest standard height the bitrate can still cover well
+pub fn bitrate_advice(bitrate_kbps: u32, width: u32, height: u32, fps: f32) -> Option<String> {
+    let bpp = bits_per_pixel(bitrate_kbps, width, height, fps);
+    if bpp >= LOW_BITS_PER_PIXEL {
+        return None;
+    }
+    
+    let aspect = width as f32 / height as f32;
+    let suggestion = STANDARD_HEIGHTS
+        .into_iter()
+        .filter(|&h| h < height)
+        .find(|&h| bits_per_pixel(bitrate_kbps, (h as f32 * aspect) as u32, h, fps) >= LOW_BITS_PER_PIXEL);
+    
+    let current = resolution_label(height);
+    Some(match suggestion {
+        Some(h) => format!(
+            "This bitrate is low for {} ({:.3} bits/pixel) - consider scaling to {}",
+            current, bpp, resolution_label(h)
+        ),
+        None => format!(
+            "This bitrate is very low for {} ({:.3} bits/pixel) - expect heavy blocking",
+            current, bpp
+        ),
+    })
+}
+
+/// Size of the frames grabbed for previews and comparisons
+pub const PREVIEW_SIZE: [u32; 2] = [480, 270];
+
+/// Grab the frame at `time` seconds as raw RGBA, scaled and letterboxed to exactly `size`
+pub fn extract_frame_rgba(path: &Path, time: f64, size: [u32; 2]) -> Option<Vec<u8>> {
+    let [width, height] = size;
+    let filter = format!(
+        "scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2",
+        w = width,
+        h = height
+    );
+    let output = Command::new("ffmpeg")
+        .args(["-v", "error", "-ss", &format!("{:.3}", time.max(0.0)), "-i"])
+        .arg(path)
+        .args(["-frames:v", "1", "-vf", &filter, "-f", "rawvideo", "-pix_fmt", "rgba", "-"])
+        .output()
+        .ok()?;
+    
+    (output.stdout.len() == (width * height * 4) as usize).then_some(output.stdout)
+}
+
+/// Format a duration as m:ss, or h:mm:ss once it passes an hour
+pub fn format_duration(duration: std::time::Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+/// A byte count as KB, MB or GB (1024-based, like FFmpeg's own figures)
+pub fn format_size(bytes: u64) -> String {
+    let bytes = bytes as f64;
+    match bytes {
+        b if b >= 1024.0 * 1024.0 * 1024.0 => format!("{:.2} GB", b / (1024.0 * 1024.0 * 1024.0)),
+        b if b >= 1024.0 * 1024.0 => format!("{:.1} MB", b / (1024.0 * 1024.0)),
+        b if b >= 1024.0 => format!("{:.0} KB", b / 1024.0),
+        b => format!("{} bytes", b),
+    }
+}
+
+/// Where the bytes of a finished output went, from the summary line FFmpeg prints when it's done
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SizeBreakdown {
+    pub video: u64,
+    pub audio: u64,
+    pub subtitle: u64,
+    pub other: u64,         // Other streams and global headers
+    pub overhead: u64,      // The container's own bytes; 0 when FFmpeg couldn't tell
+}
+
+impl SizeBreakdown {
+    pub fn total(&self) -> u64 {
+        self.video + self.audio + self.subtitle + self.other + self.overhead
+    }
+}
+
+/// Parse FFmpeg's closing `video:1234kB audio:256kB subtitle:0kB other streams:0kB global
+/// headers:0kB muxing overhead: 0.51%` line. Newer builds say KiB; both mean 1024 bytes.
+pub fn parse_size_breakdown(line: &str) -> Option<SizeBreakdown> {
+    let summary = &line[line.find("video:")?..];
+    let (streams, overhead) = summary.split_once("muxing overhead:")?;
+    let field = |name: &str| -> Option<u64> {
+        let value = streams.split_once(name)?.1.split_whitespace().next()?;
+        let number = value.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+        let scale = if value.ends_with("kB") || value.ends_with("KiB") { 1024.0 } else { 1.0 };
+        Some((number.parse::<f64>().ok()? * scale).round() as u64)
+    };
+    let video = field("video:")?;
+    let audio = field("audio:")?;
+    let subtitle = field("subtitle:").unwrap_or(0);
+    let other = field("other streams:").unwrap_or(0) + field("global headers:").unwrap_or(0);
+    // "unknown" when the muxer doesn't report it, e.g. for -f null
+    let percent = overhead.trim().trim_end_matches('%').parse::<f64>().unwrap_or(0.0);
+    let payload = video + audio + subtitle + other;
+    let overhead = (payload as f64 * percent / 100.0).round() as u64;
+    Some(SizeBreakdown { video, audio, subtitle, other, overhead })
+}
+
+/// NTSC frame rates and the exact rationals they stand for
+const NTSC_RATES: [(f32, &str); 4] = [
+    (23.976, "24000/1001"),
+    (29.97, "30000/1001"),
+    (47.952, "48000/1001"),
+    (59.94, "60000/1001"),
+];
+
+/// Format a frame rate for `-r`, using the exact fraction for NTSC rates so
+/// long encodes don't drift out of sync with the audio
+pub fn frame_rate_arg(fps: f32) -> String {
+    NTSC_RATES
+        .iter()
+        .find(|(rate, _)| (fps - rate).abs() < 0.005)
+        .map(|(_, fraction)| fraction.to_string())
+        .unwrap_or_else(|| format!("{:.3}", fps))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn long_timecodes_keep_their_milliseconds() {
+        let seconds = parse_timecode("10:00:00.001");
+        assert_eq!((seconds * 1000.0).round() as u64, 36_000_001);
+        assert_eq!(format_timestamp(seconds), "10:00:00.001");
+        // What keeping it in f32 lost: ten hours in leaves steps of about 4 ms
+        assert_eq!((f64::from(seconds as f32) * 1000.0).round() as u64, 36_000_000);
+    }
+    
+    #[test]
+    fn numbering_is_only_stripped_from_numbered_copies() {
+        let dir = std::env::temp_dir().join(format!("ffmpeg_gui_unique_path_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["holiday_2024.mp4", "trip_100.mp4", "clip.mp4", "clip_001.mp4"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+        let unique = |name: &str| unique_path(dir.join(name), NamingScheme::UnderscorePadded);
+        // Four digits are never a counter, and trip.mp4 doesn't exist to be numbered from
+        assert_eq!(unique("holiday_2024.mp4"), dir.join("holiday_2024_001.mp4"));
+        assert_eq!(unique("trip_100.mp4"), dir.join("trip_100_001.mp4"));
+        // A real copy of clip.mp4 numbers on from the original
+        assert_eq!(unique("clip_001.mp4"), dir.join("clip_002.mp4"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    
+    #[test]
+    fn frames_convert_at_ntsc_rates() {
+        assert!((frame_to_seconds(24, 23.976) - 24.0 / 23.976).abs() < 1e-6);
+        assert!((frame_to_seconds(30, 29.97) - 30.0 / 29.97).abs() < 1e-6);
+        assert!((frame_to_seconds(1800, 29.97) - 60.06).abs() < 1e-4);
+        assert_eq!(frame_to_seconds(100, 0.0), 0.0);
+        assert_eq!(frame_to_seconds(100, -25.0), 0.0);
+    }
+    
+    #[test]
+    fn trim_points_parse_in_every_form() {
+        assert_eq!(parse_trim_point("01:02:03.500", TrimUnit::Timecode, 25.0), Some(3723.5));
+        assert_eq!(parse_trim_point("02:03", TrimUnit::Timecode, 25.0), Some(123.0));
+        assert_eq!(parse_trim_point("90.5", TrimUnit::Timecode, 25.0), Some(90.5));
+        assert_eq!(parse_trim_point(" 12 ", TrimUnit::Timecode, 25.0), Some(12.0));
+        assert_eq!(parse_trim_point("48", TrimUnit::Frame, 24.0), Some(2.0));
+    }
+    
+    #[test]
+    fn paths_lose_quotes_and_whitespace() {
+        assert_eq!(clean_path("\"/home/me/My Clip.mp4\""), "/home/me/My Clip.mp4");
+        assert_eq!(clean_path("'/home/me/clip.mp4'"), "/home/me/clip.mp4");
+        assert_eq!(clean_path("\"/home/me/clip.mp4'"), "\"/home/me/clip.mp4'");
+        assert_eq!(clean_path("/home/me/clip.mp4\r\n"), "/home/me/clip.mp4");
+        assert_eq!(clean_path("  \"/home/me/clip.mp4\" \n"), "/home/me/clip.mp4");
+    }
+    
+    #[test]
+    fn file_uris_become_paths() {
+        assert_eq!(clean_path("file:///home/me/My%20Clip%20%231.mp4\r\n"), "/home/me/My Clip #1.mp4");
+        assert_eq!(clean_path("file://localhost/tmp/caf%C3%A9.mkv"), "/tmp/café.mkv");
+        assert_eq!(clean_path("file:///tmp/100%.mp4"), "/tmp/100%.mp4");
+        assert_eq!(clean_path("file:///C:/Videos/a%20b.mp4"), "C:/Videos/a b.mp4");
+    }
+    
+    #[test]
+    fn windows_paths_are_kept() {
+        assert_eq!(clean_path("\"C:\\Users\\me\\clip.mp4\""), "C:\\Users\\me\\clip.mp4");
+        assert_eq!(clean_path("\\\\server\\share\\clip.mp4 "), "\\\\server\\share\\clip.mp4");
+    }
+    
+    #[test]
+    fn percent_signs_are_only_doubled_in_batch_files() {
+        let args = vec!["-i".to_string(), "C:\\100% done\\a.mp4".to_string()];
+        assert_eq!(command_line(&args, true), "ffmpeg -i \"C:\\100% done\\a.mp4\"");
+        assert_eq!(batch_file_line(&args, true), "ffmpeg -i \"C:\\100%% done\\a.mp4\"");
+        assert_eq!(batch_file_line(&args, false), "ffmpeg -i 'C:\\100% done\\a.mp4'");
+    }
+    
+    #[test]
+    fn shell_lines_keep_their_quoting() {
+        // As a piped output's downstream command or a post-encode command is typed
+        let line = if cfg!(windows) { "echo \"a  b\"" } else { "printf '%s' \"a  b\"" };
+        let output = run_shell(line).unwrap();
+        let expected = if cfg!(windows) { "\"a  b\"" } else { "a  b" };
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim_end(), expected);
+    }
+    
+    #[test]
+    fn bad_trim_points_give_none() {
+        for text in ["", "   ", "-5", "1:-2", "abc", "1:xx", "1::2"] {
+            assert_eq!(parse_trim_point(text, TrimUnit::Timecode, 25.0), None, "{:?}", text);
+        }
+        for text in ["", "-3", "1.5", "frame"] {
+            assert_eq!(parse_trim_point(text, TrimUnit::Frame, 25.0), None, "{:?}", text);
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use eframe::egui;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+use crate::ffmpeg_utils;
+
+/// Which trim point the preview is showing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrimPoint {
+    Start,
+    End,
+}
+
+/// A single decoded frame at a trim point, extracted in the background so stepping
+/// through frames doesn't stall the UI
+pub struct FramePreview {
+    pub point: TrimPoint,
+    pub seconds: f64,
+    pub frame: u64,
+    texture: Option<egui::TextureHandle>,
+    receiver: Option<Receiver<Option<Vec<u8>>>>,
+    failed: bool,
+}
+
+impl FramePreview {
+    pub fn new(point: TrimPoint) -> Self {
+        Self { point, seconds: 0.0, frame: 0, texture: None, receiver: None, failed: false }
+    }
+
+    /// Start extracting frame number `frame`; the previous image stays up until it arrives
+    pub fn request(&mut self, ctx: &egui::Context, input: &Path, frame: u64, fps: f32) {
+        self.frame = frame;
+        self.seconds = ffmpeg_utils::frame_to_seconds(frame, fps);
+        let (sender, receiver) = mpsc::channel();
+        let input = input.to_path_buf();
+        let seconds = self.seconds;
+        let ctx = ctx.clone();
+        // -ss before -i decodes up to the exact timestamp, so this is the frame a re-encode starts on
+        std::thread::spawn(move || {
+            let _ = sender.send(ffmpeg_utils::extract_frame_rgba(&input, seconds, ffmpeg_utils::PREVIEW_SIZE));
+            ctx.request_repaint();
+        });
+        // Replacing the receiver drops any older request still running
+        self.receiver = Some(receiver);
+    }
+
+    pub fn loading(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    /// Pick up a finished extraction
+    pub fn poll(&mut self, ctx: &egui::Context) {
+        let Some(receiver) = &self.receiver else {
+            return;
+        };
+        match receiver.try_recv() {
+            Ok(Some(rgba)) => {
+                let [width, height] = ffmpeg_utils::PREVIEW_SIZE;
+                let image = egui::ColorImage::from_rgba_unmultiplied([width as usize, height as usize], &rgba);
+                self.texture = Some(ctx.load_texture("trim_frame", image, egui::TextureOptions::LINEAR));
+                self.failed = false;
+            }
+            Ok(None) | Err(TryRecvError::Disconnected) => self.failed = true,
+            Err(TryRecvError::Empty) => return,
+        }
+        self.receiver = None;
+    }
+
+    pub fn show(&self, ui: &mut egui::Ui) {
+        if let Some(texture) = &self.texture {
+            ui.image((texture.id(), texture.size_vec2()));
+        }
+        if self.failed {
+            ui.colored_label(egui::Color32::RED, "Couldn't extract a frame at this position");
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::enums::GifDither;
+use crate::ffmpeg_utils;
+
+/// How a GIF is drawn, resolved from the UI when the job is built. With `palette` set, a
+/// first pass works out the best colours for the clip and the encode maps onto them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GifPlan {
+    pub fps: f32,
+    pub width: u32,                             // 0 keeps the source width
+    pub max_colors: u32,
+    pub dither: GifDither,
+    pub palette: bool,
+    pub trim: Option<(Option<f64>, Option<f64>)>, // The same section the encode reads
+}
+
+/// Palette image generated for an output, passed to FFmpeg as a second input
+pub fn palette_path(output: &Path) -> PathBuf {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    std::env::temp_dir().join(format!("{}.palette.png", stem))
+}
+
+impl GifPlan {
+    /// Frame rate and size, shared by both passes so the palette fits the frames it's used on
+    fn frame_filters(&self) -> String {
+        let mut filters = format!("fps={}", self.fps);
+        if self.width > 0 {
+            filters.push_str(&format!(",scale={}:-1:flags=lanczos", self.width));
+        }
+        filters
+    }
+    
+    fn palettegen(&self) -> String {
+        format!("palettegen=max_colors={}", self.max_colors)
+    }
+    
+    fn paletteuse(&self) -> String {
+        format!("paletteuse=dither={}", self.dither.filter_value())
+    }
+    
+    /// Filter graph for the encode. With a palette it comes in as input 1, or `inline`
+    /// generates it within the same run, which needs the whole clip buffered first.
+    pub fn filter_graph(&self, inline: bool) -> String {
+        let frames = self.frame_filters();
+        match (self.palette, inline) {
+            (false, _) => format!("[0:v]{}", frames),
+            (true, false) => format!("[0:v]{}[x];[x][1:v]{}", frames, self.paletteuse()),
+            (true, true) => format!("[0:v]{},split[a][b];[a]{}[p];[b][p]{}", frames, self.palettegen(), self.paletteuse()),
+        }
+    }
+    
+    /// Arguments for the first pass, which analyses the clip and writes its palette for `output`
+    pub fn palette_args(&self, input: &Path, output: &Path) -> Vec<String> {
+        let mut args = vec!["-nostdin".to_string(), "-hide_banner".to_string()];
+        args.extend(ffmpeg_utils::trim_args(self.trim));
+        args.extend([
+            "-i".to_string(), input.display().to_string(),
+            "-vf".to_string(), format!("{},{}", self.frame_filters(), self.palettegen()),
+            "-y".to_string(), palette_path(output).display().to_string(),
+        ]);
+        args
+    }
+}
+
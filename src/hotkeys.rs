@@ -0,0 +1,85 @@
+use eframe::egui;
+use global_hotkey::hotkey::HotKey;
+use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+use std::sync::mpsc::{self, Receiver};
+
+/// What a global hotkey asks the app to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotkeyAction {
+    Start,
+    Stop,
+}
+
+impl HotkeyAction {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Start => "Start",
+            Self::Stop => "Stop",
+        }
+    }
+}
+
+/// System-wide Start/Stop shortcuts, which work while the window is unfocused
+pub struct GlobalHotkeys {
+    manager: GlobalHotKeyManager,
+    receiver: Receiver<GlobalHotKeyEvent>,
+    registered: Vec<(HotKey, HotkeyAction)>,
+    config: Option<(bool, String, String)>,    // Settings the current registrations were made for
+}
+
+impl GlobalHotkeys {
+    /// Set up the platform hotkey listener. Only one can exist per process.
+    pub fn new(ctx: egui::Context) -> anyhow::Result<Self> {
+        let manager = GlobalHotKeyManager::new()?;
+        let (sender, receiver) = mpsc::channel();
+        // The window may be unfocused and idle, so wake it to handle the key press
+        GlobalHotKeyEvent::set_event_handler(Some(move |event| {
+            let _ = sender.send(event);
+            ctx.request_repaint();
+        }));
+        Ok(Self { manager, receiver, registered: Vec::new(), config: None })
+    }
+    
+    /// Bring the registered hotkeys in line with the settings. Returns a message for each
+    /// hotkey that couldn't be registered (invalid, or already taken by another program).
+    pub fn sync(&mut self, enabled: bool, start: &str, stop: &str) -> Vec<String> {
+        let config = (enabled, start.trim().to_string(), stop.trim().to_string());
+        if self.config.as_ref() == Some(&config) {
+            return Vec::new();
+        }
+        self.config = Some(config);
+        
+        for (hotkey, _) in self.registered.drain(..) {
+            let _ = self.manager.unregister(hotkey);
+        }
+        if !enabled {
+            return Vec::new();
+        }
+        
+        let mut problems = Vec::new();
+        for (text, action) in [(start.trim(), HotkeyAction::Start), (stop.trim(), HotkeyAction::Stop)] {
+            if text.is_empty() {
+                continue;
+            }
+            let registered = text.parse::<HotKey>()
+                .map_err(|e| e.to_string())
+                .and_then(|hotkey| self.manager.register(hotkey).map(|()| hotkey).map_err(|e| e.to_string()));
+            match registered {
+                Ok(hotkey) => self.registered.push((hotkey, action)),
+                Err(e) => problems.push(format!("{} hotkey \"{}\" could not be registered: {}", action.display_name(), text, e)),
+            }
+        }
+        problems
+    }
+    
+    /// Actions for the hotkeys pressed since the last call
+    pub fn poll(&self) -> Vec<HotkeyAction> {
+        self.receiver
+            .try_iter()
+            .filter(|event| event.state == HotKeyState::Pressed)
+            .filter_map(|event| {
+                self.registered.iter().find(|(hotkey, _)| hotkey.id() == event.id).map(|&(_, action)| action)
+            })
+            .collect()
+    }
+}
@@ -1,48 +1,171 @@
 mod enums;
 mod ffmpeg_utils;
 mod app_state;
+mod batch;
+mod settings;
+mod probe;
+mod benchmark;
+mod chapters;
+mod presets;
+mod subtitles;
+mod dialogs;
+mod taskbar;
+mod hotkeys;
+mod report;
+mod frame_preview;
+mod gif;
+mod separate;
+mod crop;
+mod two_pass;
 
-use eframe::egui::{self, ScrollArea, Slider};
-use std::io::{BufRead, BufReader};
+use eframe::egui::{self, ScrollArea};
+use parking_lot::RwLock;
+use std::io::{BufRead, BufReader, Read};
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
-use std::sync::Arc;
+use std::process::{Child, ChildStderr, ChildStdout, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
 
-use app_state::MyApp;
-use enums::{AudioFormat, FunctionType, FrameRateMode, OutputFormat};
-use ffmpeg_utils::parse_timecode;
+use app_state::{AudioTrackTags, CropDetectJob, FrameCountJob, LastEncode, LiveProgress, MyApp, OPUS_CUTOFFS, ProbeOutcome, SEEK_FRIENDLY_GOP};
+use batch::{BatchItem, BatchJob, ItemStatus, SettingsJob};
+use benchmark::{AbResult, BenchmarkResult};
+use chapters::ChapterPlan;
+use gif::GifPlan;
+use separate::SeparatePlan;
+use two_pass::TwoPassPlan;
+use crop::CropTally;
+use dialogs::{DialogOutcome, DialogTarget, PendingDialog};
+use frame_preview::{FramePreview, TrimPoint};
+use hotkeys::{GlobalHotkeys, HotkeyAction};
+use presets::Preset;
+use probe::MediaInfo;
+use report::{EncodeReport, SourceSummary};
+use settings::Settings;
+use enums::{AacEncoder, AudioContainer, AudioFormat, CfrMethod, ChannelLayout, ChapterMode, ColorRange, CoverArt, CreationTime, FunctionType, FrameRateMode, GifDither, NamingScheme, OutputFormat, QualityMode, Rotation, SizeLimit, SubtitleMode, TrimUnit, UiMode, VideoEncoder};
+use ffmpeg_utils::{SizeBreakdown, parse_timecode};
 
 fn main() -> Result<(), eframe::Error> {
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([1000.0, 700.0])
-            .with_min_inner_size([800.0, 600.0]),
+            .with_min_inner_size([640.0, 480.0]),
         ..Default::default()
     };
 
     eframe::run_native(
-        "FFmpeg GUI",
+        taskbar::APP_TITLE,
         options,
         Box::new(|_cc| {
-            let mut app = MyApp::default();
+            let (settings, load_error) = match Settings::load() {
+                Ok(settings) => (settings, None),
+                Err(e) => (Settings::default(), Some(e)),
+            };
+            let mut app = MyApp {
+                saved_settings: settings.clone(),
+                settings,
+                pending_resume: BatchJob::load_unfinished(),
+                available_encoders: ffmpeg_utils::list_encoders(),
+                available_filters: ffmpeg_utils::list_filters(),
+                display_rotation: ffmpeg_utils::has_display_rotation(),
+                ..Default::default()
+            };
+            if let Some(e) = load_error {
+                app.output_log.write().push_str(&format!("Note: settings were reset to defaults: {}\n", e));
+            }
             app.update_command();
             Box::new(app)
         }),
     )
 }
 
+/// One output of a batch run: input, output, output height, audio format and position in the queue
+type PlannedOutput = (PathBuf, PathBuf, Option<u32>, AudioFormat, Option<usize>);
+
+/// Handles a background encode uses to report back to the UI
+#[derive(Clone)]
+struct JobHandles {
+    log: Arc<RwLock<String>>,
+    progress: Arc<RwLock<f32>>,
+    eta: Arc<RwLock<Option<Duration>>>,
+    live_progress: Arc<RwLock<Option<LiveProgress>>>,
+    running: Arc<RwLock<bool>>,
+    child: Arc<Mutex<Option<Child>>>,
+    last_encode: Arc<RwLock<Option<LastEncode>>>,
+    output_sizes: Arc<RwLock<Option<(PathBuf, SizeBreakdown)>>>,
+    run_sizes: Arc<RwLock<Option<SizeBreakdown>>>, // This job's latest FFmpeg summary line, not shared with the UI
+    smooth_progress: bool,
+    background_priority: bool,
+    stall_timeout: Option<Duration>,    // No progress for this long counts as a stall
+    kill_stalled: bool,
+    post_command: String,
+    ctx: egui::Context,
+}
+
 impl MyApp {
     // The build_command method has been moved to app_state.rs
 
-    // Using the probe_duration implementation at the end of the file
+    // Probing is started and applied at the end of the file
+
+    /// A job is running, or a stopped one hasn't exited yet; either way nothing new may start
+    fn busy(&self) -> bool {
+        *self.running.read() || *self.stopping.read()
+    }
+
+    fn job_handles(&self, ctx: egui::Context) -> JobHandles {
+        JobHandles {
+            log: self.output_log.clone(),
+            progress: self.progress.clone(),
+            eta: self.eta.clone(),
+            live_progress: self.live_progress.clone(),
+            running: self.running.clone(),
+            child: self.child.clone(),
+            last_encode: self.last_encode.clone(),
+            output_sizes: self.output_sizes.clone(),
+            run_sizes: Arc::default(),
+            smooth_progress: self.settings.smooth_progress,
+            background_priority: self.settings.background_priority,
+            stall_timeout: (self.settings.stall_timeout_secs > 0).then(|| Duration::from_secs(self.settings.stall_timeout_secs.into())),
+            kill_stalled: self.settings.kill_stalled,
+            post_command: self.settings.post_command.trim().to_string(),
+            ctx,
+        }
+    }
 
     fn run(&mut self, ctx: egui::Context) {
         // Check if a process is already running
-        if *self.running.read() {
+        if self.busy() {
             self.output_log.write().push_str("A process is already running. Please stop it first.\n");
             return;
         }
         
+        if self.pipe_active() && (!self.batch_queue.is_empty() || self.ladder_active() || self.multi_format_active() || self.split_chapters().is_some()) {
+            self.output_log.write().push_str("Error: Piping to stdout works for a single input and output only.\n");
+            return;
+        }
+        
+        // Strip stray quotes and whitespace from pasted paths before they are checked
+        self.input_path = ffmpeg_utils::clean_path(&self.input_path);
+        self.output_path = ffmpeg_utils::clean_path(&self.output_path);
+        self.audio_input_path = ffmpeg_utils::clean_path(&self.audio_input_path);
+        self.update_command();
+        
+        if let Some(problem) = self.container_problem() {
+            self.output_log.write().push_str(&format!("Error: {}. Pick a different output format or codec.\n", problem));
+            return;
+        }
+        
+        if let Some(problem) = self.size_limit_problem() {
+            self.output_log.write().push_str(&format!("Error: {}.\n", problem));
+            return;
+        }
+        
+        // A non-empty queue takes precedence over the single input file
+        if !self.batch_queue.is_empty() {
+            self.run_batch(ctx);
+            return;
+        }
+        
         // Validate input file
         if self.input_path.is_empty() {
             self.output_log.write().push_str("Error: No input file selected.\n");
@@ -54,703 +177,4760 @@ impl MyApp {
             return;
         }
         
+        // Settings like smart copy and the duration depend on the probe
+        if self.probing {
+            self.output_log.write().push_str("Still probing the input file, please try again in a moment.\n");
+            return;
+        }
+        
+        if self.selected_function == FunctionType::LoopClip
+            && let Some(problem) = self.loop_problem()
+        {
+            self.output_log.write().push_str(&format!("Error: {}.\n", problem));
+            return;
+        }
+        
+        if let Err(e) = ffmpeg_utils::split_args(&self.settings.global_options) {
+            self.output_log.write().push_str(&format!("Error: Could not parse the global options: {}\n", e));
+            return;
+        }
+        if let Err(e) = ffmpeg_utils::split_args(&self.extra_options) {
+            self.output_log.write().push_str(&format!("Error: Could not parse the extra output options: {}\n", e));
+            return;
+        }
+        if let Err(e) = ffmpeg_utils::split_args(&self.filter_maps) {
+            self.output_log.write().push_str(&format!("Error: Could not parse the filter graph maps: {}\n", e));
+            return;
+        }
+        
+        // Refuse inputs ffprobe found nothing in, rather than letting FFmpeg fail cryptically
+        if self.probed_path == self.input_path
+            && self.media_info.as_ref().is_some_and(MediaInfo::looks_corrupt)
+        {
+            self.output_log.write().push_str("Error: Input appears to be corrupt or not a media file.\n");
+            return;
+        }
+        
+        if self.uses_audio_input() && !Path::new(&self.audio_input_path).exists() {
+            self.output_log.write().push_str(&format!("Error: Audio file does not exist: {}\n", self.audio_input_path));
+            return;
+        }
+        
+        if self.active_cover_art() == CoverArt::File && !Path::new(&self.cover_path).exists() {
+            self.output_log.write().push_str(&format!("Error: Cover image does not exist: {}\n", self.cover_path));
+            return;
+        }
+        
+        if self.creation_time == CreationTime::Custom && !ffmpeg_utils::is_iso8601(self.custom_creation_time.trim()) {
+            self.output_log.write().push_str(&format!(
+                "Error: Creation time \"{}\" is not an ISO 8601 date and time, e.g. 2024-05-01T12:30:00Z.\n",
+                self.custom_creation_time.trim()
+            ));
+            return;
+        }
+        
+        if self.multi_format_active() && self.split_chapters().is_some() {
+            self.output_log.write().push_str("Error: Splitting into tracks writes a single format. Untick the extra formats to split.\n");
+            return;
+        }
+        
+        // A resolution ladder runs as a batch with one item per rung, and extracting
+        // several audio formats with one per format
+        if self.ladder_active() || self.multi_format_active() {
+            self.run_batch(ctx);
+            return;
+        }
+        
+        // Likewise splitting by chapters, with one item per chapter
+        if self.split_chapters().is_some() {
+            self.run_chapter_split(ctx);
+            return;
+        }
+        
+        // Piped output has no file to check
+        if self.pipe_active() {
+            self.run_piped(ctx);
+            return;
+        }
+        
         // Validate and ensure unique output path
         if self.output_path.is_empty() {
             self.output_path = self.default_output();
         }
         
-        // Check if output path exists and make it unique if needed
-        let output_path = Path::new(&self.output_path);
-        if output_path.exists() {
-            // Generate a unique path
-            let unique_path = ffmpeg_utils::unique_path(output_path.to_path_buf());
-            self.output_path = unique_path.display().to_string();
+        // Settle the extension now, in case the path was typed and Start clicked straight away
+        self.update_command();
+        let ext_note = self.output_ext_note.clone();
+        
+        // Number the output if the name is taken
+        let resolved = self.resolve_output_path(PathBuf::from(&self.output_path));
+        if resolved != Path::new(&self.output_path) {
+            self.output_log.write().push_str(&format!("{} already exists, saving as {}\n", self.output_path, resolved.display()));
+            self.output_path = resolved.display().to_string();
+            self.update_command();
+        }
+        
+        if !self.create_test_dir(Path::new(&self.output_path)) {
+            return;
         }
         
         // Validate output directory exists and is writable
-        if let Some(parent) = Path::new(&self.output_path).parent() {
-            if !parent.exists() {
-                self.output_log.write().push_str(&format!("Error: Output directory does not exist: {}\n", parent.display()));
-                *self.running.write() = false;
-                return;
-            }
+        if let Some(parent) = Path::new(&self.output_path).parent()
+            && !parent.exists()
+        {
+            self.output_log.write().push_str(&format!("Error: Output directory does not exist: {}\n", parent.display()));
+            *self.running.write() = false;
+            return;
+        }
+        
+        // Fail now rather than after a long encode if the drive or share can't be written to
+        let output_dir = Path::new(&self.output_path).parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
+        if let Err(e) = ffmpeg_utils::check_writable(output_dir) {
+            self.output_log.write().push_str(&format!("Error: Cannot write to the output folder {}: {}\n", output_dir.display(), e));
+            return;
         }
         
         // Mark process as running and reset progress
         *self.running.write() = true;
         *self.progress.write() = 0.0;
+        self.encode_started = Some(Instant::now());
         self.output_log.write().clear();
 
-        // Get the validated output path
+        // Get the validated input and output paths
+        let input_path = PathBuf::from(self.input_path.clone());
         let final_output_path = PathBuf::from(self.output_path.clone());
 
+        self.partial_output = Some(final_output_path.clone());
+
         // Log output destination
         self.output_log.write().push_str(&format!("Outputting to: {}\n", final_output_path.display()));
+        if let Some(note) = ext_note {
+            self.output_log.write().push_str(&format!("Note: {}\n", note));
+        }
 
+        // Log whether smart copy kicked in, since it changes what the output will be
+        if self.selected_function == FunctionType::CompressVideo && self.smart_copy && !self.remove_video {
+            let decision = match self.reencode_reason(&self.input_path, self.scale_height) {
+                Some(reason) => format!("Re-encoding video: {}\n", reason),
+                None => "Source already matches the video settings, copying the video stream\n".to_string(),
+            };
+            self.output_log.write().push_str(&decision);
+        }
+        
         // Build the FFmpeg command
         let cmd_args = self.build_command();
         self.update_command();
 
         // Clone necessary state for the background thread
-        let log = self.output_log.clone();
-        let progress_arc = self.progress.clone();
-        let running_arc = self.running.clone();
-        let child_arc = self.child.clone();
-        let duration = self.duration;
-        let ctx = Arc::new(ctx);
+        let handles = self.job_handles(ctx);
+        let duration = self.job_duration();
         
         // Make sure child process reference is cleared before starting a new one
         if let Ok(mut child_guard) = self.child.lock() {
             *child_guard = None;
         }
 
+        let separate = self.separate_plan(&self.input_path, &self.output_path);
+        if let Some(plan) = &separate {
+            self.register_temp(plan.audio_path.clone());
+            self.register_temp(ffmpeg_utils::temp_output_path(&plan.audio_path));
+            self.register_temp(plan.video_path.clone());
+        }
+        // Separate streams run the video step's two passes themselves
+        let two_pass = match &separate {
+            Some(plan) => plan.video_passes.clone(),
+            None => self.two_pass_plan(&cmd_args, &final_output_path),
+        };
+        if let Some(plan) = &two_pass {
+            for file in plan.log_files() {
+                self.register_temp(file);
+            }
+        }
+        // With separate streams, the chapters go into the intermediate video
+        let chapter_output = separate.as_ref().map_or(final_output_path.clone(), |plan| plan.video_path.clone());
+        let chapters = self.chapter_plan();
+        if chapters.is_some() {
+            self.register_temp(chapters::metadata_path(&chapter_output));
+        }
+        let palette = self.palette_plan();
+        if palette.is_some() {
+            self.register_temp(gif::palette_path(&final_output_path));
+        }
+        
+        // Scoring compares pictures, so it needs a video output; None when it's off
+        let score_segment = self.trim_range()
+            .and_then(|(start, _)| Some((start.unwrap_or(0.0), self.trimmed_duration(self.duration)?)));
+        let scores_video = self.selected_function == FunctionType::CompressVideo && !self.remove_video;
+        let score_with_vmaf = (self.score_after_encode && scores_video).then(|| self.has_filter("libvmaf"));
+        
+        // Provenance for the finished file; sizes and timing are filled in afterwards
+        let source_start = ffmpeg_utils::input_start(&cmd_args);
+        
+        let report = self.settings.write_report.then(|| EncodeReport {
+            input: input_path.clone(),
+            output: final_output_path.clone(),
+            command: cmd_args.clone(),
+            input_size: None,
+            output_size: None,
+            duration_secs: duration,
+            elapsed_secs: 0.0,
+            settings: self.encode_settings(),
+            source: self.probed_input_info().map(SourceSummary::from_info),
+        });
+        
         // Spawn a background thread to run FFmpeg
         std::thread::spawn(move || {
-            log.write().push_str(&format!("Executing: ffmpeg {}\n", cmd_args.join(" ")));
+            let started = Instant::now();
+            if let Some(plan) = chapters
+                && !prepare_chapters(&plan, &input_path, &chapter_output, duration, &handles)
+            {
+                *handles.running.write() = false;
+                handles.ctx.request_repaint();
+                return;
+            }
+            if let Some(plan) = &palette
+                && !prepare_palette(plan, &input_path, &final_output_path, &handles)
+            {
+                *handles.running.write() = false;
+                handles.ctx.request_repaint();
+                return;
+            }
+            // Stopped while a pass above ran: the encode mustn't start after all
+            if !*handles.running.read() {
+                if chapters.is_some() {
+                    let _ = std::fs::remove_file(chapters::metadata_path(&chapter_output));
+                }
+                if palette.is_some() {
+                    let _ = std::fs::remove_file(gif::palette_path(&final_output_path));
+                }
+                handles.ctx.request_repaint();
+                return;
+            }
             
-            // Create and spawn the FFmpeg process
-            let mut child = Command::new("ffmpeg")
-                .args(&cmd_args)
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .spawn()
-                .expect("Failed to spawn ffmpeg process");
-
-            // Capture stderr for progress monitoring
-            if let Some(stderr) = child.stderr.take() {
-                // Store the child process for potential cancellation
-                *child_arc.lock().unwrap() = Some(child);
-                
-                // Create a new thread to process stderr output
-                let ctx_clone = ctx.clone();
-                let log_clone = log.clone();
-                let progress_clone = progress_arc.clone();
-                let duration_clone = duration;
-                
-                std::thread::spawn(move || {
-                    let reader = BufReader::new(stderr);
-                    for line in reader.lines() {
-                        if let Ok(line_content) = line {
-                            // Add line to log with newline
-                            log_clone.write().push_str(&format!("{line_content}\n"));
-                            
-                            // Parse progress information
-                            if line_content.contains("time=") {
-                                if let Some(start) = line_content.find("time=") {
-                                    let time_str = line_content[start + 5..]
-                                        .split_whitespace()
-                                        .next()
-                                        .unwrap_or("00:00:00.00");
-                                    let current_time = parse_timecode(time_str);
-                                    let progress = (current_time / duration_clone).clamp(0.0, 1.0);
-                                    
-                                    // Update progress and log it for debugging
-                                    *progress_clone.write() = progress;
-                                    
-                                    // Force UI update
-                                    ctx_clone.request_repaint();
-                                }
-                            }
+            let result = match (&separate, &two_pass) {
+                (Some(plan), _) => run_separate(plan, duration, &handles),
+                (None, Some(plan)) => run_two_pass(plan, duration, &handles),
+                (None, None) => run_ffmpeg(&cmd_args, duration, &handles),
+            };
+            if chapters.is_some() {
+                let _ = std::fs::remove_file(chapters::metadata_path(&chapter_output));
+            }
+            if palette.is_some() {
+                let _ = std::fs::remove_file(gif::palette_path(&final_output_path));
+            }
+            
+            if let Some(status) = result {
+                handles.log.write().push_str(&format!("FFmpeg finished with status: {}\n", status));
+                if status.success() {
+                    handles.log.write().push_str(&format!("Output successfully saved to {}\n", final_output_path.display()));
+                    if let Some(report) = report {
+                        match report.finish(started.elapsed()) {
+                            Ok(path) => handles.log.write().push_str(&format!("Report written to {}\n", path.display())),
+                            Err(e) => handles.log.write().push_str(&format!("Warning: could not write the encode report: {}\n", e)),
                         }
                     }
-                });
-                
-                // Wait for the process to complete
-                if let Ok(mut guard) = child_arc.lock() {
-                    if let Some(ref mut child_process) = *guard {
-                        if let Ok(status) = child_process.wait() {
-                            log.write().push_str(&format!("FFmpeg finished with status: {}\n", status));
-                            if status.success() {
-                                log.write().push_str(&format!("Output successfully saved to {}\n", final_output_path.display()));
-                            } else {
-                                log.write().push_str("FFmpeg command failed.\n");
-                            }
-                        }
+                    if let Some(vmaf) = score_with_vmaf {
+                        score_against_source(&final_output_path, &input_path, score_segment, vmaf, &handles);
                     }
-                    *guard = None; // Clear child process reference
+                    run_post_command(&input_path, &final_output_path, &handles);
+                    *handles.output_sizes.write() = handles.run_sizes.read().map(|sizes| (final_output_path.clone(), sizes));
+                    *handles.last_encode.write() = Some(LastEncode {
+                        input: input_path,
+                        output: final_output_path,
+                        source_start,
+                        length: duration,
+                        finished: Instant::now(),
+                    });
+                    handles.log.write().push_str(&format!("Finished in {}\n", ffmpeg_utils::format_duration(started.elapsed())));
+                } else {
+                    handles.log.write().push_str("FFmpeg command failed.\n");
                 }
-            } else {
-                log.write().push_str("Failed to capture FFmpeg output.\n");
             }
             
             // Mark process as complete
-            *running_arc.write() = false;
-            *progress_arc.write() = 1.0; // Set progress to 100%
-            ctx.request_repaint(); // Update the UI
+            *handles.running.write() = false;
+            *handles.progress.write() = 1.0; // Set progress to 100%
+            handles.ctx.request_repaint(); // Update the UI
         });
     }
-}
-
-impl eframe::App for MyApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        if self.duration == 1.0 && Path::new(&self.input_path).exists() {
-            self.probe_duration();
+    
+    /// Expected length of the output, for progress reporting; None when it can't be known
+    fn job_duration(&self) -> Option<f64> {
+        if self.selected_function.needs_audio_input() {
+            // The output is as long as the audio, not the (still image) input
+            ffmpeg_utils::probe_duration(Path::new(&self.audio_input_path)).filter(|&duration| duration > 0.0)
+        } else if self.selected_function == FunctionType::LoopClip {
+            // The crossfade overlaps the end with the start, shortening the clip
+            self.trimmed_duration(self.duration).map(|duration| (duration - self.loop_crossfade).max(0.1))
+        } else {
+            self.trimmed_duration(self.duration)
         }
-
-        let _running = *self.running.read();
-        let _progress = *self.progress.read();
-
-        // Main layout with a top panel for controls and a central panel for content
-        egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
-            ui.horizontal(|ui| {
-                ui.heading("FFmpeg GUI");
-                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    let running = *self.running.read();
-                    if ui.add_enabled(!running, egui::Button::new("Start").min_size(egui::vec2(80.0, 0.0))).clicked() {
-                        self.run(ctx.clone());
-                    }
-                    if ui.add_enabled(running, egui::Button::new("Stop").min_size(egui::vec2(80.0, 0.0))).clicked() {
-                        self.stop_ffmpeg();
-                    }
-                });
-            });
+    }
+    
+    /// Run the current command with its output on stdout, feeding it to the downstream command
+    fn run_piped(&mut self, ctx: egui::Context) {
+        *self.running.write() = true;
+        self.partial_output = None;
+        *self.progress.write() = 0.0;
+        self.output_log.write().clear();
+        
+        self.encode_started = Some(Instant::now());
+        
+        let downstream = self.pipe_command.trim().to_string();
+        if downstream.is_empty() {
+            self.output_log.write().push_str("Piping output to stdout; no downstream command, so the bytes are only counted.\n");
+        } else {
+            self.output_log.write().push_str(&format!("Piping output to: {}\n", downstream));
+        }
+        
+        let cmd_args = self.build_command();
+        self.update_command();
+        let handles = self.job_handles(ctx);
+        let duration = self.job_duration();
+        if let Ok(mut child_guard) = self.child.lock() {
+            *child_guard = None;
+        }
+        
+        std::thread::spawn(move || {
+            let started = Instant::now();
+            if let Some(status) = run_ffmpeg_to(&cmd_args, duration, &handles, Some(&downstream)) {
+                handles.log.write().push_str(&format!("FFmpeg finished with status: {}\n", status));
+                if status.success() {
+                    handles.log.write().push_str(&format!("Finished in {}\n", ffmpeg_utils::format_duration(started.elapsed())));
+                } else {
+                    handles.log.write().push_str("FFmpeg command failed.\n");
+                }
+            }
+            *handles.running.write() = false;
+            *handles.progress.write() = 1.0;
+            handles.ctx.request_repaint();
         });
-
-        egui::CentralPanel::default().show(ctx, |ui| {
-
-            // Input file selection
-            ui.horizontal(|ui| {
-                ui.label("Input file:");
-                ui.text_edit_singleline(&mut self.input_path);
-                if ui.button("Browse").clicked() {
-                    if let Some(path) = rfd::FileDialog::new()
-                        .add_filter("Media files", &["mp4", "mkv", "mov", "avi", "mp3", "wav"])
-                        .pick_file()
-                    {
-                        self.input_path = path.display().to_string();
-                        self.output_path = self.default_output();
-                        self.update_command();
-                    }
+    }
+    
+    /// Every output a batch run would produce: one per queued file (or the single input)
+    /// and resolution ladder rung or extracted format
+    fn batch_plan(&self) -> Vec<PlannedOutput> {
+        let inputs: Vec<(PathBuf, Option<usize>)> = if self.batch_queue.is_empty() {
+            vec![(PathBuf::from(&self.input_path), None)]
+        } else {
+            self.batch_queue.iter().cloned().zip((0..).map(Some)).collect()
+        };
+        let rungs: Vec<Option<u32>> = if self.ladder_active() {
+            self.ladder_heights.iter().copied().map(Some).collect()
+        } else {
+            vec![self.scale_height]
+        };
+        
+        let mut plan = Vec::new();
+        for (input, queue_index) in inputs {
+            let base_output = if self.batch_queue.is_empty() && !self.output_path.is_empty() {
+                PathBuf::from(&self.output_path)
+            } else {
+                PathBuf::from(self.default_output_for(&input))
+            };
+            
+            for &rung in &rungs {
+                let output = match rung {
+                    Some(height) if self.ladder_active() => {
+                        ffmpeg_utils::with_stem_suffix(&base_output, &format!("_{}", ffmpeg_utils::resolution_label(height)))
+                    },
+                    _ => base_output.clone(),
+                };
+                // Each extra format is named by its own extension
+                for format in self.extraction_formats() {
+                    let output = if format == self.audio_format {
+                        output.clone()
+                    } else {
+                        output.with_extension(self.audio_ext_for(format))
+                    };
+                    plan.push((input.clone(), self.resolve_output_path(output), rung, format, queue_index));
                 }
-            });
-
-            // Output file selection
-            ui.horizontal(|ui| {
-                ui.label("Output file:");
-                ui.text_edit_singleline(&mut self.output_path);
-                if ui.button("Browse").clicked() {
-                    if let Some(path) = rfd::FileDialog::new()
-                        .add_filter("Output file", &[self.output_format.ext()])
-                        .set_file_name(&self.output_path)
-                        .save_file()
-                    {
-                        self.output_path = path.display().to_string();
-                        self.update_command();
-                    }
+            }
+        }
+        plan
+    }
+    
+    /// Start a fresh batch from the queued files using the current settings
+    fn run_batch(&mut self, ctx: egui::Context) {
+        // Outputs that don't exist yet all resolve to the same free name, so two inputs
+        // with the same stem would only find out, by one overwriting the other, mid-batch
+        let mut plan = self.batch_plan();
+        let outputs: Vec<&Path> = plan.iter().map(|(_, output, ..)| output.as_path()).collect();
+        let collisions = ffmpeg_utils::colliding_outputs(&outputs);
+        let rename = std::mem::take(&mut self.rename_collisions);
+        if !collisions.is_empty() && !rename {
+            let mut log = self.output_log.write();
+            log.push_str(&format!("Error: {} output name(s) would be written by more than one input:\n", collisions.len()));
+            for group in &collisions {
+                log.push_str(&format!("  {}\n", plan[group[0]].1.display()));
+                for &i in group {
+                    log.push_str(&format!("    from {}\n", plan[i].0.display()));
                 }
+            }
+            self.batch_collisions = collisions.iter().map(|group| plan[group[0]].1.clone()).collect();
+            return;
+        }
+        self.batch_collisions.clear();
+        for group in &collisions {
+            for (position, &i) in group.iter().enumerate() {
+                let suffix = collision_suffix(&plan, group, position);
+                plan[i].1 = self.resolve_output_path(ffmpeg_utils::with_stem_suffix(&plan[i].1, &suffix));
+                self.output_log.write().push_str(&format!("Writing {} to {}\n", plan[i].0.display(), plan[i].1.display()));
+            }
+        }
+        
+        let mut job = BatchJob::default();
+        // A trimmed item's length, for progress and chapters; otherwise the whole input's,
+        // probed when the item starts. Each input's rungs and formats share it.
+        let mut trimmed: Option<(PathBuf, Option<f64>)> = None;
+        for (input, output, rung, format, queue_index) in plan {
+            let temp_output = ffmpeg_utils::temp_output_path(&output);
+            let settings = self.settings_for_format(format);
+            let duration = match &trimmed {
+                Some((probed, duration)) if *probed == input => *duration,
+                _ => {
+                    let duration = self.trim_range().and_then(|_| self.trimmed_duration_of(&input));
+                    trimmed = Some((input.clone(), duration));
+                    duration
+                },
+            };
+            job.items.push(BatchItem {
+                args: self.with_encode_settings(&settings, |app| {
+                    app.build_command_scaled(&input.display().to_string(), &temp_output.display().to_string(), rung)
+                }),
+                input,
+                output,
+                status: ItemStatus::Pending,
+                chapters: self.chapter_plan(),
+                palette: self.palette_plan(),
+                duration,
+                queue_index,
             });
-
-            // Function selection
-            ui.horizontal(|ui| {
-                ui.label("Function:");
-                for &func in &[FunctionType::ExtractAudio, FunctionType::CompressVideo, FunctionType::ConvertToMp4] {
-                    if ui.radio_value(&mut self.selected_function, func, format!("{:?}", func)).clicked() {
-                        self.output_path = self.default_output();
-                        self.update_command();
-                    }
-                }
+        }
+        self.start_batch(job, ctx);
+    }
+    
+    /// Add the input with a snapshot of the current settings to the job list. An output name
+    /// an earlier job already uses gets the job's number appended.
+    fn add_settings_job(&mut self) {
+        let input = PathBuf::from(&self.input_path);
+        let mut output = if self.output_path.is_empty() {
+            PathBuf::from(self.default_output())
+        } else {
+            PathBuf::from(&self.output_path)
+        };
+        if self.settings_jobs.iter().any(|job| job.output == output) {
+            output = ffmpeg_utils::with_stem_suffix(&output, &format!("_{}", self.settings_jobs.len() + 1));
+        }
+        let temp_output = ffmpeg_utils::temp_output_path(&output);
+        self.settings_jobs.push(SettingsJob {
+            args: self.build_command_scaled(&self.input_path, &temp_output.display().to_string(), self.scale_height),
+            chapters: self.chapter_plan(),
+            palette: self.palette_plan(),
+            duration: self.trim_range().and_then(|_| self.trimmed_duration_of(&input)),
+            input,
+            output,
+            settings: self.encode_settings(),
+        });
+    }
+    
+    /// Process the job list in order, each entry with the settings it was added with
+    fn run_settings_jobs(&mut self, ctx: egui::Context) {
+        let outputs: Vec<PathBuf> = self.settings_jobs.iter().map(|job| self.resolve_output_path(job.output.clone())).collect();
+        let collisions = ffmpeg_utils::colliding_outputs(&outputs.iter().map(PathBuf::as_path).collect::<Vec<_>>());
+        if !collisions.is_empty() {
+            let mut log = self.output_log.write();
+            log.push_str(&format!("Error: {} output name(s) are used by more than one job:\n", collisions.len()));
+            for group in &collisions {
+                log.push_str(&format!("  {}\n", outputs[group[0]].display()));
+            }
+            return;
+        }
+        
+        // Each job was added for its own input, but the bitrate a limit picked was fitted to
+        // whichever input was loaded then
+        let limited: Vec<String> = self.settings_jobs.clone()
+            .iter()
+            .enumerate()
+            .filter(|(_, queued)| self.with_encode_settings(&queued.settings, |app| app.size_limited()))
+            .map(|(i, _)| (i + 1).to_string())
+            .collect();
+        if !limited.is_empty() {
+            self.output_log.write().push_str(&format!(
+                "Error: Job(s) {} fit a size limit, which only works for a single encode. Remove them and encode those inputs on their own.\n",
+                limited.join(", ")
+            ));
+            return;
+        }
+        
+        let job = BatchJob {
+            items: self.settings_jobs.iter().zip(&outputs).map(|(queued, output)| queued.batch_item(output.clone())).collect(),
+        };
+        // Jobs show the name actually written, which an existing file may have changed
+        for (queued, output) in self.settings_jobs.iter_mut().zip(outputs) {
+            queued.output = output;
+        }
+        self.start_batch(job, ctx);
+    }
+    
+    /// Read the track starts of a cue sheet or timestamp list to split the input at
+    fn load_split_points(&mut self, path: &Path) {
+        let result = std::fs::read_to_string(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|text| chapters::parse_split_points(&text));
+        match result {
+            Ok(points) => {
+                self.output_log.write().push_str(&format!("Loaded {} track(s) from {}\n", points.len(), path.display()));
+                self.split_points = points;
+                self.split_points_file = path.display().to_string();
+            },
+            Err(e) => self.output_log.write().push_str(&format!("Error: Could not read tracks from {}: {}\n", path.display(), e)),
+        }
+    }
+    
+    /// Write each chapter (or loaded track) of the input to its own file, named after its title
+    fn run_chapter_split(&mut self, ctx: egui::Context) {
+        let chapters = self.split_chapters().unwrap_or_default();
+        let base_output = if self.output_path.is_empty() {
+            PathBuf::from(self.default_output())
+        } else {
+            PathBuf::from(&self.output_path)
+        };
+        
+        let mut job = BatchJob::default();
+        for (i, chapter) in chapters.iter().enumerate() {
+            let title = chapter.title.as_deref().map(ffmpeg_utils::safe_file_name).unwrap_or_default();
+            let suffix = if title.is_empty() {
+                format!(" - {:02}", i + 1)
+            } else {
+                format!(" - {:02} - {}", i + 1, title)
+            };
+            let output = self.resolve_output_path(ffmpeg_utils::with_stem_suffix(&base_output, &suffix));
+            let temp_output = ffmpeg_utils::temp_output_path(&output);
+            job.items.push(BatchItem {
+                args: self.build_command_trimmed(
+                    &self.input_path,
+                    &temp_output.display().to_string(),
+                    self.scale_height,
+                    Some((Some(chapter.start), Some(chapter.end))),
+                ),
+                input: PathBuf::from(&self.input_path),
+                output,
+                status: ItemStatus::Pending,
+                chapters: None,
+                palette: None,
+                duration: Some(chapter.end - chapter.start),
+                queue_index: None,
             });
-
-            // Show function description
-            ui.label(self.selected_function.description());
-
-            // Show options based on selected function
-            if self.selected_function.show_audio_options() {
-                ui.collapsing("Audio Options", |ui| {
-                    ui.horizontal(|ui| {
-                        ui.label("Audio Format:");
-                        egui::ComboBox::from_id_source("audio_format")
-                            .selected_text(self.audio_format.display_name())
-                            .show_ui(ui, |ui| {
-                                for format in AudioFormat::all() {
-                                    ui.selectable_value(
-                                        &mut self.audio_format, 
-                                        format, 
-                                        format.display_name()
-                                    );
-                                }
-                            });
-                    });
-
-                    // Show different options based on audio format
-                    match self.audio_format {
-                        AudioFormat::MP3 => {
-                            // MP3 can use either bitrate or quality level (VBR), but not both
-                            ui.horizontal(|ui| {
-                                ui.radio_value(&mut self.use_audio_quality, true, "Variable Bitrate (VBR)");
-                                ui.radio_value(&mut self.use_audio_quality, false, "Constant Bitrate (CBR)");
-                            });
-
-                            if self.use_audio_quality {
-                                ui.horizontal(|ui| {
-                                    ui.label("MP3 Quality:");
-                                    // Invert the quality for display (0=best to 9=worst becomes 9=best to 0=worst)
-                                    let mut inverted_quality = 9 - self.audio_quality;
-                                    if ui.add(egui::DragValue::new(&mut inverted_quality)
-                                        .clamp_range(0..=9))
-                                        .on_hover_text("0=worst, 9=best quality")
-                                        .changed() 
-                                    {
-                                        self.audio_quality = 9 - inverted_quality; // Convert back
-                                        self.update_command();
-                                    }
-                                });
-                            } else {
-                                ui.horizontal(|ui| {
-                                    ui.label("Bitrate:");
-                                    if ui.add(egui::DragValue::new(&mut self.audio_bitrate)
-                                        .clamp_range(8..=320)
-                                        .suffix(" kbps"))
-                                        .changed() {
-                                        self.update_command();
-                                    }
-                                });
-                            }
-                        },
-                        AudioFormat::OPUS => {
-                            ui.horizontal(|ui| {
-                                ui.label("Opus Bitrate:");
-                                
-                                // Common Opus bitrate standards for reference
-                                let bitrate_standards = [16, 24, 32, 40, 48, 64, 96, 128, 160, 192, 256];
-                                
-                                // Allow direct input with custom values
-                                if ui.add(egui::DragValue::new(&mut self.audio_bitrate)
-                                    .clamp_range(8..=512)
-                                    .suffix(" kbps"))
-                                    .on_hover_text(format!("Common values: 16, 24, 32, 48, 64, 96, 128, 192, 256 kbps"))
-                                    .changed() 
-                                {
+        }
+        self.start_batch(job, ctx);
+    }
+    
+    /// Write the batch as a shell script (or a .bat file) with one FFmpeg line per output,
+    /// for running it outside the app
+    fn export_batch_script(&mut self, ctx: &egui::Context) {
+        let default_name = if cfg!(windows) { "batch.bat" } else { "batch.sh" };
+        self.browse(DialogTarget::BatchScript, ctx, move || {
+            rfd::FileDialog::new()
+                .add_filter("Shell script", &["sh"])
+                .add_filter("Batch file", &["bat", "cmd"])
+                .set_file_name(default_name)
+                .save_file()
+                .map(|path| vec![path])
+        });
+    }
+    
+    fn write_batch_script(&mut self, path: &Path) {
+        let windows = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("bat") || ext.eq_ignore_ascii_case("cmd"));
+        let mut script = String::from(if windows { "@echo off\r\n" } else { "#!/bin/sh\n" });
+        let plan = self.batch_plan();
+        for (input, output, rung, format, _) in &plan {
+            // A GIF's palette pass runs first, writing where the encode expects it
+            if let Some(palette) = self.palette_plan() {
+                script.push_str(&ffmpeg_utils::batch_file_line(&palette.palette_args(input, output), windows));
+                script.push_str(if windows { "\r\n" } else { "\n" });
+            }
+            let settings = self.settings_for_format(*format);
+            let args = self.with_encode_settings(&settings, |app| {
+                app.build_command_scaled(&input.display().to_string(), &output.display().to_string(), *rung)
+            });
+            script.push_str(&ffmpeg_utils::batch_file_line(&args, windows));
+            script.push_str(if windows { "\r\n" } else { "\n" });
+        }
+        
+        match std::fs::write(path, script) {
+            Ok(()) => self.output_log.write().push_str(&format!("Exported {} command(s) to {}\n", plan.len(), path.display())),
+            Err(e) => self.output_log.write().push_str(&format!("Error: Could not write script: {}\n", e)),
+        }
+        
+        // Make the script runnable straight away
+        #[cfg(unix)]
+        if !windows {
+            use std::os::unix::fs::PermissionsExt;
+            let _ = std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755));
+        }
+    }
+    
+    /// Encode the start of the input with every available video encoder, one after another
+    fn run_benchmark(&mut self, ctx: egui::Context) {
+        if self.busy() {
+            self.output_log.write().push_str("A process is already running. Please stop it first.\n");
+            return;
+        }
+        if !Path::new(&self.input_path).exists() {
+            self.output_log.write().push_str("Error: Select an input video to benchmark.\n");
+            return;
+        }
+        
+        let input = PathBuf::from(&self.input_path);
+        let seconds = self.benchmark_seconds;
+        let jobs: Vec<(VideoEncoder, Vec<String>)> = VideoEncoder::all()
+            .into_iter()
+            .filter(|encoder| self.has_encoder(encoder.codec()))
+            .map(|encoder| {
+                let output = benchmark::temp_output(encoder).display().to_string();
+                (encoder, self.benchmark_args(encoder, &self.input_path, &output, seconds))
+            })
+            .collect();
+        for &(encoder, _) in &jobs {
+            self.register_temp(benchmark::temp_output(encoder));
+        }
+        
+        *self.running.write() = true;
+        self.partial_output = None;
+        *self.progress.write() = 0.0;
+        self.output_log.write().clear();
+        self.output_log.write().push_str(&format!("Benchmarking {} encoder(s) on the first {}s\n", jobs.len(), seconds));
+        self.benchmark_results.write().clear();
+        
+        let handles = self.job_handles(ctx);
+        let results = self.benchmark_results.clone();
+        std::thread::spawn(move || run_benchmark_jobs(jobs, &input, seconds, &results, &handles));
+    }
+    
+    /// Encode the same segment at both A/B values with the current encoder and preset
+    fn run_ab(&mut self, ctx: egui::Context) {
+        if self.busy() {
+            self.output_log.write().push_str("A process is already running. Please stop it first.\n");
+            return;
+        }
+        if !Path::new(&self.input_path).exists() {
+            self.output_log.write().push_str("Error: Select an input video to compare.\n");
+            return;
+        }
+        
+        let input = PathBuf::from(&self.input_path);
+        let jobs: Vec<(u8, Vec<String>)> = self.ab_values.iter()
+            .map(|&value| {
+                let output = benchmark::ab_temp_output(value).display().to_string();
+                (value, self.ab_args(value, &self.input_path, &output))
+            })
+            .collect();
+        for &(value, _) in &jobs {
+            self.register_temp(benchmark::ab_temp_output(value));
+        }
+        let (start, seconds, metrics) = (self.ab_start, self.ab_seconds, self.ab_metrics);
+        
+        *self.running.write() = true;
+        self.partial_output = None;
+        *self.progress.write() = 0.0;
+        self.output_log.write().clear();
+        self.output_log.write().push_str(&format!(
+            "Comparing {} {} and {} on {}s from {}\n",
+            self.video_encoder.display_name(),
+            self.ab_values[0],
+            self.ab_values[1],
+            seconds,
+            ffmpeg_utils::format_timestamp(start)
+        ));
+        self.ab_results.write().clear();
+        
+        let handles = self.job_handles(ctx);
+        let results = self.ab_results.clone();
+        std::thread::spawn(move || {
+            for (value, args) in jobs {
+                if !*handles.running.read() {
+                    break;
+                }
+                handles.log.write().push_str(&format!("\n--- {} ---\n", value));
+                
+                let output = benchmark::ab_temp_output(value);
+                let started = Instant::now();
+                let status = run_ffmpeg(&args, Some(seconds as f64), &handles);
+                let elapsed = started.elapsed();
+                
+                let succeeded = status.is_some_and(|s| s.success());
+                let size = succeeded.then(|| std::fs::metadata(&output).ok().map(|m| m.len())).flatten();
+                let measure = succeeded && metrics;
+                let segment = Some((start, seconds as f64));
+                let psnr = if measure { benchmark::measure_psnr(&output, &input, segment) } else { None };
+                let ssim = if measure { benchmark::measure_ssim(&output, &input, segment) } else { None };
+                let _ = std::fs::remove_file(&output);
+                
+                results.write().push(AbResult { value, elapsed, size, psnr, ssim });
+                handles.ctx.request_repaint();
+            }
+            
+            handles.log.write().push_str("\nA/B comparison finished.\n");
+            *handles.running.write() = false;
+            *handles.progress.write() = 1.0;
+            handles.ctx.request_repaint();
+        });
+    }
+    
+    /// Encode the first few seconds with the current settings in a second FFmpeg process
+    /// and open the result, so bad settings show up without waiting for the full encode
+    fn quick_preview(&mut self) {
+        if !Path::new(&self.input_path).exists() {
+            self.output_log.write().push_str("Error: No input file selected.\n");
+            return;
+        }
+        if self.preview_child.lock().is_ok_and(|child| child.is_some()) {
+            return;
+        }
+        
+        let output = self.quick_preview_path();
+        self.register_temp(output.clone());
+        let args = self.quick_preview_command(&output.display().to_string());
+        self.output_log.write().push_str(&format!("Quick preview: ffmpeg {}\n", args.join(" ")));
+        
+        let mut child = match ffmpeg_utils::ffmpeg_command(self.settings.background_priority)
+            .args(&args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) => {
+                self.output_log.write().push_str(&format!("Failed to start preview encode: {}\n", e));
+                return;
+            }
+        };
+        // Read all along so a chatty encode can't fill the pipe and stall
+        let reader = child.stderr.take().map(|mut stderr| std::thread::spawn(move || {
+            let mut text = String::new();
+            let _ = stderr.read_to_string(&mut text);
+            text
+        }));
+        if let Ok(mut guard) = self.preview_child.lock() {
+            *guard = Some(child);
+        }
+        
+        let preview_child = self.preview_child.clone();
+        let log = self.output_log.clone();
+        std::thread::spawn(move || {
+            let status = wait_for_child(&preview_child, |_| {});
+            let stderr = reader.and_then(|reader| reader.join().ok()).unwrap_or_default();
+            if status.is_some_and(|status| !status.success()) {
+                log.write().push_str(&stderr);
+            }
+            match status {
+                Some(status) if status.success() => {
+                    log.write().push_str(&format!("Quick preview ready: {}\n", output.display()));
+                    if let Err(e) = ffmpeg_utils::open_in_default_app(&output) {
+                        log.write().push_str(&format!("Could not open preview: {}\n", e));
+                    }
+                },
+                _ => log.write().push_str("Quick preview failed or was stopped.\n"),
+            }
+        });
+    }
+    
+    /// Open a file dialog for `target` in the background, unless one is already open.
+    /// `show` builds and shows the dialog, returning the picked paths.
+    fn browse<F>(&mut self, target: DialogTarget, ctx: &egui::Context, show: F)
+    where
+        F: FnOnce() -> Option<Vec<PathBuf>> + Send + 'static,
+    {
+        if self.file_dialog.is_some() {
+            return;
+        }
+        if let Some(reason) = dialogs::unavailable_reason() {
+            self.output_log.write().push_str(&format!(
+                "Can't open a file dialog ({}). Type the {} path into its field instead.\n",
+                reason,
+                target.display_name()
+            ));
+            return;
+        }
+        self.file_dialog = Some(PendingDialog::open(target, ctx.clone(), show));
+    }
+    
+    /// Keep the global hotkeys registered as configured and act on any that were pressed
+    fn handle_global_hotkeys(&mut self, ctx: &egui::Context) {
+        if self.settings.global_hotkeys && self.hotkeys.is_none() && !self.hotkeys_unavailable {
+            match GlobalHotkeys::new(ctx.clone()) {
+                Ok(hotkeys) => self.hotkeys = Some(hotkeys),
+                Err(e) => {
+                    self.output_log.write().push_str(&format!("Warning: global hotkeys are unavailable: {}\n", e));
+                    self.hotkeys_unavailable = true;
+                }
+            }
+        }
+        let Some(hotkeys) = self.hotkeys.as_mut() else {
+            return;
+        };
+        
+        // Wait until the user has finished typing a hotkey before registering it
+        if !ctx.wants_keyboard_input() {
+            let problems = hotkeys.sync(self.settings.global_hotkeys, &self.settings.start_hotkey, &self.settings.stop_hotkey);
+            for problem in problems {
+                self.output_log.write().push_str(&format!("Warning: {}. Continuing without it.\n", problem));
+            }
+        }
+        
+        for action in hotkeys.poll() {
+            match action {
+                HotkeyAction::Start => self.run(ctx.clone()),
+                HotkeyAction::Stop if *self.running.read() => self.stop_ffmpeg(),
+                HotkeyAction::Stop => {}
+            }
+        }
+    }
+    
+    /// Apply the result of the open file dialog once it has closed
+    fn poll_file_dialog(&mut self) {
+        let Some(outcome) = self.file_dialog.as_ref().and_then(PendingDialog::poll) else {
+            return;
+        };
+        let Some(target) = self.file_dialog.take().map(|dialog| dialog.target) else {
+            return;
+        };
+        
+        let paths = match outcome {
+            DialogOutcome::Picked(paths) => paths,
+            DialogOutcome::Cancelled => {
+                self.output_log.write().push_str(&format!("File dialog for the {} was cancelled.\n", target.display_name()));
+                return;
+            },
+            DialogOutcome::Failed(e) => {
+                self.output_log.write().push_str(&format!(
+                    "Error: File dialog for the {} failed: {}. Type the path into its field instead.\n",
+                    target.display_name(),
+                    e
+                ));
+                return;
+            }
+        };
+        let Some(path) = paths.first().cloned() else {
+            return;
+        };
+        
+        match target {
+            DialogTarget::Input => {
+                self.input_path = path.display().to_string();
+                self.output_path = self.default_output();
+            },
+            DialogTarget::AudioInput => self.audio_input_path = path.display().to_string(),
+            DialogTarget::Output => self.output_path = path.display().to_string(),
+            DialogTarget::Cover => self.cover_path = path.display().to_string(),
+            DialogTarget::Subtitle => self.subtitle_path = path.display().to_string(),
+            DialogTarget::SplitPoints => self.load_split_points(&path),
+            DialogTarget::BatchFiles => self.batch_queue.extend(paths),
+            DialogTarget::BatchScript => self.write_batch_script(&path),
+        }
+        self.update_command();
+    }
+    
+    /// Positions in the queue of the files the last batch finished every output for
+    fn queued_done(&self) -> Vec<usize> {
+        let job = self.batch_job.read();
+        let Some(job) = job.as_ref() else { return Vec::new() };
+        (0..self.batch_queue.len()).filter(|&index| job.queued_done(index)).collect()
+    }
+    
+    /// Keep the last batch's statuses on the right files as the queue is edited
+    fn move_queued(&mut self, new_index: impl Fn(usize) -> Option<usize>) {
+        if let Some(job) = self.batch_job.write().as_mut() {
+            job.move_queued(new_index);
+        }
+    }
+    
+    /// Continue an unfinished batch, either one found on startup or one stopped this session
+    fn resume_batch(&mut self, ctx: egui::Context) {
+        let job = self.pending_resume.take().or_else(|| self.batch_job.read().clone());
+        if let Some(job) = job {
+            // The queue shows the batch's files again, in the order they were queued
+            let inputs = job.queued_inputs();
+            if !inputs.is_empty() {
+                self.batch_queue = inputs;
+            }
+            self.start_batch(job, ctx);
+        }
+    }
+    
+    /// The test-run folder is ours to create, for any output named into it. Logs and
+    /// returns false if it can't be.
+    fn create_test_dir(&self, output: &Path) -> bool {
+        if let Some(parent) = output.parent()
+            && parent.ends_with(app_state::TEST_RUN_DIR)
+            && let Err(e) = std::fs::create_dir_all(parent)
+        {
+            self.output_log.write().push_str(&format!("Error: Could not create test folder {}: {}\n", parent.display(), e));
+            return false;
+        }
+        true
+    }
+    
+    fn start_batch(&mut self, mut job: BatchJob, ctx: egui::Context) {
+        if self.busy() {
+            self.output_log.write().push_str("A process is already running. Please stop it first.\n");
+            return;
+        }
+        job.reconcile();
+        // Queued files, ladder rungs, formats, jobs and tracks all name their outputs up front
+        if !job.items.iter().all(|item| item.status == ItemStatus::Done || self.create_test_dir(&item.output)) {
+            return;
+        }
+        self.pending_resume = None;
+        for item in &job.items {
+            self.register_temp(item.temp_output());
+            if item.chapters.is_some() {
+                self.register_temp(chapters::metadata_path(&item.temp_output()));
+            }
+            if item.palette.is_some() {
+                self.register_temp(gif::palette_path(&item.temp_output()));
+            }
+        }
+        
+        *self.running.write() = true;
+        self.partial_output = None;
+        *self.progress.write() = 0.0;
+        self.encode_started = Some(Instant::now());
+        self.output_log.write().clear();
+        self.output_log.write().push_str(&format!(
+            "Starting batch: {} file(s), {} already done\n",
+            job.items.len(),
+            job.completed()
+        ));
+        
+        if let Err(e) = job.save() {
+            self.output_log.write().push_str(&format!("Warning: could not save batch progress: {}\n", e));
+        }
+        *self.batch_job.write() = Some(job);
+        
+        let handles = self.job_handles(ctx);
+        let batch_arc = self.batch_job.clone();
+        std::thread::spawn(move || process_batch(&batch_arc, &handles));
+    }
+}
+
+/// Run each benchmark encode, timing it and measuring the result against the source
+fn run_benchmark_jobs(
+    jobs: Vec<(VideoEncoder, Vec<String>)>,
+    input: &Path,
+    seconds: u32,
+    results: &RwLock<Vec<BenchmarkResult>>,
+    handles: &JobHandles,
+) {
+    for (encoder, args) in jobs {
+        if !*handles.running.read() {
+            break;
+        }
+        handles.log.write().push_str(&format!("\n--- {} ---\n", encoder.display_name()));
+        
+        let output = benchmark::temp_output(encoder);
+        let started = Instant::now();
+        let status = run_ffmpeg(&args, Some(seconds as f64), handles);
+        let elapsed = started.elapsed();
+        
+        let succeeded = status.is_some_and(|s| s.success());
+        let size = succeeded.then(|| std::fs::metadata(&output).ok().map(|m| m.len())).flatten();
+        let psnr = if succeeded { benchmark::measure_psnr(&output, input, Some((0.0, seconds as f64))) } else { None };
+        let _ = std::fs::remove_file(&output);
+        
+        results.write().push(BenchmarkResult {
+            encoder,
+            preset: encoder.default_preset().to_string(),
+            elapsed,
+            size,
+            psnr,
+        });
+        handles.ctx.request_repaint();
+    }
+    
+    handles.log.write().push_str("\nBenchmark finished.\n");
+    *handles.running.write() = false;
+    *handles.progress.write() = 1.0;
+    handles.ctx.request_repaint();
+}
+
+/// Encode every pending item of the current batch in order, persisting progress after each one
+fn process_batch(batch: &Arc<RwLock<Option<BatchJob>>>, handles: &JobHandles) {
+    let started = Instant::now();
+    let total = batch.read().as_ref().map_or(0, |job| job.items.len());
+    
+    for index in 0..total {
+        // Stop requested by the user
+        if !*handles.running.read() {
+            break;
+        }
+        
+        let item = {
+            let mut guard = batch.write();
+            let Some(job) = guard.as_mut() else { break };
+            if job.items[index].status != ItemStatus::Pending {
+                continue;
+            }
+            job.items[index].status = ItemStatus::Running;
+            let _ = job.save();
+            job.items[index].clone()
+        };
+        
+        handles.log.write().push_str(&format!("\n[{}/{}] {} -> {}\n", index + 1, total, item.input.display(), item.output.display()));
+        *handles.progress.write() = 0.0;
+        
+        let duration = item.duration
+            .or_else(|| ffmpeg_utils::probe_duration(&item.input))
+            .filter(|&duration| duration > 0.0);
+        let result = match (item.chapters, &item.palette) {
+            (Some(plan), _) if !prepare_chapters(&plan, &item.input, &item.temp_output(), duration, handles) => None,
+            (_, Some(plan)) if !prepare_palette(plan, &item.input, &item.temp_output(), handles) => None,
+            _ if !*handles.running.read() => None,
+            _ => run_ffmpeg(&item.args, duration, handles),
+        };
+        if item.chapters.is_some() {
+            let _ = std::fs::remove_file(chapters::metadata_path(&item.temp_output()));
+        }
+        if item.palette.is_some() {
+            let _ = std::fs::remove_file(gif::palette_path(&item.temp_output()));
+        }
+        
+        let status = if !*handles.running.read() {
+            // Stopped mid-encode: leave it pending so a resume picks it up again
+            ItemStatus::Pending
+        } else {
+            match result {
+                Some(status) if status.success() => match std::fs::rename(item.temp_output(), &item.output) {
+                    Ok(()) => {
+                        handles.log.write().push_str(&format!("Output successfully saved to {}\n", item.output.display()));
+                        run_post_command(&item.input, &item.output, handles);
+                        *handles.output_sizes.write() = handles.run_sizes.read().map(|sizes| (item.output.clone(), sizes));
+                        *handles.last_encode.write() = Some(LastEncode {
+                            input: item.input.clone(),
+                            output: item.output.clone(),
+                            source_start: ffmpeg_utils::input_start(&item.args),
+                            length: item.duration,
+                            finished: Instant::now(),
+                        });
+                        ItemStatus::Done
+                    },
+                    Err(e) => {
+                        handles.log.write().push_str(&format!("Error: could not move finished output into place: {}\n", e));
+                        ItemStatus::Failed
+                    }
+                },
+                _ => {
+                    handles.log.write().push_str("FFmpeg command failed.\n");
+                    ItemStatus::Failed
+                }
+            }
+        };
+        
+        if let Some(job) = batch.write().as_mut() {
+            job.items[index].status = status;
+            let _ = job.save();
+        }
+    }
+    
+    if let Some(job) = batch.read().as_ref() {
+        if job.is_finished() {
+            BatchJob::discard();
+            handles.log.write().push_str(&format!(
+                "\nBatch complete: {} of {} file(s) succeeded, finished in {}\n",
+                job.completed(),
+                job.items.len(),
+                ffmpeg_utils::format_duration(started.elapsed())
+            ));
+        } else {
+            handles.log.write().push_str("\nBatch stopped. Unfinished files can be resumed later, even after restarting the app.\n");
+        }
+    }
+    
+    *handles.running.write() = false;
+    *handles.progress.write() = 1.0;
+    handles.ctx.request_repaint();
+}
+
+/// Log objective quality scores for a finished output: VMAF when FFmpeg has libvmaf,
+/// otherwise SSIM and PSNR
+fn score_against_source(output: &Path, source: &Path, segment: Option<(f64, f64)>, vmaf: bool, handles: &JobHandles) {
+    handles.log.write().push_str("Scoring the output against the source...\n");
+    handles.ctx.request_repaint();
+    
+    let score = if vmaf {
+        benchmark::measure_vmaf(output, source, segment).map(|vmaf| format!("VMAF {:.2}", vmaf))
+    } else {
+        let ssim = benchmark::measure_ssim(output, source, segment);
+        let psnr = benchmark::measure_psnr(output, source, segment);
+        match (ssim, psnr) {
+            (Some(ssim), Some(psnr)) => Some(format!("SSIM {:.4}, PSNR {:.2} dB (libvmaf not available)", ssim, psnr)),
+            _ => None,
+        }
+    };
+    match score {
+        Some(score) => handles.log.write().push_str(&format!("Quality against source: {}\n", score)),
+        None => handles.log.write().push_str("Could not score the output against the source.\n"),
+    }
+}
+
+/// Run the user's post-encode command for a finished output and log what it printed
+fn run_post_command(input: &Path, output: &Path, handles: &JobHandles) {
+    if handles.post_command.is_empty() {
+        return;
+    }
+    let command_line = match ffmpeg_utils::expand_post_command(&handles.post_command, input, output, cfg!(windows)) {
+        Ok(command_line) => command_line,
+        Err(e) => {
+            handles.log.write().push_str(&format!("Skipped post-encode command: {}\n", e));
+            return;
+        }
+    };
+    
+    handles.log.write().push_str(&format!("Running post-encode command: {}\n", command_line));
+    match ffmpeg_utils::run_shell(&command_line) {
+        Ok(result) => {
+            let mut log = handles.log.write();
+            log.push_str(&String::from_utf8_lossy(&result.stdout));
+            log.push_str(&String::from_utf8_lossy(&result.stderr));
+            if !result.status.success() {
+                log.push_str(&format!("Post-encode command failed with status: {}\n", result.status));
+            }
+        },
+        Err(e) => handles.log.write().push_str(&format!("Error: could not run post-encode command: {}\n", e)),
+    }
+    handles.ctx.request_repaint();
+}
+
+/// Write the chapter metadata FFmpeg reads for `output`, logging the outcome
+fn prepare_chapters(plan: &ChapterPlan, input: &Path, output: &Path, duration: Option<f64>, handles: &JobHandles) -> bool {
+    let Some(duration) = duration else {
+        handles.log.write().push_str("Error: Chapters need the input's duration, which could not be determined.\n");
+        return false;
+    };
+    if plan.mode == ChapterMode::Scene {
+        handles.log.write().push_str("Detecting scene changes for chapters...\n");
+    }
+    match plan.prepare(input, output, duration, |args| run_ffmpeg_pass(args, handles)) {
+        Ok(count) => {
+            handles.log.write().push_str(&format!("Generated {} chapter(s)\n", count));
+            true
+        },
+        Err(_) if !*handles.running.read() => false,
+        Err(e) => {
+            handles.log.write().push_str(&format!("Error: Could not generate chapters: {}\n", e));
+            false
+        }
+    }
+}
+
+/// Encode the audio (unless an identical encode is left from earlier), then the video,
+/// then mux the two. Returns the status of the first step that failed, or the mux's.
+fn run_separate(plan: &SeparatePlan, duration: Option<f64>, handles: &JobHandles) -> Option<ExitStatus> {
+    if plan.audio_path.exists() {
+        handles.log.write().push_str(&format!("Reusing the audio encoded earlier: {}\n", plan.audio_path.display()));
+    } else {
+        handles.log.write().push_str("Step 1 of 3: encoding the audio\n");
+        // Written under another name first, so a stopped encode is never mistaken for a finished one
+        let status = run_ffmpeg(&plan.audio_args, duration, handles)?;
+        let part = ffmpeg_utils::temp_output_path(&plan.audio_path);
+        if !status.success() {
+            let _ = std::fs::remove_file(&part);
+            return Some(status);
+        }
+        if let Err(e) = std::fs::rename(&part, &plan.audio_path) {
+            handles.log.write().push_str(&format!("Error: Could not keep the encoded audio: {}\n", e));
+            return None;
+        }
+    }
+    
+    handles.log.write().push_str("Step 2 of 3: encoding the video\n");
+    let status = match &plan.video_passes {
+        Some(passes) => run_two_pass(passes, duration, handles)?,
+        None => run_ffmpeg(&plan.video_args, duration, handles)?,
+    };
+    if !status.success() {
+        let _ = std::fs::remove_file(&plan.video_path);
+        return Some(status);
+    }
+    
+    handles.log.write().push_str("Step 3 of 3: muxing the audio and video\n");
+    let result = run_ffmpeg(&plan.mux_args, duration, handles);
+    let _ = std::fs::remove_file(&plan.video_path);
+    result
+}
+
+/// Analyse the video, then encode it using the first pass's log, which is removed afterwards
+fn run_two_pass(plan: &TwoPassPlan, duration: Option<f64>, handles: &JobHandles) -> Option<ExitStatus> {
+    handles.log.write().push_str("Pass 1 of 2: analysing the video\n");
+    let result = match run_ffmpeg(&plan.first_args, duration, handles) {
+        Some(status) if status.success() => {
+            handles.log.write().push_str("Pass 2 of 2: encoding\n");
+            run_ffmpeg(&plan.second_args, duration, handles)
+        },
+        other => other,
+    };
+    for file in plan.log_files() {
+        let _ = std::fs::remove_file(file);
+    }
+    result
+}
+
+/// Suffix telling apart the outputs of one collision: the inputs' folder names when
+/// those differ, otherwise their position in the queue
+fn collision_suffix(plan: &[PlannedOutput], group: &[usize], position: usize) -> String {
+    let folder = |i: usize| plan[i].0.parent().and_then(Path::file_name).map(|name| name.to_string_lossy().into_owned());
+    let folders: Vec<Option<String>> = group.iter().map(|&i| folder(i)).collect();
+    let distinct = folders.iter().all(Option::is_some)
+        && folders.iter().enumerate().all(|(a, name)| !folders[..a].contains(name));
+    match &folders[position] {
+        Some(name) if distinct => format!("_{}", ffmpeg_utils::safe_file_name(name)),
+        _ => format!("_{}", position + 1),
+    }
+}
+
+/// Run a GIF's palette pass ahead of its encode. Returns false (having logged why) if it failed.
+fn prepare_palette(plan: &GifPlan, input: &Path, output: &Path, handles: &JobHandles) -> bool {
+    handles.log.write().push_str("Generating the GIF palette...\n");
+    handles.ctx.request_repaint();
+    match run_ffmpeg_pass(&plan.palette_args(input, output), handles) {
+        Ok(_) => true,
+        Err(_) if !*handles.running.read() => false,
+        Err(e) => {
+            handles.log.write().push_str(&format!("Error: Could not generate the GIF palette: {}\n", e));
+            false
+        }
+    }
+}
+
+/// Run a pass that prepares an encode (scene detection, a GIF palette) where Stop can reach
+/// it, and return its log. Errors if it couldn't start, failed or was stopped.
+fn run_ffmpeg_pass(args: &[String], handles: &JobHandles) -> anyhow::Result<String> {
+    if !*handles.running.read() {
+        anyhow::bail!("stopped");
+    }
+    let mut child = ffmpeg_utils::ffmpeg_command(handles.background_priority)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let reader = child.stderr.take().map(|mut stderr| std::thread::spawn(move || {
+        let mut text = String::new();
+        let _ = stderr.read_to_string(&mut text);
+        text
+    }));
+    *handles.child.lock().unwrap() = Some(child);
+    
+    let status = wait_for_child(&handles.child, |_| {});
+    let stderr = reader.and_then(|reader| reader.join().ok()).unwrap_or_default();
+    match status {
+        None => anyhow::bail!("stopped"),
+        Some(status) if !status.success() => anyhow::bail!("{}", stderr.lines().last().unwrap_or("FFmpeg failed")),
+        Some(_) => Ok(stderr),
+    }
+}
+
+/// Spawn FFmpeg, stream its stderr into the log/progress bar and wait for it to exit.
+/// Returns `None` if the process could not be started or was killed by `stop_ffmpeg`.
+fn run_ffmpeg(args: &[String], duration: Option<f64>, handles: &JobHandles) -> Option<ExitStatus> {
+    run_ffmpeg_to(args, duration, handles, None)
+}
+
+/// Like `run_ffmpeg`, optionally forwarding FFmpeg's stdout to a downstream shell command
+/// (an empty command just counts the bytes)
+fn run_ffmpeg_to(args: &[String], duration: Option<f64>, handles: &JobHandles, pipe: Option<&str>) -> Option<ExitStatus> {
+    handles.log.write().push_str(&format!("Executing: ffmpeg {}\n", args.join(" ")));
+    
+    // Create and spawn the FFmpeg process
+    let (args, stdin) = ffmpeg_utils::stop_stdin(args);
+    let mut child = match ffmpeg_utils::ffmpeg_command(handles.background_priority)
+        .args(&args)
+        .stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            handles.log.write().push_str(&format!("Failed to start FFmpeg: {}\n", e));
+            return None;
+        }
+    };
+    
+    // Capture stderr for progress monitoring, then store the child for potential cancellation
+    let stderr = child.stderr.take();
+    let stdout = child.stdout.take();
+    *handles.child.lock().unwrap() = Some(child);
+    
+    let last_update = Arc::new(RwLock::new(Instant::now()));
+    let reader = stderr.map(|stderr| {
+        let (handles, last_update) = (handles.clone(), last_update.clone());
+        std::thread::spawn(move || read_progress(stderr, duration, &handles, &last_update))
+    });
+    let forwarder = pipe.zip(stdout).map(|(downstream, stdout)| {
+        let (downstream, handles) = (downstream.to_string(), handles.clone());
+        std::thread::spawn(move || forward_stdout(stdout, &downstream, &handles))
+    });
+    
+    // Watchdog: FFmpeg can hang on a damaged input without exiting or printing anything
+    let mut stalled = false;
+    let status = wait_for_child(&handles.child, |process| {
+        let Some(timeout) = handles.stall_timeout else { return };
+        if last_update.read().elapsed() < timeout {
+            stalled = false;
+            return;
+        }
+        if !stalled {
+            stalled = true;
+            handles.log.write().push_str(&format!("Warning: Encode appears stalled (no progress for {} seconds)\n", timeout.as_secs()));
+            if handles.kill_stalled && process.kill().is_ok() {
+                handles.log.write().push_str("Stopped the stalled FFmpeg process.\n");
+            }
+            handles.ctx.request_repaint();
+        }
+    });
+    if let Some(reader) = reader {
+        let _ = reader.join(); // Make sure the full log is captured before reporting
+    }
+    if let Some(forwarder) = forwarder {
+        let _ = forwarder.join();
+    }
+    status
+}
+
+/// Copy FFmpeg's piped output into the downstream command's stdin and log how it went.
+/// The downstream output is collected on another thread so it can't stall the pipe. The
+/// command runs through the platform shell exactly as typed, quotes included.
+fn forward_stdout(mut stdout: ChildStdout, downstream: &str, handles: &JobHandles) {
+    if downstream.is_empty() {
+        match std::io::copy(&mut stdout, &mut std::io::sink()) {
+            Ok(bytes) => handles.log.write().push_str(&format!("Received {} bytes of output\n", bytes)),
+            Err(e) => handles.log.write().push_str(&format!("Error: Could not read the piped output: {}\n", e)),
+        }
+        return;
+    }
+    
+    // If this fails, dropping stdout closes the pipe and FFmpeg stops with an error
+    let mut process = match ffmpeg_utils::shell_command(downstream)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(process) => process,
+        Err(e) => {
+            handles.log.write().push_str(&format!("Error: Could not start the downstream command: {}\n", e));
+            return;
+        }
+    };
+    
+    let stdin = process.stdin.take();
+    let collector = std::thread::spawn(move || process.wait_with_output());
+    let copied = stdin.map(|mut stdin| std::io::copy(&mut stdout, &mut stdin)); // stdin closes here, ending the input
+    drop(stdout);
+    
+    let mut log = handles.log.write();
+    match copied {
+        Some(Ok(bytes)) => log.push_str(&format!("Sent {} bytes to the downstream command\n", bytes)),
+        Some(Err(e)) => log.push_str(&format!("Error: The downstream command stopped reading: {}\n", e)),
+        None => {}
+    }
+    match collector.join() {
+        Ok(Ok(output)) => {
+            log.push_str(&String::from_utf8_lossy(&output.stdout));
+            log.push_str(&String::from_utf8_lossy(&output.stderr));
+            log.push_str(&format!("Downstream command finished with status: {}\n", output.status));
+        },
+        Ok(Err(e)) => log.push_str(&format!("Error: Could not wait for the downstream command: {}\n", e)),
+        Err(_) => log.push_str("Error: Lost track of the downstream command\n"),
+    }
+}
+
+/// Weight of the newest sample in the progress moving average
+const PROGRESS_SMOOTHING: f32 = 0.3;
+
+/// Parse FFmpeg's stderr line by line, appending to the log and updating progress.
+/// Without a duration there's no percentage, so the frames and time done are tracked instead.
+fn read_progress(stderr: ChildStderr, duration: Option<f64>, handles: &JobHandles, last_update: &RwLock<Instant>) {
+    let started = Instant::now();
+    let mut smoothed = 0.0;
+    *handles.eta.write() = None;
+    *handles.live_progress.write() = duration.is_none().then_some(LiveProgress { started, frames: 0, time: 0.0 });
+    *handles.run_sizes.write() = None;
+    
+    // Progress lines end in '\r' rather than '\n', so split on both
+    let mut reader = BufReader::new(stderr);
+    let mut chunk = Vec::new();
+    while reader.read_until(b'\r', &mut chunk).is_ok_and(|n| n > 0) {
+        let text = String::from_utf8_lossy(&chunk).into_owned();
+        chunk.clear();
+        
+        for line_content in text.split(['\r', '\n']).filter(|line| !line.trim().is_empty()) {
+            // Add line to log with newline
+            handles.log.write().push_str(&format!("{line_content}\n"));
+            
+            if let Some(sizes) = ffmpeg_utils::parse_size_breakdown(line_content) {
+                *handles.run_sizes.write() = Some(sizes);
+            }
+            
+            // Parse progress information
+            if line_content.contains("frame=") || line_content.contains("time=") {
+                *last_update.write() = Instant::now();
+            }
+            if let Some(start) = line_content.find("time=") {
+                let time_str = line_content[start + 5..]
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or("00:00:00.00");
+                let current_time = parse_timecode(time_str);
+                let Some(duration) = duration else {
+                    let frames = line_content.split_once("frame=")
+                        .and_then(|(_, rest)| rest.split_whitespace().next())
+                        .and_then(|frames| frames.parse().ok());
+                    if let Some(live) = handles.live_progress.write().as_mut() {
+                        live.frames = frames.unwrap_or(live.frames);
+                        live.time = current_time;
+                    }
+                    handles.ctx.request_repaint();
+                    continue;
+                };
+                let raw = (current_time / duration).clamp(0.0, 1.0) as f32;
+                
+                // The ETA works from the raw value, the bar from the smoothed one
+                if raw > 0.01 {
+                    let elapsed = started.elapsed().as_secs_f32();
+                    *handles.eta.write() = Some(Duration::from_secs_f32(elapsed / raw * (1.0 - raw)));
+                }
+                smoothed += PROGRESS_SMOOTHING * (raw - smoothed);
+                *handles.progress.write() = if handles.smooth_progress { smoothed } else { raw };
+                
+                // Force UI update
+                handles.ctx.request_repaint();
+            }
+        }
+    }
+    *handles.eta.write() = None;
+}
+
+/// Poll the child until it exits without holding the lock, so `stop_ffmpeg` can always reach it.
+/// `tick` gets the running process on every poll.
+fn wait_for_child(child: &Mutex<Option<Child>>, mut tick: impl FnMut(&mut Child)) -> Option<ExitStatus> {
+    loop {
+        {
+            let mut guard = child.lock().ok()?;
+            let process = guard.as_mut()?; // Cleared by stop_ffmpeg
+            match process.try_wait() {
+                Ok(Some(status)) => {
+                    *guard = None; // Clear child process reference
+                    return Some(status);
+                },
+                Ok(None) => tick(process),
+                Err(_) => {
+                    *guard = None;
+                    return None;
+                }
+            }
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/// How long a Stop that keeps the output waits for FFmpeg to finish writing before killing it
+const GRACEFUL_STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Interrupt the child and wait up to `timeout` for it to exit by itself.
+/// Returns false, leaving the child to be killed, if it couldn't be interrupted or didn't exit in time.
+fn interrupt_child(child: &mut Child, timeout: Duration) -> bool {
+    if !ffmpeg_utils::interrupt(child) {
+        return false;
+    }
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        if let Ok(Some(_)) = child.try_wait() {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    false
+}
+
+impl eframe::App for MyApp {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        self.apply_ui_scale(ctx);
+        self.poll_file_dialog();
+        self.handle_global_hotkeys(ctx);
+        
+        // Pasted paths often come wrapped in quotes; only tidy once the result is a real file,
+        // so the field isn't rewritten while the user is still typing
+        let cleaned_input = ffmpeg_utils::clean_path(&self.input_path);
+        if cleaned_input != self.input_path && Path::new(&cleaned_input).exists() {
+            self.input_path = cleaned_input;
+            self.update_command();
+        }
+        if self.probed_path != self.input_path && Path::new(&self.input_path).exists() {
+            self.start_probe(ctx.clone());
+        }
+        self.apply_probe_results(ctx);
+        self.poll_frame_count();
+        self.poll_crop_detect();
+
+        let running = *self.running.read();
+        let progress = *self.progress.read();
+        let known_length = self.live_progress.read().is_none();
+        self.taskbar.update(ctx, frame, (running && known_length).then_some(progress));
+
+        // Main layout with a top panel for controls and a central panel for content
+        egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.heading("FFmpeg GUI");
+                self.favorites_ui(ui);
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.button("⚙ Settings").clicked() {
+                        self.show_settings = !self.show_settings;
+                    }
+                    for mode in UiMode::all() {
+                        ui.selectable_value(&mut self.settings.ui_mode, mode, mode.display_name())
+                            .on_hover_text("Simple shows only the common options; anything already in use stays visible");
+                    }
+                    if ui.add_enabled(!self.undo_stack.is_empty(), egui::Button::new("↶ Undo"))
+                        .on_hover_text("Undo the last settings change (Ctrl+Z)")
+                        .clicked()
+                    {
+                        self.undo();
+                    }
+                    let running = *self.running.read();
+                    let stopping = *self.stopping.read();
+                    if ui.add_enabled(!running && !stopping, egui::Button::new("Start").min_size(egui::vec2(80.0, 0.0))).clicked() {
+                        self.run(ctx.clone());
+                    }
+                    let stop_hint = if self.settings.keep_partial { "Stop, keeping what was encoded so far" } else { "Stop and delete the partial output" };
+                    if ui.add_enabled(running, egui::Button::new("Stop").min_size(egui::vec2(80.0, 0.0))).on_hover_text(stop_hint).clicked() {
+                        self.stop_ffmpeg();
+                    }
+                    if ui.button("Cleanup")
+                        .on_hover_text("Stop anything running and delete the temp files left by previews, benchmarks and unfinished encodes")
+                        .clicked()
+                    {
+                        self.cleanup();
+                    }
+                });
+            });
+        });
+
+        // The log gets its own resizable panel, so the options scroll on their own in a small window
+        egui::TopBottomPanel::bottom("log_panel").resizable(true).default_height(260.0).min_height(140.0).show(ctx, |ui| {
+            // Log controls stay pinned under the log
+            egui::TopBottomPanel::bottom("log_controls").show_inside(ui, |ui| {
+                ui.vertical_centered_justified(|ui| {
+                    ui.add_space(5.0);
+                    
+                    ui.horizontal(|ui| {
+                        if ui.button("📋 Copy Command").clicked()
+                            && let Err(e) = arboard::Clipboard::new().and_then(|mut c| c.set_text(self.last_command.clone()))
+                        {
+                            self.output_log.write().push_str(&format!(
+                                "Clipboard unavailable: {}. The command is selected under FFmpeg Command; copy it with Ctrl+C.\n",
+                                e
+                            ));
+                            self.clipboard_failed = true;
+                            self.select_command = true;
+                        }
+                        
+                        let has_encode = self.last_encode.read().is_some();
+                        if ui.add_enabled(has_encode, egui::Button::new("🔍 Compare Before/After"))
+                            .on_disabled_hover_text("Available after a successful encode")
+                            .clicked()
+                        {
+                            self.show_compare = true;
+                            if self.compare_textures.is_none() {
+                                self.compare_time = self.compare_length() / 2.0;
+                                self.extract_compare_frames(ctx.clone());
+                            }
+                        }
+                        
+                        let previewing = self.preview_child.lock().is_ok_and(|child| child.is_some());
+                        if ui.add_enabled(!previewing, egui::Button::new("👁 Quick Preview"))
+                            .on_hover_text(format!("Encode the first {}s with the current settings and open it; works while another encode runs", app_state::QUICK_PREVIEW_SECS))
+                            .clicked()
+                        {
+                            self.quick_preview();
+                        }
+                        
+                        if ui.button("⏱ Benchmark Encoders").clicked() {
+                            self.show_benchmark = true;
+                        }
+                        
+                        if ui.button("⚖ CRF A/B").on_hover_text("Encode a segment at two quality values and compare them").clicked() {
+                            self.show_ab = true;
+                        }
+                        
+                        if ui.button("🗑️ Clear Log").clicked() {
+                            *self.output_log.write() = String::new();
+                        }
+                        
+                        ui.checkbox(&mut self.auto_scroll, "Auto-scroll");
+                    });
+                });
+            });
+
+            // Progress bar with proper scaling
+            let progress = *self.progress.read();
+            let running = *self.running.read();
+            let live = *self.live_progress.read();
+            
+            if let Some(live) = live.filter(|_| running) {
+                // A percentage of an unknown length would be made up, so show what's been done
+                // An exact count of the input's frames at least gives something to go by
+                let frames = match self.counted_frames {
+                    Some(total) => format!("{} of {} frames", live.frames, total),
+                    None => format!("{} frames", live.frames),
+                };
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(format!(
+                        "Elapsed {} · {} · {} of output written (length unknown)",
+                        ffmpeg_utils::format_duration(live.started.elapsed()),
+                        frames,
+                        ffmpeg_utils::format_duration(Duration::from_secs_f64(live.time.max(0.0)))
+                    ));
+                });
+            } else {
+                // Only animate the progress bar when a process is running
+                ui.add(
+                    egui::ProgressBar::new(progress)
+                        .show_percentage()
+                        .animate(running) // Only animate when actually running
+                );
+            }
+            
+            // Only show percentage text when running
+            if (running || progress > 0.0) && (live.is_none() || !running) {
+                match *self.eta.read() {
+                    Some(eta) if running => ui.label(format!(
+                        "Progress: {:.1}% (about {} left)",
+                        progress * 100.0,
+                        ffmpeg_utils::format_duration(eta)
+                    )),
+                    _ => ui.label(format!("Progress: {:.1}%", progress * 100.0)),
+                };
+            }
+            // The unknown-length line above already counts the time
+            if let Some(started) = self.encode_started.filter(|_| running && live.is_none()) {
+                ui.label(format!("Elapsed: {}", ffmpeg_utils::format_duration(started.elapsed())));
+            }
+
+            // Where the bytes of the last output went, from FFmpeg's closing summary
+            let sizes = self.output_sizes.read().clone();
+            if let Some((output, sizes)) = sizes.as_ref().filter(|_| !running) {
+                let name = output.file_name().unwrap_or_default().to_string_lossy();
+                ui.collapsing(format!("Output size: {} ({})", ffmpeg_utils::format_size(sizes.total()), name), |ui| {
+                    size_breakdown_ui(ui, sizes);
+                });
+            }
+
+            // Log output with better spacing
+            ui.vertical_centered_justified(|ui| {
+                ui.add_space(10.0);
+                ui.heading("Output Log");
+                
+                // Get the log output as a string
+                let output = self.output_log.read().clone();
+                
+                ScrollArea::vertical()
+                    .auto_shrink([false; 2])
+                    .stick_to_bottom(self.auto_scroll)
+                    .show(ui, |ui| {
+                        // Create the text edit widget inside the scroll area
+                        ui.add_sized(
+                            ui.available_size(),
+                            egui::TextEdit::multiline(&mut output.as_str())
+                                .font(egui::FontId::monospace(self.settings.log_font_size))
+                                .desired_width(ui.available_width())
+                                .frame(true),  // Enable the frame
+                        );
+                    });
+            });
+        });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ScrollArea::vertical().auto_shrink([false; 2]).show(ui, |ui| {
+                // Fields that differ from the loaded preset get a marker next to their label
+                let changes = self.preset_changes();
+                
+                // Simple mode hides less common options, but never one that is in use
+                let advanced = self.settings.ui_mode == UiMode::Advanced;
+
+                // Offer to pick up a batch that was interrupted by a crash or by closing the app
+                if let Some(job) = &self.pending_resume {
+                    let (done, total) = (job.completed(), job.items.len());
+                    let mut resume = false;
+                    let mut discard = false;
+                    ui.horizontal(|ui| {
+                        ui.colored_label(
+                            egui::Color32::YELLOW,
+                            format!("An unfinished batch was found ({} of {} files done).", done, total),
+                        );
+                        resume = ui.button("Resume").clicked();
+                        discard = ui.button("Discard").clicked();
+                    });
+                    if resume {
+                        self.resume_batch(ctx.clone());
+                    } else if discard {
+                        BatchJob::discard();
+                        self.pending_resume = None;
+                    }
+                    ui.separator();
+                }
+
+                // A dialog that never appears shouldn't block browsing for good
+                if let Some(dialog) = &self.file_dialog {
+                    let target = dialog.target;
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(format!("Waiting for the {} dialog...", target.display_name()));
+                        if ui.button("Give up")
+                            .on_hover_text("Use this if the dialog never appeared, then type the path instead")
+                            .clicked()
+                        {
+                            self.file_dialog = None;
+                            self.output_log.write().push_str(&format!(
+                                "Stopped waiting for the {} dialog. Type the path into its field instead.\n",
+                                target.display_name()
+                            ));
+                        }
+                    });
+                }
+                let browsing = self.file_dialog.is_some();
+
+                // Input file selection
+                let image_input = self.selected_function == FunctionType::ImageToVideo;
+                ui.horizontal(|ui| {
+                    ui.label(if image_input { "Image file:" } else { "Input file:" });
+                    ui.text_edit_singleline(&mut self.input_path);
+                    let (filter_name, extensions): (&str, &[&str]) = if image_input {
+                        ("Images", &["png", "jpg", "jpeg", "bmp", "webp"])
+                    } else {
+                        ("Media files", &["mp4", "mkv", "mov", "avi", "mp3", "wav"])
+                    };
+                    if ui.add_enabled(!browsing, egui::Button::new("Browse")).clicked() {
+                        self.browse(DialogTarget::Input, ctx, move || {
+                            rfd::FileDialog::new().add_filter(filter_name, extensions).pick_file().map(|path| vec![path])
+                        });
+                    }
+                    if self.probing {
+                        ui.spinner();
+                        ui.weak("Probing...");
+                    }
+                });
+                self.frame_count_ui(ui, ctx);
+                
+                if self.selected_function.supports_audio_replacement()
+                    && ui.checkbox(&mut self.replace_audio, "Take audio from another file")
+                        .on_hover_text("Use the audio of a second file instead of the input's own, e.g. a dub or new music. The video is kept as it is.")
+                        .changed()
+                {
+                    self.update_command();
+                }
+                
+                // Music under a voiceover: both tracks mixed into one at the chosen balance
+                if self.replace_audio && self.selected_function == FunctionType::CompressVideo {
+                    ui.indent("mix_audio", |ui| {
+                        ui.horizontal(|ui| {
+                            let mut changed = ui.checkbox(&mut self.mix_audio, "Mix with the original audio")
+                                .on_hover_text("Keep the input's first audio track and mix the file's audio under it (amix) instead of replacing it. The output runs as long as the input's audio.")
+                                .changed();
+                            if self.mix_audio {
+                                ui.label("Balance:");
+                                changed |= ui.add(egui::Slider::new(&mut self.mix_balance, 0.0..=1.0)
+                                    .show_value(false))
+                                    .on_hover_text("Left keeps only the original, right only the added track; in the middle both play at full volume")
+                                    .changed();
+                                let (original, added) = self.mix_weights();
+                                ui.weak(format!("original {:.0}%, added {:.0}%", original * 100.0, added * 100.0));
+                                if !self.input_has_audio(&self.input_path) {
+                                    ui.weak("(the input has no audio, so the file's audio is used as it is)");
+                                }
+                            }
+                            if changed {
+                                self.update_command();
+                            }
+                        });
+                    });
+                }
+                
+                // Second input for functions that combine a separate audio file
+                if self.uses_audio_input() {
+                    ui.horizontal(|ui| {
+                        ui.label("Audio file:");
+                        if ui.text_edit_singleline(&mut self.audio_input_path).changed() {
+                            self.update_command();
+                        }
+                        if ui.add_enabled(!browsing, egui::Button::new("Browse")).clicked() {
+                            self.browse(DialogTarget::AudioInput, ctx, || {
+                                rfd::FileDialog::new()
+                                    .add_filter("Audio files", &["mp3", "wav", "flac", "m4a", "aac", "ogg", "opus"])
+                                    .pick_file()
+                                    .map(|path| vec![path])
+                            });
+                        }
+                    });
+                    let shortest_hint = if self.selected_function.needs_audio_input() {
+                        "Stop when the audio ends (-shortest). Without it the looped image never ends and FFmpeg has to be stopped by hand."
+                    } else {
+                        "Stop when the video or the audio ends, whichever is first (-shortest). Without it the output runs to the longer one: silence or a frozen last frame fills the rest."
+                    };
+                    if ui.checkbox(&mut self.shortest, "Finish at shortest input")
+                        .on_hover_text(shortest_hint)
+                        .changed()
+                    {
+                        self.update_command();
+                    }
+                }
+
+                // Output file selection
+                ui.horizontal(|ui| {
+                    ui.label("Output file:");
+                    if ui.text_edit_singleline(&mut self.output_path).lost_focus() {
+                        self.update_command();
+                    }
+                    if ui.checkbox(&mut self.test_run, "Test run")
+                        .on_hover_text(format!("Write to {}/<name>_preview and overwrite it on every run", app_state::TEST_RUN_DIR))
+                        .changed()
+                    {
+                        self.output_path = self.default_output();
+                        self.update_command();
+                    }
+                    if ui.add_enabled(!browsing, egui::Button::new("Browse")).clicked() {
+                        let ext = self.output_format.ext();
+                        let file_name = self.output_path.clone();
+                        self.browse(DialogTarget::Output, ctx, move || {
+                            rfd::FileDialog::new()
+                                .add_filter("Output file", &[ext])
+                                .set_file_name(file_name)
+                                .save_file()
+                                .map(|path| vec![path])
+                        });
+                    }
+                });
+
+                if let Some(note) = &self.output_ext_note {
+                    ui.colored_label(egui::Color32::YELLOW, note);
+                }
+
+                // Batch queue
+                if advanced || !self.batch_queue.is_empty() {
+                    egui::CollapsingHeader::new(format!("Batch Queue ({})", self.batch_queue.len()))
+                        .id_source("batch_queue")
+                        .show(ui, |ui| {
+                            let running = self.busy();
+                            ui.horizontal(|ui| {
+                                if ui.add_enabled(!running && !browsing, egui::Button::new("Add files...")).clicked() {
+                                    self.browse(DialogTarget::BatchFiles, ctx, || {
+                                        rfd::FileDialog::new()
+                                            .add_filter("Media files", &["mp4", "mkv", "mov", "avi", "mp3", "wav"])
+                                            .pick_files()
+                                    });
+                                }
+                                if ui.add_enabled(!running && !self.batch_queue.is_empty(), egui::Button::new("Clear queue")).clicked() {
+                                    self.batch_queue.clear();
+                                    self.batch_collisions.clear();
+                                    self.move_queued(|_| None);
+                                }
+                                let done = self.queued_done();
+                                if ui.add_enabled(!running && !done.is_empty(), egui::Button::new("Remove completed"))
+                                    .on_hover_text("Drop files the last batch finished successfully")
+                                    .clicked()
+                                {
+                                    let mut index = 0;
+                                    self.batch_queue.retain(|_| {
+                                        index += 1;
+                                        !done.contains(&(index - 1))
+                                    });
+                                    self.move_queued(|index| {
+                                        (!done.contains(&index)).then(|| index - done.iter().filter(|&&removed| removed < index).count())
+                                    });
+                                }
+                                if ui.add_enabled(!browsing && !self.batch_queue.is_empty(), egui::Button::new("Export as script..."))
+                                    .on_hover_text("Save one FFmpeg command per queued file as a .sh or .bat script")
+                                    .clicked()
+                                {
+                                    self.export_batch_script(ctx);
+                                }
+                                let resumable = !running && self.batch_job.read().as_ref().is_some_and(|job| !job.is_finished());
+                                if resumable && ui.button("Resume stopped batch").clicked() {
+                                    self.resume_batch(ctx.clone());
+                                }
+                            });
+                            
+                            if !self.batch_collisions.is_empty() && !running {
+                                ui.horizontal(|ui| {
+                                    ui.colored_label(egui::Color32::YELLOW, format!(
+                                        "{} output name(s) are shared by more than one file, so one would overwrite another.",
+                                        self.batch_collisions.len()
+                                    )).on_hover_text(self.batch_collisions.iter().map(|path| path.display().to_string()).collect::<Vec<_>>().join("\n"));
+                                    if ui.button("Rename and start")
+                                        .on_hover_text("Add each file's folder name (or its place in the queue) to its output name")
+                                        .clicked()
+                                    {
+                                        self.rename_collisions = true;
+                                        self.run(ctx.clone());
+                                        self.rename_collisions = false;
+                                    }
+                                });
+                            }
+                            
+                            if self.batch_queue.is_empty() {
+                                ui.label("When files are queued, Start processes all of them with the current settings instead of the input file above.");
+                            } else {
+                                // The running batch was planned when it started, so the queue stays fixed until it ends
+                                let mut move_up = None;
+                                let mut remove = None;
+                                let job = self.batch_job.read();
+                                let count = self.batch_queue.len();
+                                for (index, path) in self.batch_queue.iter().enumerate() {
+                                    let status = job.as_ref()
+                                        .and_then(|job| job.queued_status(index))
+                                        .map(|status| status.label());
+                                    ui.horizontal(|ui| {
+                                        if ui.add_enabled(!running && index > 0, egui::Button::new("⬆").small()).on_hover_text("Move up").clicked() {
+                                            move_up = Some(index);
+                                        }
+                                        if ui.add_enabled(!running && index + 1 < count, egui::Button::new("⬇").small()).on_hover_text("Move down").clicked() {
+                                            move_up = Some(index + 1);
+                                        }
+                                        if ui.add_enabled(!running, egui::Button::new("✖").small()).on_hover_text("Remove from queue").clicked() {
+                                            remove = Some(index);
+                                        }
+                                        ui.label(path.file_name().unwrap_or_default().to_string_lossy())
+                                            .on_hover_text(path.display().to_string());
+                                        if let Some(status) = status {
+                                            ui.weak(status);
+                                        }
+                                    });
+                                }
+                                drop(job);
+                                
+                                // Moving an item down is moving the next one up
+                                if let Some(index) = move_up {
+                                    self.batch_queue.swap(index - 1, index);
+                                    self.move_queued(|i| Some(if i == index { i - 1 } else if i == index - 1 { index } else { i }));
+                                }
+                                if let Some(index) = remove {
+                                    self.batch_queue.remove(index);
+                                    self.move_queued(|i| match i.cmp(&index) {
+                                        std::cmp::Ordering::Less => Some(i),
+                                        std::cmp::Ordering::Equal => None,
+                                        std::cmp::Ordering::Greater => Some(i - 1),
+                                    });
+                                }
+                            }
+                        });
+                }
+
+                self.settings_jobs_ui(ui, ctx, advanced);
+
+                self.preset_ui(ui, &changes);
+
+                // Function selection
+                ui.horizontal(|ui| {
+                    field_label(ui, "Function:", changes.contains(&"function"));
+                    // Video functions are greyed out for audio-only inputs once probed
+                    let has_video = self.probed_input_info().is_none_or(MediaInfo::has_video);
+                    for func in FunctionType::all() {
+                        let enabled = has_video || !func.needs_video_stream();
+                        if ui.add_enabled(enabled, egui::RadioButton::new(self.selected_function == func, format!("{:?}", func)))
+                            .on_disabled_hover_text("The input has no video stream")
+                            .clicked()
+                        {
+                            self.selected_function = func;
+                            self.output_path = self.default_output();
+                            self.update_command();
+                        }
+                    }
+                });
+                
+                if self.selected_function.needs_audio_stream()
+                    && self.probed_input_info().is_some_and(|info| !info.has_audio())
+                {
+                    ui.colored_label(egui::Color32::YELLOW, "The input has no audio stream, so this function will fail.");
+                }
+
+                // Show function description
+                ui.label(self.selected_function.description());
+
+                if self.selected_function == FunctionType::CompressVideo {
+                    ui.horizontal(|ui| {
+                        let mut changed = false;
+                        changed |= ui.add_enabled(!self.remove_video, egui::Checkbox::new(&mut self.remove_audio, "Remove audio"))
+                            .on_hover_text("Drop all audio streams (-an)")
+                            .changed();
+                        changed |= ui.add_enabled(!self.remove_audio, egui::Checkbox::new(&mut self.remove_video, "Remove video"))
+                            .on_hover_text("Drop the video and keep only the audio, saved with the audio format's extension (-vn)")
+                            .changed();
+                        change_marker(ui, changes.contains(&"remove_streams"));
+                        if changed {
+                            self.output_path = self.default_output();
+                            self.update_command();
+                        }
+                    });
+                }
+
+                // Show options based on selected function
+                let compressing = self.selected_function == FunctionType::CompressVideo;
+                if self.selected_function.show_audio_options() && !(compressing && self.remove_audio) {
+                    ui.collapsing("Audio Options", |ui| {
+                        ui.horizontal(|ui| {
+                            field_label(ui, "Audio Format:", changes.contains(&"audio_format"));
+                            egui::ComboBox::from_id_source("audio_format")
+                                .selected_text(self.audio_format.display_name())
+                                .show_ui(ui, |ui| {
+                                    // Audio going into a video container has to be a codec it can carry
+                                    let container = (compressing && !self.remove_video).then_some(self.output_format);
+                                    for format in AudioFormat::all() {
+                                        let enabled = container.is_none_or(|container| container.supports_audio(format));
+                                        if ui.add_enabled(enabled, egui::SelectableLabel::new(self.audio_format == format, format.display_name()))
+                                            .on_disabled_hover_text(format!("{} output can't hold {}", self.output_format.display_name(), format.display_name()))
+                                            .clicked()
+                                        {
+                                            self.audio_format = format;
+                                        }
+                                    }
+                                });
+                        });
+                        
+                        // Extra formats from the same run, e.g. a FLAC master and an MP3 copy
+                        if self.selected_function == FunctionType::ExtractAudio {
+                            ui.horizontal(|ui| {
+                                field_label(ui, "Also save as:", changes.contains(&"extra_audio_formats"));
+                                let mut changed = false;
+                                for format in AudioFormat::all() {
+                                    if format == self.audio_format {
+                                        continue;
+                                    }
+                                    let mut checked = self.extra_audio_formats.contains(&format);
+                                    if ui.checkbox(&mut checked, format.display_name())
+                                        .on_hover_text(format!("Also write a .{} file; quality and channel settings are shared", self.audio_ext_for(format)))
+                                        .changed()
+                                    {
+                                        if checked {
+                                            self.extra_audio_formats.push(format);
+                                        } else {
+                                            self.extra_audio_formats.retain(|&extra| extra != format);
+                                        }
+                                        changed = true;
+                                    }
+                                }
+                                if changed {
+                                    self.update_command();
+                                }
+                            });
+                        }
+
+                        // Pick one track of a multi-language input; the output is named after its language
+                        if self.selected_function == FunctionType::ExtractAudio && self.audio_tags.len() > 1 {
+                            ui.horizontal(|ui| {
+                                ui.label("Track:");
+                                let label = |track: Option<usize>, tags: &[AudioTrackTags]| match track {
+                                    None => "All tracks".to_string(),
+                                    Some(i) => {
+                                        let details: Vec<&str> = [tags[i].language.trim(), tags[i].title.trim()]
+                                            .into_iter()
+                                            .filter(|s| !s.is_empty())
+                                            .collect();
+                                        if details.is_empty() {
+                                            format!("Track {}", i + 1)
+                                        } else {
+                                            format!("Track {} ({})", i + 1, details.join(", "))
+                                        }
+                                    }
+                                };
+                                let tracks: Vec<Option<usize>> = std::iter::once(None).chain((0..self.audio_tags.len()).map(Some)).collect();
+                                egui::ComboBox::from_id_source("extract_track")
+                                    .selected_text(label(self.extract_track, &self.audio_tags))
+                                    .show_ui(ui, |ui| {
+                                        for track in tracks {
+                                            if ui.selectable_label(self.extract_track == track, label(track, &self.audio_tags)).clicked() {
+                                                self.extract_track = track;
+                                                self.output_path = self.default_output();
+                                                self.update_command();
+                                            }
+                                        }
+                                    });
+                            });
+                        }
+
+                        // Audiobooks and albums ripped to one file: one output per chapter
+                        let chapter_count = self.probed_input_info().map_or(0, |info| info.chapters.len());
+                        if self.selected_function == FunctionType::ExtractAudio
+                            && chapter_count > 0
+                            && ui.checkbox(&mut self.split_by_chapters, format!("Split by chapters ({})", chapter_count))
+                                .on_hover_text("Extract each chapter to its own file, named after the chapter title")
+                                .changed()
+                        {
+                            self.update_command();
+                        }
+
+                        if self.selected_function == FunctionType::ExtractAudio {
+                            ui.horizontal(|ui| {
+                                field_label(ui, "Container:", changes.contains(&"audio_container"));
+                                egui::ComboBox::from_id_source("audio_container")
+                                    .selected_text(self.audio_container.display_name())
+                                    .show_ui(ui, |ui| {
+                                        for container in AudioContainer::all() {
+                                            let enabled = container.supports(self.audio_format);
+                                            if ui.add_enabled(enabled, egui::SelectableLabel::new(self.audio_container == container, container.display_name()))
+                                                .on_disabled_hover_text(format!("{} can't hold {} audio", container.display_name(), self.audio_format.display_name()))
+                                                .clicked()
+                                            {
+                                                self.audio_container = container;
+                                                self.update_command();
+                                            }
+                                        }
+                                    });
+                                if self.active_audio_container() != self.audio_container {
+                                    ui.weak(format!("(not for {}, using .{})", self.audio_format.display_name(), self.audio_format.ext()));
+                                }
+                            });
+                        }
+
+                        ui.horizontal(|ui| {
+                            field_label(ui, "Channels:", changes.contains(&"channel_layout"));
+                            let source_layout = self.probed_input_info().and_then(MediaInfo::audio_layout);
+                            let label = |layout: ChannelLayout| match (layout, &source_layout) {
+                                (ChannelLayout::Source, Some(source)) => format!("Keep source ({})", source),
+                                _ => layout.display_name().to_string(),
+                            };
+                            let surround_ok = self.audio_format.supports_surround();
+                            egui::ComboBox::from_id_source("channel_layout")
+                                .selected_text(label(self.channel_layout))
+                                .show_ui(ui, |ui| {
+                                    for layout in ChannelLayout::all() {
+                                        let enabled = surround_ok || !layout.is_surround();
+                                        if ui.add_enabled(enabled, egui::SelectableLabel::new(self.channel_layout == layout, label(layout)))
+                                            .on_disabled_hover_text("MP3 only supports mono and stereo")
+                                            .clicked()
+                                        {
+                                            self.channel_layout = layout;
+                                            self.update_command();
+                                        }
+                                    }
+                                });
+                        });
+                        // Cleaning up recordings: only where no video has to stay in step with the audio
+                        if (advanced || self.silence_trim) && self.is_audio_output() {
+                            self.silence_ui(ui, &changes);
+                        }
+                        
+                        // Album art for extracted music
+                        if (advanced || self.cover_art != CoverArt::None)
+                            && self.selected_function == FunctionType::ExtractAudio
+                            && self.cover_art_supported()
+                        {
+                            ui.horizontal(|ui| {
+                                ui.label("Cover art:");
+                                egui::ComboBox::from_id_source("cover_art")
+                                    .selected_text(self.cover_art.display_name())
+                                    .show_ui(ui, |ui| {
+                                        for cover in CoverArt::all() {
+                                            if ui.selectable_value(&mut self.cover_art, cover, cover.display_name()).clicked() {
+                                                self.update_command();
+                                            }
+                                        }
+                                    });
+                                if self.cover_art == CoverArt::File {
+                                    if ui.text_edit_singleline(&mut self.cover_path).changed() {
+                                        self.update_command();
+                                    }
+                                    if ui.add_enabled(!browsing, egui::Button::new("Browse")).clicked() {
+                                        self.browse(DialogTarget::Cover, ctx, || {
+                                            rfd::FileDialog::new().add_filter("Images", &["jpg", "jpeg", "png"]).pick_file().map(|path| vec![path])
+                                        });
+                                    }
+                                }
+                            });
+                        }
+
+                        // Show different options based on audio format
+                        match self.audio_format {
+                            AudioFormat::MP3 => {
+                                // MP3 can use either bitrate or quality level (VBR), but not both
+                                ui.horizontal(|ui| {
+                                    ui.radio_value(&mut self.use_audio_quality, true, "Variable Bitrate (VBR)");
+                                    ui.radio_value(&mut self.use_audio_quality, false, "Constant Bitrate (CBR)");
+                                });
+
+                                if self.use_audio_quality {
+                                    ui.horizontal(|ui| {
+                                        field_label(ui, "MP3 Quality:", changes.contains(&"audio_quality"));
+                                        // The same 0-10 scale as Vorbis; the command gets LAME's reversed one
+                                        if ui.add(egui::DragValue::new(&mut self.audio_quality)
+                                            .clamp_range(0..=10))
+                                            .on_hover_text(format!("0=smallest, 10=best quality (LAME -q:a {})", self.vbr_quality()))
+                                            .changed() 
+                                        {
+                                            self.update_command();
+                                        }
+                                    });
+                                } else {
+                                    ui.horizontal(|ui| {
+                                        field_label(ui, "Bitrate:", changes.contains(&"audio_bitrate"));
+                                        if ui.add(egui::DragValue::new(&mut self.audio_bitrate)
+                                            .clamp_range(8..=320)
+                                            .suffix(" kbps"))
+                                            .changed() {
+                                            self.update_command();
+                                        }
+                                    });
+                                }
+                            },
+                            AudioFormat::VORBIS => {
+                                ui.horizontal(|ui| {
+                                    if ui.radio_value(&mut self.use_audio_quality, true, "Quality (VBR)").clicked()
+                                        | ui.radio_value(&mut self.use_audio_quality, false, "Bitrate").clicked()
+                                    {
+                                        self.update_command();
+                                    }
+                                });
+                                
+                                if self.use_audio_quality {
+                                    ui.horizontal(|ui| {
+                                        field_label(ui, "Vorbis Quality:", changes.contains(&"audio_quality"));
+                                        if ui.add(egui::DragValue::new(&mut self.audio_quality)
+                                            .clamp_range(0..=10))
+                                            .on_hover_text("0=smallest, 10=best quality; 3-6 suits most music")
+                                            .changed()
+                                        {
+                                            self.update_command();
+                                        }
+                                    });
+                                } else {
+                                    ui.horizontal(|ui| {
+                                        field_label(ui, "Bitrate:", changes.contains(&"audio_bitrate"));
+                                        if ui.add(egui::DragValue::new(&mut self.audio_bitrate)
+                                            .clamp_range(45..=500)
+                                            .suffix(" kbps"))
+                                            .changed()
+                                        {
+                                            self.update_command();
+                                        }
+                                    });
+                                }
+                            },
+                            AudioFormat::OPUS => {
+                                ui.horizontal(|ui| {
+                                    field_label(ui, "Opus Bitrate:", changes.contains(&"audio_bitrate"));
+                                    
+                                    // Allow direct input with custom values
+                                    if ui.add(egui::DragValue::new(&mut self.audio_bitrate)
+                                        .clamp_range(8..=512)
+                                        .suffix(" kbps"))
+                                        .on_hover_text("Common values: 16, 24, 32, 48, 64, 96, 128, 192, 256 kbps")
+                                        .changed() 
+                                    {
+                                        self.update_command();
+                                    }
+                                    
+                                    // Add buttons for common bitrates
+                                    ui.horizontal(|ui| {
+                                        for &standard in &[32, 64, 128, 192, 256] {
+                                            if ui.small_button(format!("{}", standard)).clicked() {
+                                                self.audio_bitrate = standard;
+                                                self.update_command();
+                                            }
+                                        }
+                                    });
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.label("Compression:");
+                                    if ui.add(egui::DragValue::new(&mut self.audio_quality)
+                                        .clamp_range(0..=10))
+                                        .on_hover_text("0=fastest, 10=smallest file")
+                                        .changed() 
+                                    {
+                                        self.update_command();
+                                    }
+                                });
+                                ui.horizontal(|ui| {
+                                    field_label(ui, "Cutoff:", changes.contains(&"audio_cutoff"));
+                                    let label = |cutoff: u32| if cutoff == 0 { "Auto".to_string() } else { format!("{} kHz", cutoff / 1000) };
+                                    egui::ComboBox::from_id_source("opus_cutoff")
+                                        .selected_text(label(self.audio_cutoff))
+                                        .show_ui(ui, |ui| {
+                                            for cutoff in std::iter::once(0).chain(OPUS_CUTOFFS) {
+                                                if ui.selectable_value(&mut self.audio_cutoff, cutoff, label(cutoff)).clicked() {
+                                                    self.update_command();
+                                                }
+                                            }
+                                        })
+                                        .response
+                                        .on_hover_text("Lowpass filter frequency. Lower values save bits at low bitrates; Auto lets the encoder decide.");
+                                });
+                            },
+                            AudioFormat::AAC => {
+                                ui.horizontal(|ui| {
+                                    field_label(ui, "AAC Encoder:", changes.contains(&"aac_encoder"));
+                                    let fdk_available = self.has_encoder(AacEncoder::Fdk.codec());
+                                    egui::ComboBox::from_id_source("aac_encoder")
+                                        .selected_text(self.aac_encoder.display_name())
+                                        .show_ui(ui, |ui| {
+                                            for encoder in AacEncoder::all() {
+                                                let enabled = encoder != AacEncoder::Fdk || fdk_available;
+                                                if ui.add_enabled(enabled, egui::SelectableLabel::new(self.aac_encoder == encoder, encoder.display_name()))
+                                                    .on_disabled_hover_text("Not available in the installed FFmpeg build")
+                                                    .clicked()
+                                                {
+                                                    self.aac_encoder = encoder;
+                                                    self.update_command();
+                                                }
+                                            }
+                                        });
+                                });
+                                
+                                if self.aac_encoder == AacEncoder::Fdk {
+                                    ui.horizontal(|ui| {
+                                        ui.label("VBR Mode:");
+                                        if ui.add(egui::DragValue::new(&mut self.fdk_vbr)
+                                            .clamp_range(0..=5))
+                                            .on_hover_text("1=lowest to 5=highest quality, 0=use the bitrate below")
+                                            .changed()
+                                        {
+                                            self.update_command();
+                                        }
+                                    });
+                                }
+                                
+                                // In FDK VBR mode the bitrate is chosen by the encoder
+                                if self.aac_encoder != AacEncoder::Fdk || self.fdk_vbr == 0 {
+                                    ui.horizontal(|ui| {
+                                        field_label(ui, "AAC Bitrate:", changes.contains(&"audio_bitrate"));
+                                    
+                                        // Allow direct input with custom values
+                                        if ui.add(egui::DragValue::new(&mut self.audio_bitrate)
+                                            .clamp_range(8..=512)
+                                            .suffix(" kbps"))
+                                            .on_hover_text("Common values: 32, 64, 96, 128, 160, 192, 224, 256, 320 kbps")
+                                            .changed() 
+                                        {
+                                            self.update_command();
+                                        }
+                                    
+                                        // Add buttons for common bitrates
+                                        ui.horizontal(|ui| {
+                                            for &standard in &[64, 128, 192, 256, 320] {
+                                                if ui.small_button(format!("{}", standard)).clicked() {
+                                                    self.audio_bitrate = standard;
+                                                    self.update_command();
+                                                }
+                                            }
+                                        });
+                                    });
+                                }
+                                
+                                ui.horizontal(|ui| {
+                                    field_label(ui, "Cutoff:", changes.contains(&"audio_cutoff"));
+                                    if ui.add(egui::DragValue::new(&mut self.audio_cutoff)
+                                        .clamp_range(0..=22050)
+                                        .speed(100)
+                                        .custom_formatter(|value, _| if value == 0.0 { "Auto".to_string() } else { format!("{} Hz", value) }))
+                                        .on_hover_text("Lowpass filter frequency. Lower values save bits at low bitrates; 0 (Auto) lets the encoder decide.")
+                                        .changed()
+                                    {
+                                        self.update_command();
+                                    }
+                                });
+                            },
+                            AudioFormat::FLAC => {
+                                ui.horizontal(|ui| {
+                                    ui.label("Compression:");
+                                    if ui.add(egui::DragValue::new(&mut self.audio_quality)
+                                        .clamp_range(0..=12))
+                                        .on_hover_text("0=fastest, 12=smallest file")
+                                        .changed() 
+                                    {
+                                        self.update_command();
+                                    }
+                                });
+                            },
+                            AudioFormat::WAV => {
+                                ui.label("WAV uses uncompressed PCM audio (no quality settings)");
+                            }
+                        }
+                    });
+                }
+
+                if self.selected_function.show_video_options() && !self.remove_video {
+                    ui.collapsing("Video Options", |ui| {
+                        // Encoder selection, hardware encoders only when the FFmpeg build has them
+                        ui.horizontal(|ui| {
+                            field_label(ui, "Encoder:", changes.contains(&"video_encoder"));
+                            egui::ComboBox::from_id_source("video_encoder")
+                                .selected_text(self.video_encoder.display_name())
+                                .show_ui(ui, |ui| {
+                                    for encoder in VideoEncoder::all() {
+                                        let enabled = self.has_encoder(encoder.codec());
+                                        if ui.add_enabled(enabled, egui::SelectableLabel::new(self.video_encoder == encoder, encoder.display_name()))
+                                            .on_disabled_hover_text("Not available in the installed FFmpeg build")
+                                            .clicked()
+                                        {
+                                            self.set_video_encoder(encoder);
+                                            self.update_command();
+                                        }
+                                    }
+                                });
+                        });
+                        
+                        // Auto: quality, preset and size picked from the probed input
+                        let probed = self.probed_path == self.input_path && self.media_info.is_some();
+                        ui.horizontal(|ui| {
+                            ui.add_enabled(probed || self.settings.auto_video, egui::Checkbox::new(&mut self.settings.auto_video, "Auto settings"))
+                                .on_hover_text("Pick the quality, preset and resolution from the input's size, frame rate and bitrate. Change any of them to take over.")
+                                .on_disabled_hover_text("Choose an input file first");
+                            if let Some(auto) = &self.auto_applied {
+                                let quality = match auto.quality_mode {
+                                    QualityMode::Crf => format!("CRF {}", auto.crf),
+                                    _ => format!("QP {}", auto.qp),
+                                };
+                                let size = auto.scale_height.map_or("original size".to_string(), ffmpeg_utils::resolution_label);
+                                let preset = if auto.encoding_preset.is_empty() { String::new() } else { format!(", {}", auto.encoding_preset) };
+                                ui.weak(format!("{}{}, {}", quality, preset, size));
+                            }
+                        });
+                        let auto = self.auto_applied.is_some();
+                        
+                        // HDR10 sources need 10-bit output and their metadata to survive an x265 encode
+                        if advanced && self.video_encoder == VideoEncoder::X265 {
+                            let source_hdr = self.probed_path == self.input_path
+                                && self.media_info.as_ref().is_some_and(MediaInfo::is_hdr10);
+                            ui.horizontal(|ui| {
+                                if ui.checkbox(&mut self.hdr10_passthrough, "HDR10 passthrough")
+                                    .on_hover_text("Encode HDR10 sources as 10-bit with the source's mastering display and light level metadata")
+                                    .changed()
+                                {
+                                    self.update_command();
+                                }
+                                change_marker(ui, changes.contains(&"hdr10_passthrough"));
+                                if !source_hdr {
+                                    ui.weak("(source is not HDR10)");
+                                }
+                            });
+                        }
+                        
+                        // Limited/full mismatches show up as washed-out or crushed blacks
+                        if advanced || self.color_range != ColorRange::Auto {
+                            let source_range = self.probed_input_info().and_then(MediaInfo::color_range);
+                            ui.horizontal(|ui| {
+                                field_label(ui, "Colour range:", changes.contains(&"color_range"));
+                                egui::ComboBox::from_id_source("color_range")
+                                    .selected_text(self.color_range.display_name())
+                                    .show_ui(ui, |ui| {
+                                        for range in ColorRange::all() {
+                                            if ui.selectable_value(&mut self.color_range, range, range.display_name()).clicked() {
+                                                self.update_command();
+                                            }
+                                        }
+                                    })
+                                    .response
+                                    .on_hover_text("Limited (16-235) is usual for video; full (0-255) comes from screen recordings and some phones. A different range from the source's converts the levels.");
+                                ui.weak(match source_range {
+                                    Some("pc") => "(source: full)",
+                                    Some(_) => "(source: limited)",
+                                    None => "(source doesn't say)",
+                                });
+                            });
+                        }
+                        
+                        if advanced || self.separate_streams {
+                            ui.checkbox(&mut self.separate_streams, "Encode audio and video separately")
+                                .on_hover_text("Encode the audio to one file and the video to another, then mux them. The audio is kept for the session, so trying other video settings doesn't encode it again. Also helps tell which stream a problem comes from. Single files only; a batch encodes in one go.");
+                        }
+                        
+                        if advanced {
+                            ui.checkbox(&mut self.score_after_encode, "Score against source when done")
+                                .on_hover_text(if self.has_filter("libvmaf") {
+                                    "Log the output's VMAF score compared with the source after a successful encode"
+                                } else {
+                                    "Log the output's SSIM and PSNR compared with the source after a successful encode (this FFmpeg has no libvmaf)"
+                                });
+                        }
+                        
+                        if advanced || self.seek_friendly {
+                            ui.horizontal(|ui| {
+                                if ui.checkbox(&mut self.seek_friendly, "Seek-friendly (frequent keyframes)")
+                                    .on_hover_text(format!(
+                                        "A keyframe every {} frames with no scene-cut adjustment, so editors and players scrub instantly. Good for screen recordings; files come out noticeably larger.",
+                                        SEEK_FRIENDLY_GOP
+                                    ))
+                                    .changed()
+                                {
+                                    self.update_command();
+                                }
+                                change_marker(ui, changes.contains(&"seek_friendly"));
+                            });
+                        }
+                        
+                        // Region of interest: drawn in the probed input's pixels, so only offered once it's probed
+                        let source_size = self.media_info.as_ref()
+                            .filter(|_| self.probed_path == self.input_path)
+                            .and_then(MediaInfo::resolution);
+                        if (advanced || self.crop.is_some()) && source_size.is_some() {
+                            self.crop_ui(ui, ctx);
+                        }
+                        if (advanced || self.roi_enabled) && let Some((width, height)) = source_size {
+                            let supported = self.video_encoder.supports_roi();
+                            let mut changed = false;
+                            ui.add_enabled_ui(supported, |ui| {
+                                changed |= ui.checkbox(&mut self.roi_enabled, "Higher quality in a region")
+                                    .on_hover_text("Spend more bits on one rectangle of the frame, e.g. the code or text in a screen recording. The rest of the frame is encoded as usual.")
+                                    .on_disabled_hover_text("Only x264 and x265 can favour a region")
+                                    .changed();
+                            });
+                            if self.roi_enabled && supported {
+                                ui.indent("roi", |ui| {
+                                    let [x, y, w, h] = &mut self.roi_rect;
+                                    ui.horizontal(|ui| {
+                                        ui.label("Position:");
+                                        changed |= ui.add(egui::DragValue::new(x).clamp_range(0..=width - 1).prefix("x ")).changed();
+                                        changed |= ui.add(egui::DragValue::new(y).clamp_range(0..=height - 1).prefix("y ")).changed();
+                                        ui.label("Size:");
+                                        changed |= ui.add(egui::DragValue::new(w).clamp_range(1..=width).suffix(" px")).changed();
+                                        ui.label("×");
+                                        changed |= ui.add(egui::DragValue::new(h).clamp_range(1..=height).suffix(" px")).changed();
+                                    });
+                                    ui.horizontal(|ui| {
+                                        ui.label("Boost:");
+                                        changed |= ui.add(egui::Slider::new(&mut self.roi_boost, 0.1..=1.0).fixed_decimals(1))
+                                            .on_hover_text("How strongly the encoder favours the region. Higher values take more bits from the rest of the frame.")
+                                            .changed();
+                                    });
+                                    ui.weak(format!("Source is {}×{}; the region follows rotation and scaling", width, height));
+                                    if !self.available_filters.is_empty() && !self.has_filter("addroi") {
+                                        ui.colored_label(egui::Color32::YELLOW, "⚠ This FFmpeg has no addroi filter (needs FFmpeg 4.3 or newer)");
+                                    }
+                                });
+                            }
+                            if changed {
+                                self.update_command();
+                            }
+                        }
+                        
+                        // Smart copy: skip the re-encode when it wouldn't change the video
+                        if advanced {
+                            let reason = self.reencode_reason(&self.input_path, self.scale_height);
+                            let hover = match &reason {
+                                Some(reason) => format!("Will re-encode: {}", reason),
+                                None => "The source already matches these settings; the video stream will be copied".to_string(),
+                            };
+                            ui.horizontal(|ui| {
+                                if ui.checkbox(&mut self.smart_copy, "Copy video stream when re-encoding isn't needed")
+                                    .on_hover_text(hover)
+                                    .changed()
+                                {
+                                    self.update_command();
+                                }
+                                change_marker(ui, changes.contains(&"smart_copy"));
+                            });
+                        }
+                        
+                        // Output resolution, replaced by the ladder when any rung is checked
+                        ui.add_enabled_ui(self.ladder_heights.is_empty(), |ui| {
+                            ui.horizontal(|ui| {
+                                auto_tint(ui, auto);
+                                field_label(ui, "Resolution:", changes.contains(&"scale_height"));
+                                let selected = self.scale_height.map_or("Original".to_string(), ffmpeg_utils::resolution_label);
+                                egui::ComboBox::from_id_source("scale_height")
+                                    .selected_text(selected)
+                                    .show_ui(ui, |ui| {
+                                        if ui.selectable_value(&mut self.scale_height, None, "Original").clicked() {
+                                            self.update_command();
+                                        }
+                                        for height in ffmpeg_utils::STANDARD_HEIGHTS {
+                                            if ui.selectable_value(&mut self.scale_height, Some(height), ffmpeg_utils::resolution_label(height)).clicked() {
+                                                self.update_command();
+                                            }
+                                        }
+                                    });
+                            });
+                        });
+                        
+                        if advanced || !self.ladder_heights.is_empty() {
+                            ui.horizontal(|ui| {
+                                ui.label("Resolution ladder:")
+                                    .on_hover_text("Encode one output per checked height, named with the height suffix");
+                                for height in ffmpeg_utils::STANDARD_HEIGHTS {
+                                    let mut checked = self.ladder_heights.contains(&height);
+                                    if ui.checkbox(&mut checked, ffmpeg_utils::resolution_label(height)).changed() {
+                                        if checked {
+                                            self.ladder_heights.push(height);
+                                            self.ladder_heights.sort_unstable_by(|a, b| b.cmp(a)); // Largest first
+                                        } else {
+                                            self.ladder_heights.retain(|&h| h != height);
+                                        }
+                                        self.update_command();
+                                    }
+                                }
+                            });
+                        }
+                        
+                        // Frame rate mode selection
+                        ui.horizontal(|ui| {
+                            field_label(ui, "Frame Rate Mode:", changes.contains(&"framerate_mode"));
+                            if ui.radio_value(&mut self.framerate_mode, FrameRateMode::CFR, "Constant Frame Rate (CFR)").clicked() {
+                                self.update_command();
+                            }
+                            if ui.radio_value(&mut self.framerate_mode, FrameRateMode::VFR, "Variable Frame Rate (VFR)").clicked() {
+                                // When switching to VFR, ensure we're using bitrate mode
+                                self.quality_mode = QualityMode::Bitrate;
+                                self.update_command();
+                            }
+                            if ui.radio_value(&mut self.framerate_mode, FrameRateMode::Source, "Keep source frame rate")
+                                .on_hover_text("Don't pass -r; frames keep the source's timing and none are dropped or duplicated")
+                                .clicked()
+                            {
+                                self.update_command();
+                            }
+                        });
+                        
+                        // Quality control method, limited to the modes the encoder understands
+                        ui.horizontal(|ui| {
+                            auto_tint(ui, auto);
+                            field_label(ui, "Quality Control Method:", changes.contains(&"quality_mode"));
+                            let vfr = self.framerate_mode == FrameRateMode::VFR;
+                            let current = self.effective_quality_mode();
+                            for &mode in self.video_encoder.quality_modes() {
+                                let enabled = !vfr || !mode.needs_cfr();
+                                let hover = if !enabled {
+                                    "Not available with Variable Frame Rate"
+                                } else if mode.needs_cfr() {
+                                    "Quality-based encoding"
+                                } else {
+                                    "Target an average bitrate"
+                                };
+                                if ui.add_enabled(enabled, egui::RadioButton::new(current == mode, mode.display_name()))
+                                    .on_hover_text(hover)
+                                    .clicked()
+                                {
+                                    self.quality_mode = mode;
+                                    // Only a bitrate can be fitted to a size
+                                    if mode != QualityMode::Bitrate {
+                                        self.size_limit = None;
+                                    }
+                                    self.update_command();
+                                }
+                            }
+                        });
+
+                        ui.horizontal(|ui| {
+                            field_label(ui, "Fit under:", changes.contains(&"size_limit"));
+                            let mut limit = self.size_limit;
+                            egui::ComboBox::from_id_source("size_limit")
+                                .selected_text(limit.map_or("No limit", |limit| limit.display_name()))
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut limit, None, "No limit");
+                                    for option in SizeLimit::all() {
+                                        ui.selectable_value(&mut limit, Some(option), option.display_name());
+                                    }
+                                })
+                                .response
+                                .on_hover_text(format!(
+                                    "Pick the bitrate from the output's length and the audio bitrate so the file lands {:.0}% under the limit",
+                                    two_pass::SIZE_MARGIN * 100.0
+                                ));
+                            if limit != self.size_limit {
+                                self.apply_size_limit(limit);
+                            }
+                        });
+                        
+                        // Say what the limit worked out to, or why it can't be met
+                        if let Some(limit) = self.size_limit {
+                            match self.size_limit_target() {
+                                Some((limit, duration)) => match self.size_limit_bitrate(limit, duration) {
+                                    Some(kbps) => {
+                                        ui.weak(format!(
+                                            "{} kbps fits {} with {} kbps of audio under {} MB",
+                                            kbps.min(app_state::MAX_VIDEO_KBPS),
+                                            ffmpeg_utils::format_duration(Duration::from_secs_f64(duration)),
+                                            self.output_audio_kbps(),
+                                            limit.megabytes()
+                                        ));
+                                    },
+                                    None => {
+                                        ui.colored_label(
+                                            egui::Color32::YELLOW,
+                                            format!("Too long to fit under {} MB; trim it or lower the audio bitrate.", limit.megabytes()),
+                                        );
+                                    },
+                                },
+                                None => {
+                                    ui.weak(format!("{}: waiting for the input's length", limit.display_name()));
+                                },
+                            }
+                            if let Some(problem) = self.size_limit_problem() {
+                                ui.colored_label(egui::Color32::YELLOW, format!("{}.", problem));
+                            }
+                        }
+                        
+                        // Show appropriate quality control based on selection
+                        let quality_mode = self.effective_quality_mode();
+                        if quality_mode == QualityMode::Cqp {
+                            ui.horizontal(|ui| {
+                                auto_tint(ui, auto);
+                                field_label(ui, "Quantizer:", changes.contains(&"qp"));
+                                let hover = if self.video_encoder == VideoEncoder::Theora {
+                                    "Theora quality, 0-10: higher value = better quality and larger files"
+                                } else {
+                                    "Lower value = better quality and larger files (about 20-28 is typical)"
+                                };
+                                if ui.add(egui::DragValue::new(&mut self.qp)
+                                    .speed(1.0)
+                                    .clamp_range(self.video_encoder.qp_range())
+                                    .prefix("QP "))
+                                    .on_hover_text(hover)
+                                    .changed()
+                                {
                                     self.update_command();
                                 }
                                 
-                                // Add buttons for common bitrates
                                 ui.horizontal(|ui| {
-                                    for &standard in &[32, 64, 128, 192, 256] {
+                                    for &standard in self.video_encoder.qp_steps() {
                                         if ui.small_button(format!("{}", standard)).clicked() {
-                                            self.audio_bitrate = standard;
+                                            self.qp = standard;
                                             self.update_command();
                                         }
                                     }
                                 });
                             });
+                        } else if quality_mode == QualityMode::Crf {
                             ui.horizontal(|ui| {
-                                ui.label("Compression:");
-                                if ui.add(egui::DragValue::new(&mut self.audio_quality)
-                                    .clamp_range(0..=10))
-                                    .on_hover_text("0=fastest, 10=smallest file")
+                                auto_tint(ui, auto);
+                                field_label(ui, "Quality:", changes.contains(&"crf"));
+                                // Allow direct input of CRF value
+                                let hover = if self.video_encoder == VideoEncoder::Vp8 {
+                                    "Lower value = better quality (4-63, about 10 for high quality). The video bitrate below caps the rate."
+                                } else {
+                                    "Lower value = better quality (17-18=visually lossless, 23=default, 28=good compression)"
+                                };
+                                if ui.add(egui::DragValue::new(&mut self.crf)
+                                    .speed(1.0)
+                                    .clamp_range(self.video_encoder.crf_range())
+                                    .prefix("CRF "))
+                                    .on_hover_text(hover)
                                     .changed() 
                                 {
                                     self.update_command();
                                 }
+                                
+                                // Add buttons for common CRF values
+                                ui.horizontal(|ui| {
+                                    for &standard in self.video_encoder.crf_steps() {
+                                        if ui.small_button(format!("{}", standard)).clicked() {
+                                            self.crf = standard;
+                                            self.update_command();
+                                        }
+                                    }
+                                });
+                            });
+                        } else {
+                            ui.horizontal(|ui| {
+                                field_label(ui, "Bitrate:", changes.contains(&"video_bitrate"));
+                                
+                                // Allow direct input with high upper limit
+                                // The size limit sets the bitrate while it's on
+                                ui.add_enabled_ui(self.size_limit.is_none(), |ui| {
+                                    if ui.add(egui::DragValue::new(&mut self.video_bitrate)
+                                        .speed(100.0)
+                                        .clamp_range(app_state::MIN_VIDEO_KBPS..=app_state::MAX_VIDEO_KBPS)
+                                        .suffix(" kbps"))
+                                        .on_hover_text("Enter any value between 100-50000 kbps")
+                                        .changed() 
+                                    {
+                                        self.update_command();
+                                    }
+                                    
+                                    // Add buttons for common video bitrates
+                                    ui.horizontal(|ui| {
+                                        for &standard in &[1000, 2500, 5000, 8000, 15000] {
+                                            if ui.small_button(format!("{}", standard)).clicked() {
+                                                self.video_bitrate = standard;
+                                                self.update_command();
+                                            }
+                                        }
+                                    });
+                                });
+                            });
+                            
+                            ui.horizontal(|ui| {
+                                let supports_two_pass = self.video_encoder.supports_two_pass();
+                                ui.add_enabled_ui(supports_two_pass, |ui| {
+                                    if ui.checkbox(&mut self.two_pass, "Two-pass")
+                                        .on_hover_text("Analyse the video first, then encode it using what was learned, landing much closer to the bitrate. Takes longer. Batches and the command preview stay single-pass.")
+                                        .on_disabled_hover_text("This encoder doesn't support FFmpeg's two-pass mode")
+                                        .changed()
+                                    {
+                                        self.update_command();
+                                    }
+                                });
+                                change_marker(ui, changes.contains(&"two_pass"));
+                            });
+                            
+                            if advanced || self.strict_cbr {
+                                ui.horizontal(|ui| {
+                                    if ui.checkbox(&mut self.strict_cbr, "Strict CBR")
+                                        .on_hover_text("Constant bitrate with minrate = maxrate and HRD signalling, for streaming and broadcast targets that reject variable bitrate")
+                                        .changed()
+                                    {
+                                        self.update_command();
+                                    }
+                                    change_marker(ui, changes.contains(&"strict_cbr"));
+                                    if self.strict_cbr {
+                                        ui.label("Buffer:");
+                                        if ui.add(egui::DragValue::new(&mut self.cbr_buffer_secs)
+                                            .speed(0.1)
+                                            .clamp_range(0.1..=10.0)
+                                            .fixed_decimals(1)
+                                            .suffix(" s"))
+                                            .on_hover_text("-bufsize as seconds of bitrate; smaller holds the rate tighter")
+                                            .changed()
+                                        {
+                                            self.update_command();
+                                        }
+                                    }
+                                });
+                            }
+                            
+                            // How far the bitrate stretches at the output resolution, warning when it's too thin
+                            match &self.bpp_note {
+                                Some((advice, true)) => { ui.colored_label(egui::Color32::YELLOW, advice); },
+                                Some((note, false)) => { ui.weak(note); },
+                                None => {}
+                            }
+                        }
+                        
+                        // Add frame rate slider for CFR mode
+                        if self.framerate_mode == FrameRateMode::CFR {
+                            ui.horizontal(|ui| {
+                                field_label(ui, "CFR Method:", changes.contains(&"cfr_method"));
+                                egui::ComboBox::from_id_source("cfr_method")
+                                    .selected_text(self.cfr_method.display_name())
+                                    .show_ui(ui, |ui| {
+                                        for method in CfrMethod::all() {
+                                            if ui.selectable_value(&mut self.cfr_method, method, method.display_name())
+                                                .on_hover_text(method.description())
+                                                .clicked()
+                                            {
+                                                self.update_command();
+                                            }
+                                        }
+                                    })
+                                    .response
+                                    .on_hover_text(self.cfr_method.description());
                             });
-                        },
-                        AudioFormat::AAC => {
                             ui.horizontal(|ui| {
-                                ui.label("AAC Bitrate:");
+                                field_label(ui, "Frame Rate:", changes.contains(&"frame_rate"));
+                                let fps_max = self.original_fps.max(60.0); // Use original FPS or 60 as max
                                 
-                                // Common audio bitrate standards for reference
-                                let bitrate_standards = [32, 64, 96, 128, 160, 192, 224, 256, 320];
+                                // Common FPS values to snap to (filtered to not exceed max)
+                                let common_fps: Vec<f32> = [23.976, 24.0, 25.0, 29.97, 30.0, 50.0, 60.0]
+                                    .iter()
+                                    .filter(|&&fps| fps <= fps_max)
+                                    .cloned()
+                                    .collect();
+                                
+                                // Add original FPS if not already in the list
+                                let mut all_fps = common_fps.clone();
+                                if !all_fps.contains(&self.original_fps) {
+                                    all_fps.push(self.original_fps);
+                                    all_fps.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                                }
                                 
-                                // Allow direct input with custom values
-                                if ui.add(egui::DragValue::new(&mut self.audio_bitrate)
-                                    .clamp_range(8..=512)
-                                    .suffix(" kbps"))
-                                    .on_hover_text(format!("Common values: 32, 64, 96, 128, 160, 192, 224, 256, 320 kbps"))
+                                // Allow direct input of frame rate with upper limit based on original fps
+                                if ui.add(egui::DragValue::new(&mut self.frame_rate)
+                                    .speed(0.1)
+                                    .clamp_range(1.0..=fps_max)
+                                    .suffix(" fps")
+                                    .fixed_decimals(3))
+                                    .on_hover_text(format!("Original: {:.3} fps. Enter any value up to {:.3}", self.original_fps, fps_max))
                                     .changed() 
                                 {
                                     self.update_command();
                                 }
                                 
-                                // Add buttons for common bitrates
+                                // Add buttons for common frame rates and original
                                 ui.horizontal(|ui| {
-                                    for &standard in &[64, 128, 192, 256, 320] {
-                                        if ui.small_button(format!("{}", standard)).clicked() {
-                                            self.audio_bitrate = standard;
+                                    // Only show fps values that don't exceed the max
+                                    let common_fps = [23.976, 24.0, 25.0, 29.97, 30.0, 50.0, 60.0];
+                                    for &fps in common_fps.iter().filter(|&&fps| fps <= fps_max) {
+                                        if ui.small_button(format!("{:.3}", fps)).clicked() {
+                                            self.frame_rate = fps;
                                             self.update_command();
                                         }
                                     }
+                                    
+                                    // Add original fps button if not already in common values
+                                    if !common_fps.contains(&self.original_fps) && ui.small_button("Original").clicked() {
+                                        self.frame_rate = self.original_fps;
+                                        self.update_command();
+                                    }
+                                });
+                            });
+                        }
+                        
+                        // Preset selection; Theora has none
+                        if !self.video_encoder.presets().is_empty() {
+                            ui.horizontal(|ui| {
+                                auto_tint(ui, auto);
+                                field_label(ui, "Encoding Preset:", changes.contains(&"encoding_preset"));
+                                egui::ComboBox::from_id_source("encoding_preset")
+                                    .selected_text(&self.encoding_preset)
+                                    .show_ui(ui, |ui| {
+                                        for preset in self.video_encoder.presets() {
+                                            if ui.selectable_value(&mut self.encoding_preset, preset.to_string(), *preset).clicked() {
+                                                self.update_command();
+                                            }
+                                        }
+                                    });
+                            });
+                        }
+                    });
+                }
+
+                if self.selected_function == FunctionType::ConvertToMp4 {
+                    self.remux_streams_ui(ui);
+                }
+                if self.selected_function.supports_splitting() {
+                    self.split_tracks_ui(ui, ctx);
+                }
+
+                // Per-track tags, which players rely on for their audio track menus
+                if advanced && self.keeps_all_audio() && !self.audio_tags.is_empty() {
+                    ui.collapsing(format!("Audio Tracks ({})", self.audio_tags.len()), |ui| {
+                        let mut changed = false;
+                        let mut move_up = None;
+                        let mut make_default = None;
+                        let count = self.audio_tags.len();
+                        // Listed in output order; some players always start with the first track
+                        egui::Grid::new("audio_tags").num_columns(5).show(ui, |ui| {
+                            ui.label("Track");
+                            ui.label("Language");
+                            ui.label("Title");
+                            ui.label("Default");
+                            ui.label("Order");
+                            ui.end_row();
+                            for (i, tags) in self.audio_tags.iter_mut().enumerate() {
+                                ui.label(format!("#{}", tags.source + 1));
+                                changed |= ui.add(egui::TextEdit::singleline(&mut tags.language)
+                                    .hint_text("eng")
+                                    .desired_width(50.0))
+                                    .changed();
+                                changed |= ui.add(egui::TextEdit::singleline(&mut tags.title)
+                                    .hint_text("English Commentary"))
+                                    .changed();
+                                if ui.checkbox(&mut tags.default, "").changed() {
+                                    changed = true;
+                                    if tags.default {
+                                        make_default = Some(i);
+                                    }
+                                }
+                                ui.horizontal(|ui| {
+                                    if ui.add_enabled(i > 0, egui::Button::new("⬆").small()).clicked() {
+                                        move_up = Some(i);
+                                    }
+                                    if ui.add_enabled(i + 1 < count, egui::Button::new("⬇").small()).clicked() {
+                                        move_up = Some(i + 1);
+                                    }
+                                });
+                                ui.end_row();
+                            }
+                        });
+                        // Only one track can be the default
+                        if let Some(default) = make_default {
+                            for (i, tags) in self.audio_tags.iter_mut().enumerate() {
+                                tags.default = i == default;
+                            }
+                            changed = true;
+                        }
+                        if let Some(i) = move_up {
+                            self.audio_tags.swap(i - 1, i);
+                            changed = true;
+                        }
+                        
+                        // Which subtitle track players should show unless told otherwise
+                        let subtitle_count = self.probed_input_info()
+                            .map_or(0, |info| info.streams.iter().filter(|s| s.codec_type == "subtitle").count());
+                        if subtitle_count > 0 && self.selected_function != FunctionType::ExtractAudio {
+                            ui.horizontal(|ui| {
+                                ui.label("Default subtitle:");
+                                let label = |track: Option<usize>| track.map_or("As in source".to_string(), |i| format!("Track {}", i + 1));
+                                egui::ComboBox::from_id_source("default_subtitle")
+                                    .selected_text(label(self.default_subtitle))
+                                    .show_ui(ui, |ui| {
+                                        for track in std::iter::once(None).chain((0..subtitle_count).map(Some)) {
+                                            changed |= ui.selectable_value(&mut self.default_subtitle, track, label(track)).clicked();
+                                        }
+                                    });
+                            });
+                        }
+                        if changed {
+                            self.update_command();
+                        }
+                    });
+                }
+
+                // Asset managers sort by creation_time, which FFmpeg copies from the source by default
+                if (advanced || self.creation_time != CreationTime::Source) && self.selected_function != FunctionType::Spectrogram {
+                    ui.horizontal(|ui| {
+                        ui.label("Creation time:");
+                        egui::ComboBox::from_id_source("creation_time")
+                            .selected_text(self.creation_time.display_name())
+                            .show_ui(ui, |ui| {
+                                for mode in CreationTime::all() {
+                                    if ui.selectable_value(&mut self.creation_time, mode, mode.display_name()).clicked() {
+                                        self.update_command();
+                                    }
+                                }
+                            });
+                        let source_time = self.media_info.as_ref()
+                            .filter(|_| self.probed_path == self.input_path)
+                            .and_then(|info| info.creation_time.clone());
+                        match self.creation_time {
+                            CreationTime::Source => {
+                                ui.weak(source_time.unwrap_or_else(|| "(source has none)".to_string()));
+                            },
+                            CreationTime::Custom => {
+                                if self.custom_creation_time.is_empty()
+                                    && let Some(time) = source_time
+                                {
+                                    self.custom_creation_time = time;
+                                    self.update_command();
+                                }
+                                if ui.add(egui::TextEdit::singleline(&mut self.custom_creation_time).hint_text("2024-05-01T12:30:00Z"))
+                                    .changed()
+                                {
+                                    self.update_command();
+                                }
+                                if !ffmpeg_utils::is_iso8601(self.custom_creation_time.trim()) {
+                                    ui.colored_label(egui::Color32::YELLOW, "Not an ISO 8601 date and time");
+                                }
+                            },
+                            CreationTime::Now | CreationTime::Clear => {}
+                        }
+                    });
+                }
+
+                if self.selected_function == FunctionType::LoopClip {
+                    ui.horizontal(|ui| {
+                        ui.label("Crossfade:");
+                        if ui.add(egui::DragValue::new(&mut self.loop_crossfade)
+                            .speed(0.05)
+                            .clamp_range(0.1..=10.0)
+                            .fixed_decimals(2)
+                            .suffix(" s"))
+                            .on_hover_text("How much of the segment's end is blended into its start. The clip comes out this much shorter than the segment.")
+                            .changed()
+                        {
+                            self.update_command();
+                        }
+                    });
+                    ui.weak("Use Trim to pick the segment; without it the whole input is looped.");
+                    if let Some(problem) = self.loop_problem() {
+                        ui.colored_label(egui::Color32::YELLOW, problem);
+                    }
+                }
+
+                if self.selected_function == FunctionType::Gif {
+                    self.gif_ui(ui);
+                }
+
+                if self.selected_function == FunctionType::Spectrogram {
+                    ui.horizontal(|ui| {
+                        ui.label("Image size:");
+                        let width = ui.add(egui::DragValue::new(&mut self.spectrogram_size[0]).clamp_range(64..=8192).suffix(" px"));
+                        ui.label("×");
+                        let height = ui.add(egui::DragValue::new(&mut self.spectrogram_size[1]).clamp_range(64..=8192).suffix(" px"));
+                        if width.changed() || height.changed() {
+                            self.update_command();
+                        }
+                    });
+                }
+
+                if self.selected_function.show_output_format() && !self.is_audio_output() {
+                    ui.horizontal(|ui| {
+                        field_label(ui, "Output Format:", changes.contains(&"output_format"));
+                        for format in OutputFormat::all() {
+                            if ui.radio(self.output_format == format, format.display_name()).clicked() {
+                                self.set_output_format(format);
+                                self.update_command();
+                            }
+                        }
+                    });
+                }
+
+                if (advanced || self.rotation != Rotation::None)
+                    && self.selected_function.show_rotation()
+                    && !(self.selected_function == FunctionType::CompressVideo && self.remove_video)
+                {
+                    ui.horizontal(|ui| {
+                        field_label(ui, "Rotation:", changes.contains(&"rotation"));
+                        egui::ComboBox::from_id_source("rotation")
+                            .selected_text(self.rotation.display_name())
+                            .show_ui(ui, |ui| {
+                                for rotation in Rotation::all() {
+                                    if ui.selectable_value(&mut self.rotation, rotation, rotation.display_name()).clicked() {
+                                        self.update_command();
+                                    }
+                                }
+                            })
+                            .response
+                            .on_hover_text("Copied streams are rotated with metadata, re-encoded ones with a filter");
+                    });
+                }
+
+                let picks_segment = matches!(self.selected_function, FunctionType::LoopClip | FunctionType::Gif);
+                if (advanced || self.trim_enabled || picks_segment) && self.selected_function.supports_trim() {
+                    ui.collapsing("Trim", |ui| {
+                        let mut changed = ui.checkbox(&mut self.trim_enabled, "Only encode part of the input").changed();
+                        ui.add_enabled_ui(self.trim_enabled, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.label("Points as:");
+                                changed |= ui.radio_value(&mut self.trim_unit, TrimUnit::Timecode, "Timecode").clicked();
+                                changed |= ui.radio_value(&mut self.trim_unit, TrimUnit::Frame, "Frame number")
+                                    .on_hover_text(format!("Converted using the source frame rate ({:.3} fps)", self.original_fps))
+                                    .clicked();
+                            });
+                            let hint = match self.trim_unit {
+                                TrimUnit::Timecode => "00:00:00.000",
+                                TrimUnit::Frame => "0",
+                            };
+                            let can_step = self.probed_path == self.input_path && self.original_fps > 0.0;
+                            let mut step = None;
+                            for (point, label, text) in [(TrimPoint::Start, "Start:", &mut self.trim_start), (TrimPoint::End, "End:", &mut self.trim_end)] {
+                                ui.horizontal(|ui| {
+                                    ui.label(label);
+                                    changed |= ui.add(egui::TextEdit::singleline(text).hint_text(hint).desired_width(100.0)).changed();
+                                    for (button, delta, hover) in [("⏴", -1, "Previous frame"), ("⏵", 1, "Next frame")] {
+                                        if ui.add_enabled(can_step, egui::Button::new(button).small())
+                                            .on_hover_text(hover)
+                                            .on_disabled_hover_text("Needs a probed video input")
+                                            .clicked()
+                                        {
+                                            step = Some((point, delta));
+                                        }
+                                    }
+                                    // Show what a frame number works out to
+                                    if self.trim_unit == TrimUnit::Frame
+                                        && let Some(seconds) = ffmpeg_utils::parse_trim_point(text, TrimUnit::Frame, self.original_fps)
+                                    {
+                                        ui.weak(format!("= {}", ffmpeg_utils::format_timestamp(seconds)));
+                                    }
+                                });
+                            }
+                            if let Some((point, delta)) = step {
+                                self.step_trim_point(ui.ctx(), point, delta);
+                                changed = true;
+                            }
+                            if let Some(preview) = &mut self.frame_preview {
+                                preview.poll(ui.ctx());
+                                let point = if preview.point == TrimPoint::Start { "Start" } else { "End" };
+                                ui.horizontal(|ui| {
+                                    ui.label(format!("{}: frame {} at {}", point, preview.frame, ffmpeg_utils::format_timestamp(preview.seconds)));
+                                    if preview.loading() {
+                                        ui.spinner();
+                                    }
                                 });
-                            });
-                        },
-                        AudioFormat::FLAC => {
-                            ui.horizontal(|ui| {
-                                ui.label("Compression:");
-                                if ui.add(egui::DragValue::new(&mut self.audio_quality)
-                                    .clamp_range(0..=12))
-                                    .on_hover_text("0=fastest, 12=smallest file")
-                                    .changed() 
-                                {
-                                    self.update_command();
-                                }
-                            });
-                        },
-                        AudioFormat::WAV => {
-                            ui.label("WAV uses uncompressed PCM audio (no quality settings)");
-                        }
-                    }
-                });
-            }
-
-            if self.selected_function.show_video_options() {
-                ui.collapsing("Video Options", |ui| {
-                    // Frame rate mode selection
-                    ui.horizontal(|ui| {
-                        ui.label("Frame Rate Mode:");
-                        if ui.radio_value(&mut self.framerate_mode, FrameRateMode::CFR, "Constant Frame Rate (CFR)").clicked() {
-                            self.update_command();
-                        }
-                        if ui.radio_value(&mut self.framerate_mode, FrameRateMode::VFR, "Variable Frame Rate (VFR)").clicked() {
-                            // When switching to VFR, ensure we're using bitrate mode
-                            self.use_crf = false;
+                                preview.show(ui);
+                            }
+                            if self.selected_function == FunctionType::CompressVideo {
+                                changed |= ui.checkbox(&mut self.keyframes_at_cuts, "Force keyframes at the cut points")
+                                    .on_hover_text("Start the clip on a keyframe and put another at its end, so it can be edited or concatenated cleanly. Always re-encodes the video.")
+                                    .changed();
+                            }
+                            if self.trim_snaps_to_keyframe() {
+                                ui.colored_label(
+                                    egui::Color32::YELLOW,
+                                    "The video is copied, not re-encoded, so the clip starts at the nearest keyframe before the start point. Re-encode (e.g. turn off smart copy) for a frame-exact cut.",
+                                );
+                            }
+                        });
+                        if changed {
                             self.update_command();
                         }
                     });
-                    
-                    // Quality control method
+                }
+
+                if (advanced || self.subtitle_mode != SubtitleMode::Off)
+                    && matches!(self.selected_function, FunctionType::CompressVideo | FunctionType::ConvertToMp4)
+                {
+                    self.subtitles_ui(ui);
+                }
+
+                if (advanced || self.chapter_mode != ChapterMode::Off) && self.selected_function.supports_chapters() {
                     ui.horizontal(|ui| {
-                        ui.label("Quality Control Method:");
-                        let crf_enabled = self.framerate_mode == FrameRateMode::CFR;
-                        ui.add_enabled(crf_enabled, egui::RadioButton::new(self.use_crf, "Constant Rate Factor (CRF)"))
-                            .on_hover_text(if !crf_enabled { "CRF is only available with Constant Frame Rate" } else { "Quality-based encoding" })
-                            .clicked().then(|| {
-                                self.use_crf = true;
-                                self.update_command();
+                        ui.label("Chapters:");
+                        egui::ComboBox::from_id_source("chapter_mode")
+                            .selected_text(self.chapter_mode.display_name())
+                            .show_ui(ui, |ui| {
+                                for mode in ChapterMode::all() {
+                                    if ui.selectable_value(&mut self.chapter_mode, mode, mode.display_name()).clicked() {
+                                        self.update_command();
+                                    }
+                                }
                             });
-                        ui.radio_value(&mut self.use_crf, false, "Bitrate").clicked().then(|| {
-                            self.update_command();
-                        });
+                        match self.chapter_mode {
+                            ChapterMode::Off => {},
+                            ChapterMode::Interval => {
+                                ui.label("every");
+                                ui.add(egui::DragValue::new(&mut self.chapter_interval_min)
+                                    .clamp_range(1..=120)
+                                    .suffix(" min"));
+                            },
+                            ChapterMode::Scene => {
+                                ui.label("threshold");
+                                ui.add(egui::Slider::new(&mut self.scene_threshold, 0.1..=0.9).fixed_decimals(2))
+                                    .on_hover_text("Lower values start a chapter at smaller changes. Scene detection scans the whole input before encoding.");
+                            }
+                        }
                     });
+                }
 
-                    // Show appropriate quality control based on selection
-                    if self.use_crf && self.framerate_mode == FrameRateMode::CFR {
+                let advanced_in_use = !self.settings.force_format.trim().is_empty()
+                    || !self.settings.global_options.trim().is_empty()
+                    || !self.extra_options.trim().is_empty()
+                    || self.custom_filter_graph().is_some()
+                    || self.pipe_output
+                    || (self.copy_timestamps && self.selected_function == FunctionType::ConvertToMp4)
+                    || !self.reset_timestamp_args().is_empty();
+                if advanced || advanced_in_use {
+                    ui.collapsing("Advanced", |ui| {
                         ui.horizontal(|ui| {
-                            ui.label("Quality:");
-                            // Common CRF values
-                            let crf_standards = [17, 18, 20, 23, 28, 30, 35, 40];
-                            
-                            // Allow direct input of CRF value
-                            if ui.add(egui::DragValue::new(&mut self.crf)
-                                .speed(1.0)
-                                .clamp_range(0..=51)
-                                .prefix("CRF "))
-                                .on_hover_text("Lower value = better quality (17-18=visually lossless, 23=default, 28=good compression)")
-                                .changed() 
+                            ui.label("Force format (-f):");
+                            if ui.add(egui::TextEdit::singleline(&mut self.settings.force_format)
+                                .hint_text("auto")
+                                .desired_width(100.0))
+                                .on_hover_text("Muxer to use instead of guessing from the extension, e.g. mpegts, flv, matroska")
+                                .changed()
                             {
                                 self.update_command();
                             }
-                            
-                            // Add buttons for common CRF values
-                            ui.horizontal(|ui| {
-                                for &standard in &[18, 23, 28, 35] {
-                                    if ui.small_button(format!("{}", standard)).clicked() {
-                                        self.crf = standard;
-                                        self.update_command();
-                                    }
-                                }
-                            });
                         });
-                    } else {
                         ui.horizontal(|ui| {
-                            ui.label("Bitrate:");
-                            
-                            // Common video bitrate standards
-                            let bitrate_standards = [500, 750, 1000, 1500, 2000, 2500, 3000, 4000, 5000, 6000, 8000, 10000, 15000, 20000];
-                            
-                            // Allow direct input with high upper limit
-                            if ui.add(egui::DragValue::new(&mut self.video_bitrate)
-                                .speed(100.0)
-                                .clamp_range(100..=50000)
-                                .suffix(" kbps"))
-                                .on_hover_text("Enter any value between 100-50000 kbps")
-                                .changed() 
+                            ui.label("Global options:");
+                            if ui.add(egui::TextEdit::singleline(&mut self.settings.global_options)
+                                .hint_text("-hwaccel auto"))
+                                .on_hover_text("Placed before the first -i, where FFmpeg expects global and input options such as -loglevel or -hwaccel. -nostdin is always added.")
+                                .changed()
                             {
                                 self.update_command();
                             }
-                            
-                            // Add buttons for common video bitrates
-                            ui.horizontal(|ui| {
-                                for &standard in &[1000, 2500, 5000, 8000, 15000] {
-                                    if ui.small_button(format!("{}", standard)).clicked() {
-                                        self.video_bitrate = standard;
-                                        self.update_command();
-                                    }
-                                }
-                            });
                         });
-                    }
-                    
-                    // Add frame rate slider for CFR mode
-                    if self.framerate_mode == FrameRateMode::CFR {
+                        if let Err(e) = ffmpeg_utils::split_args(&self.settings.global_options) {
+                            ui.colored_label(egui::Color32::YELLOW, format!("Global options ignored: {}", e));
+                        }
                         ui.horizontal(|ui| {
-                            ui.label("Frame Rate:");
-                            let fps_max = self.original_fps.max(60.0); // Use original FPS or 60 as max
-                            
-                            // Common FPS values to snap to (filtered to not exceed max)
-                            let common_fps: Vec<f32> = [23.976, 24.0, 25.0, 29.97, 30.0, 50.0, 60.0]
-                                .iter()
-                                .filter(|&&fps| fps <= fps_max)
-                                .cloned()
-                                .collect();
-                            
-                            // Add original FPS if not already in the list
-                            let mut all_fps = common_fps.clone();
-                            if !all_fps.contains(&self.original_fps) {
-                                all_fps.push(self.original_fps);
-                                all_fps.sort_by(|a, b| a.partial_cmp(b).unwrap());
-                            }
-                            
-                            // Allow direct input of frame rate with upper limit based on original fps
-                            if ui.add(egui::DragValue::new(&mut self.frame_rate)
-                                .speed(0.1)
-                                .clamp_range(1.0..=fps_max)
-                                .suffix(" fps")
-                                .fixed_decimals(3))
-                                .on_hover_text(format!("Original: {:.3} fps. Enter any value up to {:.3}", self.original_fps, fps_max))
-                                .changed() 
+                            field_label(ui, "Extra output options:", changes.contains(&"extra_options"));
+                            if ui.add(egui::TextEdit::singleline(&mut self.extra_options)
+                                .hint_text("-tune film"))
+                                .on_hover_text("Placed just before the output file, after everything the GUI sets, so they can add to or override it")
+                                .changed()
                             {
                                 self.update_command();
                             }
-                            
-                            // Add buttons for common frame rates and original
+                        });
+                        if let Err(e) = ffmpeg_utils::split_args(&self.extra_options) {
+                            ui.colored_label(egui::Color32::YELLOW, format!("Extra output options ignored: {}", e));
+                        }
+                        
+                        // The escape hatch for filtering the simple options can't express
+                        if self.selected_function == FunctionType::CompressVideo && !self.remove_video {
                             ui.horizontal(|ui| {
-                                // Only show fps values that don't exceed the max
-                                let common_fps = [23.976, 24.0, 25.0, 29.97, 30.0, 50.0, 60.0];
-                                for &fps in common_fps.iter().filter(|&&fps| fps <= fps_max) {
-                                    if ui.small_button(format!("{:.3}", fps)).clicked() {
-                                        self.frame_rate = fps;
+                                field_label(ui, "Filter graph:", changes.contains(&"filter_complex"));
+                                if ui.add(egui::TextEdit::multiline(&mut self.filter_complex)
+                                    .hint_text("[0:v]split[a][b];[b]hflip[f];[a][f]hstack[v]")
+                                    .desired_rows(2)
+                                    .code_editor())
+                                    .on_hover_text("Passed as -filter_complex. It replaces the scaling, rotation, subtitle burn-in and region filters the GUI would add.")
+                                    .changed()
+                                {
+                                    self.update_command();
+                                }
+                            });
+                            ui.add_enabled_ui(self.custom_filter_graph().is_some(), |ui| {
+                                ui.horizontal(|ui| {
+                                    ui.label("Map:");
+                                    if ui.add(egui::TextEdit::singleline(&mut self.filter_maps).hint_text("[v] 0:a?"))
+                                        .on_hover_text("Streams for the output, each passed as -map: labelled graph outputs like [v] and input streams like 0:a?. Leave empty to let FFmpeg take the graph's unlabelled output plus the best audio and subtitles.")
+                                        .changed()
+                                    {
                                         self.update_command();
                                     }
+                                });
+                            });
+                            if let Err(e) = ffmpeg_utils::split_args(&self.filter_maps) {
+                                ui.colored_label(egui::Color32::YELLOW, format!("Maps ignored: {}", e));
+                            }
+                        }
+                        
+                        if self.selected_function == FunctionType::ConvertToMp4
+                            && ui.checkbox(&mut self.copy_timestamps, "Copy timestamps")
+                                .on_hover_text("Pass -copyts -avoid_negative_ts make_zero. Try this when a remuxed file drifts out of sync, which happens with some sources (e.g. recordings or streams that don't start at zero).")
+                                .changed()
+                        {
+                            self.update_command();
+                        }
+                        
+                        if matches!(self.selected_function, FunctionType::ConvertToMp4 | FunctionType::CompressVideo)
+                            && !(self.selected_function == FunctionType::ConvertToMp4 && self.copy_timestamps)
+                            && ui.checkbox(&mut self.reset_timestamps, "Reset timestamps")
+                                .on_hover_text("Pass -avoid_negative_ts make_zero so the output starts at zero. Fixes DVR and stream captures that begin at a large timestamp and play with an odd initial seek or duration.")
+                                .changed()
+                        {
+                            self.update_command();
+                        }
+                        
+                        // Stream the output into another program instead of a file
+                        ui.add_enabled_ui(self.selected_function != FunctionType::Spectrogram, |ui| {
+                            ui.horizontal(|ui| {
+                                if ui.checkbox(&mut self.pipe_output, "Pipe output to stdout")
+                                    .on_hover_text("Write to pipe:1 instead of the output file. The container is forced from the output format; MP4 is written fragmented so it can stream.")
+                                    .changed()
+                                {
+                                    self.update_command();
                                 }
-                                
-                                // Add original fps button if not already in common values
-                                if !common_fps.contains(&self.original_fps) && ui.small_button("Original").clicked() {
-                                    self.frame_rate = self.original_fps;
+                                if self.pipe_output
+                                    && ui.add(egui::TextEdit::singleline(&mut self.pipe_command)
+                                        .hint_text("downstream command, e.g. mpv -"))
+                                        .on_hover_text("Shell command that reads the output on its stdin. Leave empty to only count the bytes.")
+                                        .changed()
+                                {
                                     self.update_command();
                                 }
                             });
                         });
+                    });
+                }
+
+                // Command preview
+                egui::CollapsingHeader::new("FFmpeg Command")
+                    .open(self.select_command.then_some(true))
+                    .show(ui, |ui| {
+                        ui.checkbox(&mut self.settings.wrap_command, "Wrap command")
+                            .on_hover_text("Show each option and its value on its own line. Copy Command still copies the single-line form.");
+                        if self.clipboard_failed {
+                            // Without a clipboard the command has to be selectable to copy it by hand
+                            let mut command = self.last_command.as_str();
+                            let output = egui::TextEdit::multiline(&mut command)
+                                .font(egui::TextStyle::Monospace)
+                                .desired_width(f32::INFINITY)
+                                .show(ui);
+                            if std::mem::take(&mut self.select_command) {
+                                let mut state = output.state;
+                                let end = egui::text::CCursor::new(self.last_command.chars().count());
+                                state.cursor.set_char_range(Some(egui::text::CCursorRange::two(egui::text::CCursor::new(0), end)));
+                                state.store(ui.ctx(), output.response.id);
+                                output.response.request_focus();
+                            }
+                        } else if self.settings.wrap_command {
+                            ui.monospace(&self.last_command_wrapped);
+                        } else {
+                            ScrollArea::horizontal().id_source("command_line").show(ui, |ui| {
+                                ui.add(egui::Label::new(egui::RichText::new(&self.last_command).monospace()).wrap(false));
+                            });
+                        }
+                        ui.horizontal(|ui| {
+                            ui.add(egui::TextEdit::singleline(&mut self.command_import)
+                                .hint_text("Paste an ffmpeg command to load its settings")
+                                .font(egui::TextStyle::Monospace));
+                            if ui.add_enabled(!self.command_import.trim().is_empty(), egui::Button::new("Load"))
+                                .on_hover_text("Set the options from the pasted command where the GUI has a control for them. Unknown output options are kept as extra options.")
+                                .clicked()
+                            {
+                                match ffmpeg_utils::parse_command(&self.command_import) {
+                                    Ok(parsed) => {
+                                        let loaded = self.load_command(parsed);
+                                        self.output_log.write().push_str(&format!("Loaded from command: {}\n", loaded.join(", ")));
+                                        self.command_import.clear();
+                                    },
+                                    Err(e) => self.output_log.write().push_str(&format!("Error: Could not read the command: {}\n", e)),
+                                }
+                            }
+                        });
+                    });
+            });
+        });
+
+        // A new encode makes the frames compared so far stale, including any still being extracted
+        let last_encode = self.last_encode.read().clone();
+        if last_encode != self.compared_encode && !*self.compare_busy.read() {
+            self.compared_encode = last_encode;
+            self.compare_textures = None;
+            *self.compare_frames.write() = None;
+            if self.show_compare && self.compared_encode.is_some() {
+                self.compare_time = self.compare_length() / 2.0;
+                self.extract_compare_frames(ctx.clone());
+            }
+        }
+        
+        // Turn frames from the comparison thread into textures
+        if let Some((before, after)) = self.compare_frames.write().take() {
+            let [width, height] = ffmpeg_utils::PREVIEW_SIZE;
+            let size = [width as usize, height as usize];
+            let load = |name: &str, rgba: Vec<u8>| {
+                ctx.load_texture(name, egui::ColorImage::from_rgba_unmultiplied(size, &rgba), egui::TextureOptions::LINEAR)
+            };
+            self.compare_textures = Some((load("compare_before", before), load("compare_after", after)));
+        }
+        
+        let mut show_compare = self.show_compare;
+        egui::Window::new("Compare Before/After")
+            .open(&mut show_compare)
+            .resizable(false)
+            .show(ctx, |ui| self.compare_ui(ui));
+        self.show_compare = show_compare;
+        
+        let mut show_preset_diff = self.show_preset_diff;
+        egui::Window::new("Compare Presets")
+            .open(&mut show_preset_diff)
+            .show(ctx, |ui| self.preset_diff_ui(ui));
+        self.show_preset_diff = show_preset_diff;
+        
+        let mut show_benchmark = self.show_benchmark;
+        egui::Window::new("Benchmark Encoders")
+            .open(&mut show_benchmark)
+            .resizable(false)
+            .show(ctx, |ui| self.benchmark_ui(ui));
+        self.show_benchmark = show_benchmark;
+        
+        let mut show_ab = self.show_ab;
+        egui::Window::new("CRF A/B Comparison")
+            .open(&mut show_ab)
+            .resizable(false)
+            .show(ctx, |ui| self.ab_ui(ui));
+        self.show_ab = show_ab;
+        
+        let mut show_settings = self.show_settings;
+        egui::Window::new("Settings")
+            .open(&mut show_settings)
+            .resizable(false)
+            .show(ctx, |ui| self.settings_ui(ui));
+        self.show_settings = show_settings;
+        
+        self.sync_auto_settings();
+        self.sync_size_limit();
+        self.persist_settings();
+        
+        // Ctrl+Z in a text field undoes typing there, not a setting
+        let editing = ctx.wants_keyboard_input();
+        if !editing && ctx.input_mut(|i| i.consume_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z))) {
+            self.undo();
+        }
+        self.record_undo_snapshot(editing || ctx.input(|i| i.pointer.any_down()));
+
+        // Request repaint to keep the UI responsive
+        ctx.request_repaint();
+    }
+}
+
+/// Label for a control, marked when its value differs from the active preset
+fn field_label(ui: &mut egui::Ui, text: &str, changed: bool) {
+    ui.label(text);
+    change_marker(ui, changed);
+}
+
+/// One row per part of the output that has any bytes, with its share of the total
+fn size_breakdown_ui(ui: &mut egui::Ui, sizes: &SizeBreakdown) {
+    let total = sizes.total().max(1) as f32;
+    let parts = [
+        ("Video", sizes.video),
+        ("Audio", sizes.audio),
+        ("Subtitles", sizes.subtitle),
+        ("Other streams", sizes.other),
+        ("Container overhead", sizes.overhead),
+    ];
+    egui::Grid::new("size_breakdown").num_columns(3).show(ui, |ui| {
+        for (label, bytes) in parts.into_iter().filter(|&(_, bytes)| bytes > 0) {
+            let share = bytes as f32 / total;
+            ui.label(label);
+            ui.label(ffmpeg_utils::format_size(bytes));
+            ui.add(egui::ProgressBar::new(share).desired_width(150.0).text(format!("{:.1}%", share * 100.0)));
+            ui.end_row();
+        }
+    });
+}
+
+/// Grey out a row whose value Auto picked; it stays editable so the user can take over
+fn auto_tint(ui: &mut egui::Ui, auto: bool) {
+    if auto {
+        ui.visuals_mut().override_text_color = Some(ui.visuals().weak_text_color());
+    }
+}
+
+fn change_marker(ui: &mut egui::Ui, changed: bool) {
+    if changed {
+        ui.colored_label(egui::Color32::LIGHT_BLUE, "•").on_hover_text("Changed from the preset");
+    }
+}
+
+impl MyApp {
+    fn preset_ui(&mut self, ui: &mut egui::Ui, changes: &[&str]) {
+        ui.horizontal(|ui| {
+            ui.label("Preset:");
+            let selected = match &self.active_preset {
+                Some(preset) if !changes.is_empty() => format!("{} (modified)", preset.name),
+                Some(preset) => preset.name.clone(),
+                None => "None".to_string(),
+            };
+            let mut load = None;
+            egui::ComboBox::from_id_source("preset")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    for preset in &self.settings.presets {
+                        let active = self.active_preset.as_ref().is_some_and(|p| p.name == preset.name);
+                        if ui.selectable_label(active, &preset.name).clicked() {
+                            load = Some(preset.clone());
+                        }
                     }
-                    
-                    // Preset selection
+                });
+            if let Some(preset) = load {
+                self.apply_encode_settings(&preset.settings);
+                self.active_preset = Some(preset);
+            }
+            
+            if let Some(preset) = self.active_preset.clone() {
+                if ui.add_enabled(!changes.is_empty(), egui::Button::new("Reset"))
+                    .on_hover_text("Go back to the preset's values")
+                    .clicked()
+                {
+                    self.apply_encode_settings(&preset.settings);
+                }
+                if ui.add_enabled(!changes.is_empty(), egui::Button::new("Save"))
+                    .on_hover_text("Overwrite the preset with the current values")
+                    .clicked()
+                {
+                    self.save_preset(preset.name.clone());
+                }
+                let favorite = self.settings.presets.iter().any(|p| p.name == preset.name && p.favorite);
+                if ui.selectable_label(favorite, if favorite { "★ Favorite" } else { "☆ Favorite" })
+                    .on_hover_text("Show this preset as a one-click button at the top of the window")
+                    .clicked()
+                    && let Some(stored) = self.settings.presets.iter_mut().find(|p| p.name == preset.name)
+                {
+                    stored.favorite = !favorite;
+                }
+                if ui.button("Delete").clicked() {
+                    self.settings.presets.retain(|p| p.name != preset.name);
+                    self.active_preset = None;
+                }
+            }
+            
+            if ui.add_enabled(!self.settings.presets.is_empty(), egui::Button::new("Compare..."))
+                .on_hover_text("Show how two presets, or a preset and the current settings, differ")
+                .clicked()
+            {
+                self.show_preset_diff = true;
+                self.preset_diff = [self.active_preset.as_ref().map(|preset| preset.name.clone()), None];
+            }
+            
+            ui.separator();
+            ui.add(egui::TextEdit::singleline(&mut self.new_preset_name).hint_text("New preset name").desired_width(120.0));
+            let name = self.new_preset_name.trim().to_string();
+            if ui.add_enabled(!name.is_empty(), egui::Button::new("Save as")).clicked() {
+                self.save_preset(name);
+                self.new_preset_name.clear();
+            }
+        });
+    }
+    
+    /// Two presets (or one and the current settings) side by side, field by field
+    fn preset_diff_ui(&mut self, ui: &mut egui::Ui) {
+        let label = |name: &Option<String>| name.clone().unwrap_or_else(|| "Current settings".to_string());
+        ui.horizontal(|ui| {
+            for (side, id) in [(0, "preset_diff_a"), (1, "preset_diff_b")] {
+                egui::ComboBox::from_id_source(id)
+                    .selected_text(label(&self.preset_diff[side]))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.preset_diff[side], None, label(&None));
+                        for preset in &self.settings.presets {
+                            ui.selectable_value(&mut self.preset_diff[side], Some(preset.name.clone()), &preset.name);
+                        }
+                    });
+                if side == 0 {
+                    ui.label("vs");
+                }
+            }
+            ui.checkbox(&mut self.preset_diff_all, "Show all fields");
+        });
+        
+        // A preset deleted since it was picked compares as the current settings
+        let settings = |name: &Option<String>| {
+            name.as_ref()
+                .and_then(|name| self.settings.presets.iter().find(|preset| &preset.name == name))
+                .map_or_else(|| self.encode_settings(), |preset| preset.settings.clone())
+        };
+        let fields = presets::field_values(&settings(&self.preset_diff[0]), &settings(&self.preset_diff[1]));
+        let differences = fields.iter().filter(|(_, a, b)| a != b).count();
+        ui.label(match differences {
+            0 => "The settings are identical.".to_string(),
+            1 => "1 field differs.".to_string(),
+            n => format!("{} fields differ.", n),
+        });
+        
+        ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+            egui::Grid::new("preset_diff").striped(true).show(ui, |ui| {
+                ui.strong("Field");
+                ui.strong(label(&self.preset_diff[0]));
+                ui.strong(label(&self.preset_diff[1]));
+                ui.end_row();
+                for (name, a, b) in &fields {
+                    let differs = a != b;
+                    if !differs && !self.preset_diff_all {
+                        continue;
+                    }
+                    let color = if differs { egui::Color32::YELLOW } else { ui.visuals().weak_text_color() };
+                    ui.colored_label(color, name);
+                    ui.colored_label(color, a);
+                    ui.colored_label(color, b);
+                    ui.end_row();
+                }
+            });
+        });
+    }
+    
+    /// One button per favorite preset, loading all of its settings at once
+    fn favorites_ui(&mut self, ui: &mut egui::Ui) {
+        let running = *self.running.read();
+        let mut load = None;
+        for preset in self.settings.presets.iter().filter(|p| p.favorite) {
+            let active = self.active_preset.as_ref().is_some_and(|p| p.name == preset.name);
+            if ui.add_enabled(!running, egui::SelectableLabel::new(active, &preset.name))
+                .on_hover_text(format!("{:?} with the preset's settings", preset.settings.function))
+                .clicked()
+            {
+                load = Some(preset.clone());
+            }
+        }
+        if let Some(preset) = load {
+            self.apply_encode_settings(&preset.settings);
+            self.active_preset = Some(preset);
+        }
+    }
+    
+    /// Store the current settings under `name`, replacing a preset of the same name, and make it active
+    fn save_preset(&mut self, name: String) {
+        let mut preset = Preset { name, settings: self.encode_settings(), favorite: false };
+        match self.settings.presets.iter_mut().find(|p| p.name == preset.name) {
+            Some(existing) => {
+                preset.favorite = existing.favorite;
+                *existing = preset.clone();
+            },
+            None => self.settings.presets.push(preset.clone()),
+        }
+        self.active_preset = Some(preset);
+    }
+    
+    /// Jobs that each carry their own settings, for producing several variants of one source
+    fn settings_jobs_ui(&mut self, ui: &mut egui::Ui, ctx: &egui::Context, advanced: bool) {
+        if !advanced && self.settings_jobs.is_empty() {
+            return;
+        }
+        egui::CollapsingHeader::new(format!("Job List ({})", self.settings_jobs.len()))
+            .id_source("settings_jobs")
+            .show(ui, |ui| {
+                let running = self.busy();
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(!running && !self.input_path.is_empty(), egui::Button::new("Add with current settings"))
+                        .on_hover_text("Queue the input and output with a copy of the current settings; change them and add again for another variant")
+                        .clicked()
+                    {
+                        self.add_settings_job();
+                    }
+                    if ui.add_enabled(!running && !self.settings_jobs.is_empty(), egui::Button::new("Run job list")).clicked() {
+                        self.run_settings_jobs(ctx.clone());
+                    }
+                    if ui.add_enabled(!running && !self.settings_jobs.is_empty(), egui::Button::new("Clear")).clicked() {
+                        self.settings_jobs.clear();
+                    }
+                });
+                
+                if self.settings_jobs.is_empty() {
+                    ui.label("Each job is encoded with the settings it was added with, e.g. a 1080p MP4, a 720p WebM and an MP3 from one source.");
+                    return;
+                }
+                let mut remove = None;
+                let mut load = None;
+                let job = self.batch_job.read();
+                for (index, queued) in self.settings_jobs.iter().enumerate() {
+                    let status = job.as_ref()
+                        .and_then(|job| job.items.iter().find(|item| item.output == queued.output))
+                        .map(|item| item.status.label());
                     ui.horizontal(|ui| {
-                        ui.label("Encoding Preset:");
-                        egui::ComboBox::from_id_source("encoding_preset")
-                            .selected_text(&self.encoding_preset)
-                            .show_ui(ui, |ui| {
-                                for preset in &["ultrafast", "superfast", "veryfast", "faster", "fast", "medium", "slow", "slower", "veryslow"] {
-                                    ui.selectable_value(&mut self.encoding_preset, preset.to_string(), *preset);
-                                }
-                            });
+                        if ui.add_enabled(!running, egui::Button::new("✖").small()).on_hover_text("Remove from the job list").clicked() {
+                            remove = Some(index);
+                        }
+                        if ui.add_enabled(!running, egui::Button::new("⟲").small()).on_hover_text("Load this job's settings").clicked() {
+                            load = Some(index);
+                        }
+                        ui.label(format!(
+                            "{} → {}",
+                            queued.input.file_name().unwrap_or_default().to_string_lossy(),
+                            queued.output.file_name().unwrap_or_default().to_string_lossy()
+                        )).on_hover_text(format!("{}\n{}", queued.input.display(), queued.output.display()));
+                        ui.weak(format!("{:?}", queued.settings.function));
+                        if let Some(status) = status {
+                            ui.weak(status);
+                        }
                     });
+                }
+                drop(job);
+                
+                if let Some(index) = load {
+                    let settings = self.settings_jobs[index].settings.clone();
+                    self.apply_encode_settings(&settings);
+                }
+                if let Some(index) = remove {
+                    self.settings_jobs.remove(index);
+                }
+            });
+    }
+    
+    fn silence_ui(&mut self, ui: &mut egui::Ui, changes: &[&str]) {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            changed |= ui.checkbox(&mut self.silence_trim, "Trim silence")
+                .on_hover_text("Cut silence from the start and end of the audio (silenceremove)")
+                .changed();
+            change_marker(ui, changes.contains(&"silence"));
+            ui.add_enabled_ui(self.silence_trim, |ui| {
+                ui.label("Below:");
+                changed |= ui.add(egui::DragValue::new(&mut self.silence_threshold_db)
+                    .speed(0.5)
+                    .clamp_range(-90.0..=-10.0)
+                    .suffix(" dB"))
+                    .on_hover_text("Anything quieter counts as silence. Raise it for noisy recordings.")
+                    .changed();
+            });
+        });
+        if self.silence_trim {
+            ui.indent("silence", |ui| {
+                ui.horizontal(|ui| {
+                    changed |= ui.checkbox(&mut self.silence_internal, "Also remove pauses longer than")
+                        .on_hover_text("Cut silent gaps within the audio too, e.g. for a podcast or voice memo. This changes the timing, which is why it isn't offered when the output keeps video.")
+                        .changed();
+                    changed |= ui.add_enabled(self.silence_internal, egui::DragValue::new(&mut self.silence_min_secs)
+                        .speed(0.1)
+                        .clamp_range(0.1..=30.0)
+                        .suffix(" s"))
+                        .changed();
+                });
+            });
+        }
+        if changed {
+            self.update_command();
+        }
+    }
+    
+    fn gif_ui(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label("Frame rate:");
+            changed |= ui.add(egui::DragValue::new(&mut self.gif_fps)
+                .speed(0.5)
+                .clamp_range(1.0..=50.0)
+                .suffix(" fps"))
+                .on_hover_text("GIFs get large quickly; 10-15 fps is usually enough")
+                .changed();
+            ui.label("Width:");
+            changed |= ui.add(egui::DragValue::new(&mut self.gif_width)
+                .speed(10)
+                .clamp_range(0..=3840)
+                .custom_formatter(|value, _| if value == 0.0 { "Source".to_string() } else { format!("{} px", value) }))
+                .on_hover_text("The height follows the aspect ratio; 0 keeps the source width")
+                .changed();
+        });
+        changed |= ui.checkbox(&mut self.gif_palette, "High quality (two-pass palette)")
+            .on_hover_text("First work out the best 256 colours for this clip, then map every frame onto them. Without it, frames are squeezed into a fixed palette and come out banded.")
+            .changed();
+        if self.gif_palette {
+            ui.indent("gif_palette", |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Colours:");
+                    changed |= ui.add(egui::DragValue::new(&mut self.gif_max_colors).clamp_range(2..=256))
+                        .on_hover_text("Fewer colours make smaller files")
+                        .changed();
+                    ui.label("Dithering:");
+                    egui::ComboBox::from_id_source("gif_dither")
+                        .selected_text(self.gif_dither.display_name())
+                        .show_ui(ui, |ui| {
+                            for dither in GifDither::all() {
+                                changed |= ui.selectable_value(&mut self.gif_dither, dither, dither.display_name()).clicked();
+                            }
+                        });
                 });
+            });
+        }
+        ui.weak("Use Trim to pick the part of the video to turn into a GIF.");
+        if changed {
+            self.update_command();
+        }
+    }
+    
+    /// The input's frame count: counted exactly on request, otherwise the container's figure
+    fn frame_count_ui(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        let Some(info) = self.probed_input_info().filter(|info| info.has_video()) else {
+            return;
+        };
+        let container_count = info.frame_count();
+        ui.horizontal(|ui| {
+            if let Some(job) = &self.frame_count_job {
+                ui.spinner();
+                let progress = *job.progress.read();
+                ui.add(egui::ProgressBar::new(progress).desired_width(150.0).text(format!("Counting frames... {:.0}%", progress * 100.0)));
+                if ui.button("Cancel").clicked() {
+                    self.cancel_frame_count();
+                }
+                return;
             }
-
-            if self.selected_function.show_output_format() {
+            match (self.counted_frames, container_count) {
+                (Some(frames), _) => ui.label(format!("{} frames", frames)).on_hover_text("Counted by decoding the whole video"),
+                (None, Some(frames)) => ui.label(format!("{} frames", frames)).on_hover_text("As the container reports it, which can be slightly off"),
+                (None, None) => ui.weak("Frame count unknown"),
+            };
+            if self.counted_frames.is_none()
+                && ui.button("Count exactly")
+                    .on_hover_text("Decode the whole video to count its frames. This can take minutes on a long file and can be cancelled.")
+                    .clicked()
+            {
+                self.start_frame_count(ctx);
+            }
+        });
+    }
+    
+    /// Detect black bars and pick a crop from what cropdetect suggested over the whole file
+    fn crop_ui(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        let mut picked = None;
+        ui.horizontal(|ui| {
+            ui.label("Crop:");
+            match self.crop {
+                Some(crop) => {
+                    ui.label(crop.label());
+                    if ui.button("Don't crop").clicked() {
+                        picked = Some(None);
+                    }
+                },
+                None => {
+                    ui.weak("None");
+                },
+            }
+            if let Some(job) = &self.crop_job {
+                ui.spinner();
+                let progress = *job.progress.read();
+                ui.add(egui::ProgressBar::new(progress).desired_width(150.0).text(format!("Detecting... {:.0}%", progress * 100.0)));
+                if ui.button("Cancel").clicked() {
+                    self.cancel_crop_detect();
+                }
+            } else if ui.button(if self.crop_tally.counts.is_empty() { "Detect black bars" } else { "Detect again" })
+                .on_hover_text("Run cropdetect over the input's keyframes and list the crops it suggests, with how often each came up")
+                .clicked()
+            {
+                self.start_crop_detect(ctx);
+            }
+        });
+        
+        // Bars can change between scenes, and a dark scene can look like all bars, so every
+        // suggestion is listed rather than trusting any single one
+        if !self.crop_tally.counts.is_empty() {
+            ui.indent("crop_tally", |ui| {
+                let total = self.crop_tally.total().max(1);
+                for (crop, count) in self.crop_tally.counts.iter().take(8) {
+                    ui.horizontal(|ui| {
+                        if ui.selectable_label(self.crop == Some(*crop), crop.label()).clicked() {
+                            picked = Some(Some(*crop));
+                        }
+                        ui.weak(format!("{:.0}% of {} keyframes", *count as f32 * 100.0 / total as f32, total));
+                    });
+                }
+                if self.crop_tally.counts.len() > 8 {
+                    ui.weak(format!("and {} less common", self.crop_tally.counts.len() - 8));
+                }
                 ui.horizontal(|ui| {
-                    ui.label("Output Format:");
-                    for format in OutputFormat::all() {
-                        if ui.radio_value(&mut self.output_format, format, format.display_name()).clicked() {
-                            self.output_path = self.default_output();
+                    if ui.button("Use most common")
+                        .on_hover_text("The crop suggested most often")
+                        .clicked()
+                    {
+                        picked = Some(self.crop_tally.most_common());
+                    }
+                    if ui.button("Use union")
+                        .on_hover_text("The smallest crop that keeps everything any suggestion keeps, so no scene loses picture")
+                        .clicked()
+                    {
+                        picked = Some(self.crop_tally.union());
+                    }
+                });
+            });
+        }
+        
+        if let Some(crop) = picked {
+            self.crop = crop;
+            self.update_command();
+        }
+    }
+    
+    /// Load a cue sheet or timestamp list to split a long recording into one file per track
+    fn split_tracks_ui(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        ui.horizontal(|ui| {
+            if ui.add_enabled(self.file_dialog.is_none(), egui::Button::new("Load track list..."))
+                .on_hover_text("A cue sheet, or a text file with one \"12:34 Title\" line per track. Each track is written to its own file, named after its title.")
+                .clicked()
+            {
+                self.browse(DialogTarget::SplitPoints, ctx, || {
+                    rfd::FileDialog::new().add_filter("Track lists", &["cue", "txt"]).pick_file().map(|path| vec![path])
+                });
+            }
+            if self.split_points.is_empty() {
+                ui.weak("Split into tracks from a cue sheet or timestamp list");
+            } else {
+                let file_name = Path::new(&self.split_points_file).file_name().unwrap_or_default().to_string_lossy();
+                ui.label(format!("Splitting into {} tracks from {}", self.split_points.len(), file_name));
+                if ui.button("Clear").clicked() {
+                    self.split_points.clear();
+                    self.split_points_file.clear();
+                    self.update_command();
+                }
+            }
+        });
+        if !self.split_points.is_empty() {
+            if self.duration.is_none() {
+                ui.colored_label(egui::Color32::YELLOW, "The input's length is unknown, so it can't be split.");
+            } else if self.selected_function == FunctionType::ConvertToMp4 {
+                ui.weak("Stream copies can only cut on keyframes, so a track may start slightly before its time.");
+            }
+        }
+    }
+    
+    /// Streams to leave out of a remux; everything else is copied as-is
+    fn remux_streams_ui(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label("Leave out:");
+            changed |= ui.checkbox(&mut self.remux_drop_audio, "Audio")
+                .on_hover_text("Copy the file without any audio streams (-an)")
+                .changed();
+            changed |= ui.checkbox(&mut self.remux_drop_subtitles, "Subtitles")
+                .on_hover_text("Copy the file without its subtitle streams. A subtitle file added below is still muxed in.")
+                .changed();
+        });
+        
+        // Single streams can only be picked once the input is probed
+        let streams = self.media_info.as_ref()
+            .filter(|_| self.probed_path == self.input_path)
+            .map(|info| info.streams.clone())
+            .unwrap_or_default();
+        if streams.len() > 1 {
+            ui.collapsing(format!("Streams ({})", streams.len()), |ui| {
+                for stream in &streams {
+                    let dropped_by_type = (self.remux_drop_audio && stream.codec_type == "audio")
+                        || (self.remux_drop_subtitles && stream.codec_type == "subtitle");
+                    let mut keep = !dropped_by_type && !self.remux_excluded.contains(&stream.index);
+                    if ui.add_enabled(!dropped_by_type, egui::Checkbox::new(&mut keep, stream.label())).changed() {
+                        if keep {
+                            self.remux_excluded.retain(|&index| index != stream.index);
+                        } else {
+                            self.remux_excluded.push(stream.index);
+                        }
+                        changed = true;
+                    }
+                }
+            });
+        }
+        if changed {
+            self.update_command();
+        }
+    }
+    
+    fn subtitles_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Subtitles:");
+            let can_burn = self.selected_function == FunctionType::CompressVideo;
+            egui::ComboBox::from_id_source("subtitle_mode")
+                .selected_text(self.subtitle_mode.display_name())
+                .show_ui(ui, |ui| {
+                    for mode in SubtitleMode::all() {
+                        let enabled = can_burn || mode != SubtitleMode::Burn;
+                        if ui.add_enabled(enabled, egui::SelectableLabel::new(self.subtitle_mode == mode, mode.display_name()))
+                            .on_disabled_hover_text("Burning in needs a re-encode; use Compress Video")
+                            .clicked()
+                        {
+                            self.subtitle_mode = mode;
                             self.update_command();
                         }
                     }
                 });
-            }
-
-            // Progress bar with proper scaling
-            let progress = *self.progress.read();
-            let running = *self.running.read();
-            
-            // Only animate the progress bar when a process is running
-            ui.add(
-                egui::ProgressBar::new(progress)
-                    .show_percentage()
-                    .animate(running) // Only animate when actually running
-            );
-            
-            // Only show percentage text when running
-            if running || progress > 0.0 {
-                ui.label(format!("Progress: {:.1}%", progress * 100.0));
-            }
-
-            // Command preview
-            ui.collapsing("FFmpeg Command", |ui| {
-                ui.monospace(&self.last_command);
-            });
-
-            // Log output with better spacing
-            ui.vertical_centered_justified(|ui| {
-                ui.add_space(10.0);
-                ui.heading("Output Log");
-                
-                // Get the log output as a string
-                let output = self.output_log.read().clone();
-                
-                ScrollArea::vertical()
-                    .auto_shrink([false; 2])
-                    .stick_to_bottom(self.auto_scroll)
-                    .show(ui, |ui| {
-                        // Create the text edit widget inside the scroll area
-                        ui.add_sized(
-                            ui.available_size(),
-                            egui::TextEdit::multiline(&mut output.as_str())
-                                .font(egui::TextStyle::Monospace)
-                                .desired_width(ui.available_width())
-                                .frame(true),  // Enable the frame
-                        );
+            if self.subtitle_mode != SubtitleMode::Off {
+                if ui.text_edit_singleline(&mut self.subtitle_path).changed() {
+                    self.update_command();
+                }
+                if ui.add_enabled(self.file_dialog.is_none(), egui::Button::new("Browse")).clicked() {
+                    let ctx = ui.ctx().clone();
+                    self.browse(DialogTarget::Subtitle, &ctx, || {
+                        rfd::FileDialog::new().add_filter("Subtitles", &["srt", "ass", "ssa", "vtt"]).pick_file().map(|path| vec![path])
                     });
+                }
+            }
+        });
+        
+        if self.subtitle_mode == SubtitleMode::Mux {
+            ui.horizontal(|ui| {
+                let mut changed = false;
+                ui.label("Language:");
+                let hint = self.muxed_subtitle_language().filter(|_| self.subtitle_language.trim().is_empty()).unwrap_or_else(|| "e.g. eng".to_string());
+                changed |= ui.add(egui::TextEdit::singleline(&mut self.subtitle_language).hint_text(hint).desired_width(60.0))
+                    .on_hover_text("ISO 639-2 code for the track. Left empty, a code in the file name (movie.en.srt) is used.")
+                    .changed();
+                changed |= ui.checkbox(&mut self.subtitle_default, "Default")
+                    .on_hover_text("Flag the track as default so players show it without being asked. The source's subtitles lose their default flag.")
+                    .changed();
+                changed |= ui.checkbox(&mut self.subtitle_forced, "Forced")
+                    .on_hover_text("Flag the track as forced, for subtitles that only translate foreign-language parts; most players always show forced tracks")
+                    .changed();
+                if changed {
+                    self.update_command();
+                }
             });
-
-            // Bottom panel for log controls
-            ui.vertical_centered_justified(|ui| {
-                ui.add_space(10.0);
-                ui.separator();
-                ui.add_space(5.0);
-                
-                ui.horizontal(|ui| {
-                    if ui.button("📋 Copy Command").clicked() {
-                        if let Err(e) = arboard::Clipboard::new().and_then(|mut c| c.set_text(self.last_command.clone())) {
-                            eprintln!("Failed to copy to clipboard: {}", e);
+        }
+        
+        if self.subtitle_mode == SubtitleMode::Burn {
+            ui.horizontal(|ui| {
+                ui.label("Encoding:");
+                egui::ComboBox::from_id_source("subtitle_charset")
+                    .selected_text(subtitles::charset_label(&self.subtitle_charset))
+                    .show_ui(ui, |ui| {
+                        for (name, label) in subtitles::CHARSETS {
+                            if ui.selectable_label(self.subtitle_charset == name, label).clicked() {
+                                self.subtitle_charset = name.to_string();
+                                self.update_command();
+                            }
                         }
+                    })
+                    .response
+                    .on_hover_text("Pick the file's encoding if burned-in text comes out garbled (e.g. Windows-1251 for older Cyrillic subtitles)");
+            });
+        }
+        
+        // Quick picks for subtitle files sitting next to the input
+        if !self.sidecar_subtitles.is_empty() {
+            ui.horizontal_wrapped(|ui| {
+                ui.weak("Found next to the input:");
+                let mut picked = None;
+                for sidecar in &self.sidecar_subtitles {
+                    let selected = Path::new(self.subtitle_path.trim()) == sidecar.path;
+                    if ui.selectable_label(selected, sidecar.label()).clicked() {
+                        picked = Some(sidecar.path.display().to_string());
                     }
-                    
-                    if ui.button("🗑️ Clear Log").clicked() {
-                        *self.output_log.write() = String::new();
+                }
+                if let Some(path) = picked {
+                    self.subtitle_path = path;
+                    if self.subtitle_mode == SubtitleMode::Off {
+                        self.subtitle_mode = SubtitleMode::Mux;
                     }
-                    
-                    ui.checkbox(&mut self.auto_scroll, "Auto-scroll");
-                });
+                    self.update_command();
+                }
             });
+        }
+    }
+    
+    /// Zoom the window to the chosen scale. egui's own Ctrl +/- zoom changes the setting
+    /// instead of being undone on the next frame.
+    fn apply_ui_scale(&mut self, ctx: &egui::Context) {
+        let zoom = ctx.zoom_factor();
+        if zoom != self.applied_ui_scale {
+            self.settings.ui_scale = zoom;
+        }
+        ctx.set_zoom_factor(self.settings.ui_scale);
+        self.applied_ui_scale = self.settings.ui_scale;
+    }
+    
+    fn settings_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Interface scale:");
+            ui.add(egui::Slider::new(&mut self.settings.ui_scale, 0.5..=3.0)
+                .step_by(0.05)
+                .custom_formatter(|n, _| format!("{:.0}%", n * 100.0)))
+                .on_hover_text("Size of everything in the window, on top of the display's own scaling. Ctrl + and Ctrl - change it too.");
         });
-
-        // Request repaint to keep the UI responsive
-        ctx.request_repaint();
+        ui.horizontal(|ui| {
+            ui.label("Log text size:");
+            ui.add(egui::DragValue::new(&mut self.settings.log_font_size)
+                .speed(0.5)
+                .clamp_range(8.0..=32.0)
+                .suffix(" pt"))
+                .on_hover_text("Font size of the output log, where FFmpeg's errors are read");
+        });
+        
+        ui.horizontal(|ui| {
+            ui.label("Duplicate file naming:");
+            egui::ComboBox::from_id_source("naming_scheme")
+                .selected_text(self.settings.naming_scheme.display_name())
+                .show_ui(ui, |ui| {
+                    for scheme in NamingScheme::all() {
+                        ui.selectable_value(&mut self.settings.naming_scheme, scheme, scheme.display_name());
+                    }
+                })
+                .response
+                .on_hover_text("Suffix added when the output file already exists");
+        });
+        
+        ui.checkbox(&mut self.settings.smooth_progress, "Smooth progress bar")
+            .on_hover_text("Average recent progress updates so the bar moves steadily");
+        
+        ui.checkbox(&mut self.settings.count_frames, "Precise frame count (slow)")
+            .on_hover_text("Count each new input's frames by decoding all of it, in the background. Otherwise the count comes from the container, when it has one.");
+        
+        ui.checkbox(&mut self.settings.background_priority, "Background priority")
+            .on_hover_text("Run FFmpeg at a lower CPU priority so the computer stays responsive while encoding");
+        
+        ui.horizontal(|ui| {
+            ui.label("Stall warning after:");
+            ui.add(egui::DragValue::new(&mut self.settings.stall_timeout_secs)
+                .clamp_range(0..=3600)
+                .custom_formatter(|n, _| if n == 0.0 { "Off".to_string() } else { format!("{} s", n) }))
+                .on_hover_text("Log a warning when FFmpeg reports no progress for this long, e.g. hung on a damaged input");
+            ui.add_enabled(self.settings.stall_timeout_secs > 0, egui::Checkbox::new(&mut self.settings.kill_stalled, "Stop stalled encodes"))
+                .on_hover_text("Also stop the process, so an unattended batch moves on to the next file");
+        });
+        
+        ui.checkbox(&mut self.settings.keep_partial, "Keep partial output on Stop")
+            .on_hover_text("Let FFmpeg close the file written so far, so an aborted recording still plays up to the stop. Untick to delete the partial file instead.");
+        
+        ui.checkbox(&mut self.settings.global_hotkeys, "Global hotkeys")
+            .on_hover_text("Start and stop encoding with these shortcuts even when the window isn't focused");
+        ui.add_enabled_ui(self.settings.global_hotkeys, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Start:");
+                ui.add(egui::TextEdit::singleline(&mut self.settings.start_hotkey).desired_width(100.0));
+                ui.label("Stop:");
+                ui.add(egui::TextEdit::singleline(&mut self.settings.stop_hotkey).desired_width(100.0))
+                    .on_hover_text("Modifiers and a key joined by +, e.g. Ctrl+Alt+S or Shift+F9");
+            });
+        });
+        
+        if ui.checkbox(&mut self.settings.exact_ntsc_rates, "Exact NTSC frame rates")
+            .on_hover_text("Pass 29.97/23.976 etc. as 30000/1001, 24000/1001 to avoid A/V drift")
+            .changed()
+        {
+            self.update_command();
+        }
+        
+        ui.checkbox(&mut self.settings.write_report, "Write an encode report")
+            .on_hover_text("After each successful encode, save <output>.json with the command, settings, source details, file sizes and time taken");
+        
+        ui.horizontal(|ui| {
+            ui.label("Run after success:");
+            ui.text_edit_singleline(&mut self.settings.post_command)
+                .on_hover_text("Shell command run after each successful encode, e.g. upload.sh {output}. {input} and {output} are replaced with the quoted paths.");
+        });
+        let template = self.settings.post_command.trim();
+        if !template.is_empty()
+            && let Err(e) = ffmpeg_utils::expand_post_command(template, Path::new(""), Path::new(""), cfg!(windows))
+        {
+            ui.colored_label(egui::Color32::YELLOW, format!("Post-encode command: {}", e));
+        }
     }
-}
-
-impl MyApp {
+    
+    fn benchmark_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("Encodes the start of the input with each available encoder at its default preset, using the current quality settings.");
+        ui.horizontal(|ui| {
+            ui.label("Seconds:");
+            ui.add(egui::DragValue::new(&mut self.benchmark_seconds).clamp_range(1..=120));
+            let running = self.busy();
+            if ui.add_enabled(!running, egui::Button::new("Run benchmark")).clicked() {
+                self.run_benchmark(ui.ctx().clone());
+            }
+        });
+        
+        let results = self.benchmark_results.read();
+        if results.is_empty() {
+            return;
+        }
+        ui.separator();
+        egui::Grid::new("benchmark_results").num_columns(5).striped(true).show(ui, |ui| {
+            for heading in ["Encoder", "Preset", "Time", "Size", "PSNR"] {
+                ui.strong(heading);
+            }
+            ui.end_row();
+            for result in results.iter() {
+                ui.label(result.encoder.display_name());
+                ui.label(&result.preset);
+                ui.label(format!("{:.1} s", result.elapsed.as_secs_f32()));
+                match result.size {
+                    Some(size) => ui.label(format!("{:.2} MB", size as f64 / 1_000_000.0)),
+                    None => ui.weak("failed"),
+                };
+                match result.psnr {
+                    Some(psnr) => ui.label(format!("{:.2} dB", psnr)),
+                    None => ui.weak("-"),
+                };
+                ui.end_row();
+            }
+        });
+    }
+    
+    fn ab_ui(&mut self, ui: &mut egui::Ui) {
+        // Hardware encoders and Theora have no CRF, so their constant quantizer is compared instead
+        let crf = self.video_encoder.quality_modes().contains(&QualityMode::Crf);
+        let name = if crf { "CRF" } else { "QP" };
+        let range = if crf { self.video_encoder.crf_range() } else { self.video_encoder.qp_range() };
+        ui.label(format!("Encodes the same segment with {} at two {} values using the current preset.", self.video_encoder.display_name(), name));
+        ui.horizontal(|ui| {
+            ui.label(format!("{} A:", name));
+            ui.add(egui::DragValue::new(&mut self.ab_values[0]).clamp_range(range.clone()));
+            ui.label(format!("{} B:", name));
+            ui.add(egui::DragValue::new(&mut self.ab_values[1]).clamp_range(range));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Start:");
+            ui.add(egui::DragValue::new(&mut self.ab_start).clamp_range(0.0..=self.duration.unwrap_or(0.0).max(0.0)).suffix(" s"));
+            ui.label("Seconds:");
+            ui.add(egui::DragValue::new(&mut self.ab_seconds).clamp_range(1..=120));
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.ab_metrics, "Measure PSNR and SSIM")
+                .on_hover_text("Compare each encode with the source; takes about as long again");
+            let running = self.busy();
+            if ui.add_enabled(!running, egui::Button::new("Run comparison")).clicked() {
+                self.run_ab(ui.ctx().clone());
+            }
+        });
+        
+        let results = self.ab_results.read();
+        if results.is_empty() {
+            return;
+        }
+        ui.separator();
+        egui::Grid::new("ab_results").num_columns(5).striped(true).show(ui, |ui| {
+            for heading in [name, "Time", "Size", "PSNR", "SSIM"] {
+                ui.strong(heading);
+            }
+            ui.end_row();
+            for result in results.iter() {
+                ui.label(result.value.to_string());
+                ui.label(format!("{:.1} s", result.elapsed.as_secs_f32()));
+                match result.size {
+                    Some(size) => ui.label(format!("{:.2} MB", size as f64 / 1_000_000.0)),
+                    None => ui.weak("failed"),
+                };
+                match result.psnr {
+                    Some(psnr) => ui.label(format!("{:.2} dB", psnr)),
+                    None => ui.weak("-"),
+                };
+                match result.ssim {
+                    Some(ssim) => ui.label(format!("{:.4}", ssim)),
+                    None => ui.weak("-"),
+                };
+                ui.end_row();
+            }
+        });
+    }
+    
+    fn compare_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Time:");
+            let length = self.compare_length();
+            let response = ui.add(egui::Slider::new(&mut self.compare_time, 0.0..=length)
+                .suffix(" s")
+                .fixed_decimals(2));
+            // Re-extract once the user lets go rather than on every drag step
+            if response.drag_stopped() || (response.changed() && !response.dragged()) {
+                self.extract_compare_frames(ui.ctx().clone());
+            }
+            if *self.compare_busy.read() {
+                ui.spinner();
+            }
+        });
+        
+        match &self.compare_textures {
+            Some((before, after)) => {
+                ui.horizontal(|ui| {
+                    ui.vertical(|ui| {
+                        ui.label("Source");
+                        ui.image(before);
+                    });
+                    ui.vertical(|ui| {
+                        ui.label("Output");
+                        ui.image(after);
+                    });
+                });
+            },
+            None => {
+                ui.label("Extracting frames...");
+            }
+        }
+    }
+    
+    /// Length of the last encode's output, which the comparison time runs over
+    fn compare_length(&self) -> f64 {
+        let encode = self.last_encode.read();
+        let Some(encode) = encode.as_ref() else { return 0.0 };
+        encode.length
+            .or_else(|| self.duration.map(|duration| duration - encode.source_start))
+            .unwrap_or(0.0)
+            .max(0.0)
+    }
+    
+    /// Grab the frame at `compare_time` of the output, and the matching frame of the source, in the background
+    fn extract_compare_frames(&self, ctx: egui::Context) {
+        let Some(encode) = self.last_encode.read().clone() else { return };
+        if *self.compare_busy.read() {
+            return;
+        }
+        *self.compare_busy.write() = true;
+        
+        let time = self.compare_time;
+        let frames = self.compare_frames.clone();
+        let busy = self.compare_busy.clone();
+        let log = self.output_log.clone();
+        std::thread::spawn(move || {
+            // A trimmed output starts at the trim point, so the source frame is that far in
+            let before = ffmpeg_utils::extract_frame_rgba(&encode.input, encode.source_start + time, ffmpeg_utils::PREVIEW_SIZE);
+            let after = ffmpeg_utils::extract_frame_rgba(&encode.output, time, ffmpeg_utils::PREVIEW_SIZE);
+            match before.zip(after) {
+                Some(pair) => *frames.write() = Some(pair),
+                None => log.write().push_str(&format!("Could not extract comparison frames at {:.2}s\n", time)),
+            }
+            *busy.write() = false;
+            ctx.request_repaint();
+        });
+    }
+    
+    /// Write settings to disk whenever they differ from what was last saved
+    fn persist_settings(&mut self) {
+        if self.settings == self.saved_settings {
+            return;
+        }
+        if let Err(e) = self.settings.save() {
+            self.output_log.write().push_str(&format!("Warning: could not save settings: {}\n", e));
+        }
+        self.saved_settings = self.settings.clone();
+    }
+    
     fn stop_ffmpeg(&mut self) {
         // Log that we're stopping the process
         self.output_log.write().push_str("\nStopping FFmpeg process...\n");
         
+        // The file being written: the running batch item's in-progress file, or a single encode's output.
+        // A batch item's is kept under a name of its own, where Resume won't overwrite it.
+        let (partial, kept_path) = if *self.running.read() {
+            let job = self.batch_job.read();
+            let running_item = job.as_ref().and_then(|job| job.items.iter().find(|item| item.status == ItemStatus::Running));
+            match running_item {
+                Some(item) => (
+                    Some(item.temp_output()),
+                    Some(ffmpeg_utils::unique_path(ffmpeg_utils::with_stem_suffix(&item.output, "_partial"), self.settings.naming_scheme)),
+                ),
+                None => (self.partial_output.clone(), None),
+            }
+        } else {
+            (None, None)
+        };
+        let keep_partial = self.settings.keep_partial;
+        // Cleanup would delete a kept in-progress file
+        if keep_partial && let Some(path) = &partial {
+            self.temp_paths.retain(|temp| temp != path);
+        }
+        
         // First set running to false to prevent UI updates
         *self.running.write() = false;
+        self.encode_started = None;
         
-        // Create a separate thread to kill the process to avoid UI hanging
+        // A quick preview running alongside stops too; its own thread reaps it
+        if let Ok(mut guard) = self.preview_child.lock()
+            && let Some(preview) = guard.as_mut()
+        {
+            let _ = preview.kill();
+        }
+        
+        // Create a separate thread to kill the process to avoid UI hanging.
+        // Nothing new starts until it's done, so the kill and the delete can't reach another job.
+        *self.stopping.write() = true;
         let child_arc = self.child.clone();
+        let stopping_arc = self.stopping.clone();
         let log_arc = self.output_log.clone();
         let progress_arc = self.progress.clone();
         
         std::thread::spawn(move || {
-            // Try to kill the process
-            let killed = if let Ok(mut child_guard) = child_arc.lock() {
-                if let Some(child) = child_guard.as_mut() {
-                    // Try to kill the process
-                    match child.kill() {
-                        Ok(_) => {
-                            log_arc.write().push_str("Process terminated.\n");
-                            // Wait for the process to fully exit
-                            let _ = child.wait();
-                            true
-                        },
-                        Err(e) => {
-                            log_arc.write().push_str(&format!("Error killing process: {}\n", e));
-                            false
+            // Take the process, so the job thread sees it stopped rather than failed
+            let child = child_arc.lock().ok().and_then(|mut child_guard| child_guard.take());
+            
+            // When keeping the output, let FFmpeg finish it off so it plays up to the stop
+            let mut finished = false;
+            let killed = match child {
+                Some(mut child) => {
+                    finished = keep_partial && partial.is_some() && interrupt_child(&mut child, GRACEFUL_STOP_TIMEOUT);
+                    if finished {
+                        log_arc.write().push_str("Process stopped.\n");
+                        true
+                    } else {
+                        // Try to kill the process
+                        match child.kill() {
+                            Ok(_) => {
+                                log_arc.write().push_str("Process terminated.\n");
+                                // Wait for the process to fully exit
+                                let _ = child.wait();
+                                true
+                            },
+                            Err(e) => {
+                                log_arc.write().push_str(&format!("Error killing process: {}\n", e));
+                                false
+                            }
                         }
                     }
-                } else {
-                    false
-                }
-            } else {
-                false
+                },
+                None => false,
             };
             
-            // Clear the child process reference regardless of kill result
-            if let Ok(mut child_guard) = child_arc.lock() {
-                *child_guard = None;
-            }
-            
             // Set progress to 0 to reset the UI
             *progress_arc.write() = 0.0;
             
             if !killed {
                 log_arc.write().push_str("No active process to stop.\n");
             }
+            
+            if killed
+                && let Some(path) = partial.filter(|path| path.exists())
+            {
+                if keep_partial {
+                    let note = if finished { "" } else { " (cut off mid-write, it may not play everywhere)" };
+                    let kept = match kept_path {
+                        Some(kept) => match std::fs::rename(&path, &kept) {
+                            Ok(()) => kept,
+                            Err(e) => {
+                                log_arc.write().push_str(&format!("Error: Could not rename the partial output to {}: {}\n", kept.display(), e));
+                                path
+                            }
+                        },
+                        None => path,
+                    };
+                    log_arc.write().push_str(&format!("Kept the partial output {}{}\n", kept.display(), note));
+                } else {
+                    match std::fs::remove_file(&path) {
+                        Ok(()) => log_arc.write().push_str(&format!("Deleted the partial output {}\n", path.display())),
+                        Err(e) => log_arc.write().push_str(&format!("Error: Could not delete the partial output {}: {}\n", path.display(), e)),
+                    }
+                }
+            }
+            
+            *stopping_arc.write() = false;
         });
     }
 
-    // update_command is now in app_state.rs
+    /// Move a trim point `delta` frames, write it back in the current unit and show that frame.
+    /// An empty start counts as the first frame and an empty end as the last.
+    fn step_trim_point(&mut self, ctx: &egui::Context, point: TrimPoint, delta: i64) {
+        let fps = self.original_fps;
+        let last_frame = self.duration.map_or(u64::MAX, |duration| ((duration * fps as f64).round() as u64).saturating_sub(1));
+        let text = match point {
+            TrimPoint::Start => &mut self.trim_start,
+            TrimPoint::End => &mut self.trim_end,
+        };
+        let current = match ffmpeg_utils::parse_trim_point(text, self.trim_unit, fps) {
+            Some(seconds) => (seconds * fps as f64).round() as u64,
+            None if point == TrimPoint::Start => 0,
+            None => last_frame,
+        };
+        let frame = current.saturating_add_signed(delta).min(last_frame.max(current));
+        *text = match self.trim_unit {
+            TrimUnit::Frame => frame.to_string(),
+            TrimUnit::Timecode => ffmpeg_utils::format_timestamp(ffmpeg_utils::frame_to_seconds(frame, fps)),
+        };
+        
+        let preview = self.frame_preview.get_or_insert_with(|| FramePreview::new(point));
+        preview.point = point;
+        preview.request(ctx, Path::new(&self.input_path), frame, fps);
+    }
     
-    fn probe_duration(&mut self) {
-        if !Path::new(&self.input_path).exists() {
-            self.duration = 1.0;
-            self.original_fps = 30.0; // Default FPS
-            return;
+    /// Stop every running process, then delete the temp files jobs have registered
+    fn cleanup(&mut self) {
+        let preview_running = self.preview_child.lock().is_ok_and(|child| child.is_some());
+        if *self.running.read() || preview_running {
+            self.stop_ffmpeg();
         }
         
-        // Log that we're probing the file
-        self.output_log.write().push_str("Probing file information...\n");
-        
-        // First, get the duration
-        let duration_output = Command::new("ffprobe")
-            .args(["-v", "error", "-show_entries", "format=duration", "-of", "default=noprint_wrappers=1:nokey=1", &self.input_path])
-            .output();
-            
-        if let Ok(output) = duration_output {
-            if let Ok(duration_str) = String::from_utf8(output.stdout) {
-                if let Ok(duration) = duration_str.trim().parse::<f32>() {
-                    self.duration = duration.max(1.0); // Ensure duration is at least 1.0
-                    self.output_log.write().push_str(&format!("File duration: {:.2} seconds\n", self.duration));
+        let paths = std::mem::take(&mut self.temp_paths);
+        let child = self.child.clone();
+        let preview_child = self.preview_child.clone();
+        let stopping = self.stopping.clone();
+        let log = self.output_log.clone();
+        std::thread::spawn(move || {
+            // Windows won't delete a file a dying process still has open, so wait for them to exit,
+            // allowing for a stop that lets FFmpeg finish writing
+            let polls = 50 + GRACEFUL_STOP_TIMEOUT.as_millis() / 100;
+            for _ in 0..polls {
+                let exited = |slot: &Mutex<Option<Child>>| slot.lock().is_ok_and(|child| child.is_none());
+                if exited(&child) && exited(&preview_child) && !*stopping.read() {
+                    break;
                 }
+                std::thread::sleep(Duration::from_millis(100));
             }
-        } else {
-            // Default duration if probing fails
-            self.duration = 1.0;
-            self.output_log.write().push_str("Could not determine file duration, using default.\n");
-        }
-        
-        // Now, get the frame rate
-        let fps_output = Command::new("ffprobe")
-            .args([
-                "-v", "error", 
-                "-select_streams", "v:0", 
-                "-show_entries", "stream=r_frame_rate", 
-                "-of", "default=noprint_wrappers=1:nokey=1", 
-                &self.input_path
-            ])
-            .output();
             
-        if let Ok(output) = fps_output {
-            if let Ok(fps_str) = String::from_utf8(output.stdout) {
-                let fps_str = fps_str.trim();
-                if fps_str.contains('/') {
-                    // Handle fractional frame rates like "30000/1001"
-                    let parts: Vec<&str> = fps_str.split('/').collect();
-                    if parts.len() == 2 {
-                        if let (Ok(num), Ok(den)) = (parts[0].parse::<f32>(), parts[1].parse::<f32>()) {
-                            if den > 0.0 {
-                                let fps = num / den;
-                                self.original_fps = fps;
-                                self.frame_rate = fps.min(60.0); // Cap initial frame rate at 60 fps
-                                self.output_log.write().push_str(&format!("Original frame rate: {:.3} fps\n", fps));
-                                return;
-                            }
-                        }
-                    }
-                } else if let Ok(fps) = fps_str.parse::<f32>() {
-                    // Handle direct frame rate values
-                    self.original_fps = fps;
-                    self.frame_rate = fps.min(60.0); // Cap initial frame rate at 60 fps
-                    self.output_log.write().push_str(&format!("Original frame rate: {:.3} fps\n", fps));
-                    return;
+            let mut removed = 0;
+            for path in paths.iter().filter(|path| path.exists()) {
+                match std::fs::remove_file(path) {
+                    Ok(()) => removed += 1,
+                    Err(e) => log.write().push_str(&format!("Could not delete {}: {}\n", path.display(), e)),
                 }
             }
+            log.write().push_str(&format!("Cleanup: deleted {} temp file(s)\n", removed));
+        });
+    }
+    
+    // update_command is now in app_state.rs
+    
+    /// Probe the current input on a background thread, so slow or network files don't
+    /// stall the UI. The result is picked up by `apply_probe_results`.
+    fn start_probe(&mut self, ctx: egui::Context) {
+        self.probed_path = self.input_path.clone();
+        self.media_info = None;
+        self.cancel_frame_count();
+        self.counted_frames = None;
+        self.cancel_crop_detect();
+        self.audio_tags.clear();
+        self.sidecar_subtitles.clear();
+        self.probing = true;
+        
+        // Log that we're probing the file
+        self.output_log.write().push_str("Probing file information...\n");
+        
+        let path = self.input_path.clone();
+        let results = self.probe_results.clone();
+        std::thread::spawn(move || {
+            let info = probe::probe(Path::new(&path));
+            let sidecars = subtitles::find_sidecars(Path::new(&path));
+            results.write().push(ProbeOutcome { path, info, sidecars });
+            ctx.request_repaint();
+        });
+    }
+    
+    /// Count the input's frames exactly on a background thread, decoding the whole video
+    fn start_frame_count(&mut self, ctx: &egui::Context) {
+        self.cancel_frame_count();
+        self.output_log.write().push_str("Counting frames...\n");
+        
+        let job = FrameCountJob {
+            path: self.input_path.clone(),
+            progress: Arc::default(),
+            cancel: Arc::default(),
+            result: Arc::default(),
+        };
+        let path = job.path.clone();
+        let duration = self.duration;
+        let (progress, cancel, result) = (job.progress.clone(), job.cancel.clone(), job.result.clone());
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let counted = probe::count_frames(Path::new(&path), duration, &progress, &cancel);
+            *result.write() = Some(counted);
+            ctx.request_repaint();
+        });
+        self.frame_count_job = Some(job);
+    }
+    
+    fn cancel_frame_count(&mut self) {
+        if let Some(job) = self.frame_count_job.take() {
+            job.cancel.store(true, Ordering::Relaxed);
+        }
+    }
+    
+    /// Pick up a finished frame count of the current input
+    fn poll_frame_count(&mut self) {
+        let Some(result) = self.frame_count_job.as_ref().and_then(|job| job.result.write().take()) else {
+            return;
+        };
+        self.frame_count_job = None;
+        match result {
+            Ok(Some(frames)) => {
+                self.counted_frames = Some(frames);
+                self.output_log.write().push_str(&format!("Frames: {} (counted)\n", frames));
+            },
+            Ok(None) => self.output_log.write().push_str("Frame count cancelled.\n"),
+            Err(e) => self.output_log.write().push_str(&format!("Error: Could not count frames: {}\n", e)),
+        }
+    }
+    
+    /// Run cropdetect over the input on a background thread, tallying its suggestions
+    fn start_crop_detect(&mut self, ctx: &egui::Context) {
+        self.cancel_crop_detect();
+        self.crop_tally = CropTally::default();
+        self.output_log.write().push_str("Detecting black bars...\n");
+        
+        let job = CropDetectJob {
+            path: self.input_path.clone(),
+            progress: Arc::default(),
+            cancel: Arc::default(),
+            tally: Arc::default(),
+            result: Arc::default(),
+        };
+        let path = job.path.clone();
+        let duration = self.duration;
+        let (progress, cancel, tally, result) = (job.progress.clone(), job.cancel.clone(), job.tally.clone(), job.result.clone());
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let detected = crop::detect(Path::new(&path), duration, &tally, &progress, &cancel);
+            *result.write() = Some(detected);
+            ctx.request_repaint();
+        });
+        self.crop_job = Some(job);
+    }
+    
+    fn cancel_crop_detect(&mut self) {
+        if let Some(job) = self.crop_job.take() {
+            job.cancel.store(true, Ordering::Relaxed);
+        }
+    }
+    
+    /// Copy the suggestions in so far and pick up a finished detection of the current input
+    fn poll_crop_detect(&mut self) {
+        let Some(job) = &self.crop_job else {
+            return;
+        };
+        self.crop_tally = job.tally.read().clone();
+        let Some(result) = job.result.write().take() else {
+            return;
+        };
+        self.crop_job = None;
+        match result {
+            Ok(true) => match self.crop_tally.most_common() {
+                Some(crop) => self.output_log.write().push_str(&format!(
+                    "Detected {} different crop(s) over {} keyframes; most common: {}\n",
+                    self.crop_tally.counts.len(),
+                    self.crop_tally.total(),
+                    crop.label()
+                )),
+                None => self.output_log.write().push_str("cropdetect made no suggestions.\n"),
+            },
+            Ok(false) => self.output_log.write().push_str("Crop detection cancelled.\n"),
+            Err(e) => self.output_log.write().push_str(&format!("Error: Could not detect black bars: {}\n", e)),
+        }
+    }
+    
+    /// Apply a finished probe of the current input; probes of inputs since replaced are dropped
+    fn apply_probe_results(&mut self, ctx: &egui::Context) {
+        let finished: Vec<ProbeOutcome> = self.probe_results.write().drain(..).collect();
+        let Some(outcome) = finished.into_iter().rfind(|outcome| outcome.path == self.probed_path) else {
+            return;
+        };
+        self.probing = false;
+        
+        let info = match outcome.info {
+            Ok(info) => info,
+            Err(e) => {
+                self.output_log.write().push_str(&format!("Could not probe file: {}\n", e));
+                MediaInfo::default()
+            }
+        };
+        
+        // First, the duration
+        if info.looks_corrupt() {
+            self.output_log.write().push_str("Warning: Input appears to be corrupt or not a media file.\n");
+        }
+        
+        self.duration = info.duration.filter(|&duration| duration > 0.0);
+        match self.duration {
+            Some(duration) => self.output_log.write().push_str(&format!("File duration: {:.2} seconds\n", duration)),
+            None => self.output_log.write().push_str("Could not determine file duration; progress will show elapsed time instead of a percentage.\n"),
+        }
+        
+        if let Some((width, height)) = info.resolution() {
+            self.output_log.write().push_str(&format!("Resolution: {}x{}\n", width, height));
+        }
+        
+        // Offer subtitle files that share the input's name
+        self.sidecar_subtitles = outcome.sidecars;
+        if let Some(first) = self.sidecar_subtitles.first() {
+            let found: Vec<String> = self.sidecar_subtitles.iter().map(|s| s.label()).collect();
+            self.output_log.write().push_str(&format!("Found subtitles next to the input: {}\n", found.join(", ")));
+            self.subtitle_path = first.path.display().to_string();
+        }
+        
+        // Start the track tags from what the source already has
+        self.audio_tags = info.streams.iter()
+            .filter(|s| s.codec_type == "audio")
+            .enumerate()
+            .map(|(source, s)| {
+                let source_default = s.disposition.get("default") == Some(&1);
+                AudioTrackTags {
+                    language: s.tags.get("language").cloned().unwrap_or_default(),
+                    title: s.tags.get("title").cloned().unwrap_or_default(),
+                    source,
+                    default: source_default,
+                    source_default,
+                }
+            })
+            .collect();
+        self.extract_track = None;
+        self.default_subtitle = None;
+        self.frame_preview = None;
+        self.remux_excluded.clear();
+        self.split_points.clear();
+        self.split_points_file.clear();
+        // The region is in this file's pixels; start from the middle quarter of the frame
+        self.roi_enabled = false;
+        self.roi_rect = info.resolution().map_or([0, 0, 0, 0], |(w, h)| [w / 4, h / 4, w / 2, h / 2]);
+        self.crop = None;
+        self.crop_tally = CropTally::default();
+        
+        let rotation = info.rotation();
+        if rotation != 0 {
+            self.output_log.write().push_str(&format!(
+                "Warning: Source video is rotated {}° for display. Stream copies keep the rotation tag; check the output's orientation.\n",
+                rotation
+            ));
+        }
+        
+        if info.is_hdr10() {
+            self.output_log.write().push_str(
+                "Source is HDR10. Use H.265 (x265) with HDR10 passthrough to keep it HDR; other encoders will wash out the colours.\n"
+            );
+        }
+        
+        // Now, the frame rate
+        if let Some(fps) = info.fps() {
+            self.original_fps = fps;
+            self.frame_rate = fps.min(60.0); // Cap initial frame rate at 60 fps
+            self.output_log.write().push_str(&format!("Original frame rate: {:.3} fps\n", fps));
+        } else {
+            // Default frame rate if probing fails
+            self.original_fps = 30.0;
+            self.frame_rate = 30.0;
+            self.output_log.write().push_str("Could not determine original frame rate, using 30 fps.\n");
+        }
+        
+        if let Some(frames) = info.frame_count() {
+            self.output_log.write().push_str(&format!("Frames: {} (from the container)\n", frames));
+        }
+        let count_frames = self.settings.count_frames && info.has_video();
+        
+        // Video functions can't do anything with an audio file
+        if !info.looks_corrupt() && !info.has_video() && self.selected_function.needs_video_stream() {
+            self.selected_function = FunctionType::ExtractAudio;
+            self.output_path = self.default_output();
+            self.output_log.write().push_str("Input has no video stream, switched to ExtractAudio.\n");
+        }
+        if self.selected_function.needs_audio_stream() && info.has_video() && !info.has_audio() {
+            self.output_log.write().push_str("Warning: Input has no audio stream.\n");
+        }
+        
+        self.media_info = Some(info);
+        self.update_command();
+        
+        if count_frames {
+            self.start_frame_count(ctx);
         }
         
-        // Default frame rate if probing fails
-        self.original_fps = 30.0;
-        self.frame_rate = 30.0;
-        self.output_log.write().push_str("Could not determine original frame rate, using 30 fps.\n");
+        // Settings picked up from the new input (frame rate, function) aren't an undoable change
+        self.undo_baseline = None;
+    }
+    
+    /// Probe results for the current input, once they're in
+    fn probed_input_info(&self) -> Option<&MediaInfo> {
+        self.media_info.as_ref().filter(|_| self.probed_path == self.input_path)
     }
 }
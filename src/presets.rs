@@ -0,0 +1,197 @@
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::MyApp;
+use crate::enums::{AacEncoder, AudioContainer, AudioFormat, CfrMethod, ChannelLayout, ColorRange, FrameRateMode, GifDither, FunctionType, OutputFormat, QualityMode, Rotation, SizeLimit, VideoEncoder};
+use crate::ffmpeg_utils;
+use crate::probe::MediaInfo;
+
+/// The encode-related part of the UI state: what a preset saves and restores.
+/// Paths, probe results and per-file options are deliberately left out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EncodeSettings {
+    pub function: FunctionType,
+    pub output_format: OutputFormat,
+    pub audio_format: AudioFormat,
+    pub extra_audio_formats: Vec<AudioFormat>,
+    pub video_encoder: VideoEncoder,
+    pub quality_mode: QualityMode,
+    pub crf: u8,
+    pub qp: u8,
+    pub video_bitrate: u32,
+    pub strict_cbr: bool,
+    pub two_pass: bool,
+    pub size_limit: Option<SizeLimit>,
+    pub seek_friendly: bool,
+    pub extra_options: String,
+    pub filter_complex: String,
+    pub filter_maps: String,
+    pub cbr_buffer_secs: f32,
+    pub encoding_preset: String,
+    pub framerate_mode: FrameRateMode,
+    pub cfr_method: CfrMethod,
+    pub frame_rate: f32,
+    pub scale_height: Option<u32>,
+    pub rotation: Rotation,
+    pub smart_copy: bool,
+    pub hdr10_passthrough: bool,
+    pub color_range: ColorRange,
+    pub remove_audio: bool,
+    pub remove_video: bool,
+    pub audio_bitrate: u32,
+    pub audio_quality: u8,
+    pub use_audio_quality: bool,
+    pub aac_encoder: AacEncoder,
+    pub fdk_vbr: u8,
+    pub audio_cutoff: u32,
+    pub channel_layout: ChannelLayout,
+    pub silence_trim: bool,
+    pub silence_internal: bool,
+    pub silence_threshold_db: f32,
+    pub silence_min_secs: f32,
+    pub audio_container: AudioContainer,
+    pub gif_fps: f32,
+    pub gif_width: u32,
+    pub gif_palette: bool,
+    pub gif_max_colors: u32,
+    pub gif_dither: GifDither,
+}
+
+impl Default for EncodeSettings {
+    // Fields missing from an older saved preset take the app's defaults
+    fn default() -> Self {
+        MyApp::default().encode_settings()
+    }
+}
+
+impl EncodeSettings {
+    /// Names of the fields that differ from `other`, as used by the UI's change markers
+    pub fn changed_fields(&self, other: &EncodeSettings) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        if self.function != other.function { changed.push("function"); }
+        if self.output_format != other.output_format { changed.push("output_format"); }
+        if self.audio_format != other.audio_format { changed.push("audio_format"); }
+        if self.extra_audio_formats != other.extra_audio_formats { changed.push("extra_audio_formats"); }
+        if self.video_encoder != other.video_encoder { changed.push("video_encoder"); }
+        if self.quality_mode != other.quality_mode { changed.push("quality_mode"); }
+        if self.crf != other.crf { changed.push("crf"); }
+        if self.qp != other.qp { changed.push("qp"); }
+        if self.video_bitrate != other.video_bitrate { changed.push("video_bitrate"); }
+        if self.strict_cbr != other.strict_cbr || self.cbr_buffer_secs != other.cbr_buffer_secs { changed.push("strict_cbr"); }
+        if self.two_pass != other.two_pass { changed.push("two_pass"); }
+        if self.size_limit != other.size_limit { changed.push("size_limit"); }
+        if self.seek_friendly != other.seek_friendly { changed.push("seek_friendly"); }
+        if self.extra_options != other.extra_options { changed.push("extra_options"); }
+        if self.filter_complex != other.filter_complex || self.filter_maps != other.filter_maps { changed.push("filter_complex"); }
+        if self.encoding_preset != other.encoding_preset { changed.push("encoding_preset"); }
+        if self.framerate_mode != other.framerate_mode { changed.push("framerate_mode"); }
+        if self.cfr_method != other.cfr_method { changed.push("cfr_method"); }
+        if self.frame_rate != other.frame_rate { changed.push("frame_rate"); }
+        if self.scale_height != other.scale_height { changed.push("scale_height"); }
+        if self.rotation != other.rotation { changed.push("rotation"); }
+        if self.smart_copy != other.smart_copy { changed.push("smart_copy"); }
+        if self.hdr10_passthrough != other.hdr10_passthrough { changed.push("hdr10_passthrough"); }
+        if self.color_range != other.color_range { changed.push("color_range"); }
+        if self.remove_audio != other.remove_audio || self.remove_video != other.remove_video { changed.push("remove_streams"); }
+        if self.audio_bitrate != other.audio_bitrate { changed.push("audio_bitrate"); }
+        if self.audio_quality != other.audio_quality || self.use_audio_quality != other.use_audio_quality { changed.push("audio_quality"); }
+        if self.aac_encoder != other.aac_encoder || self.fdk_vbr != other.fdk_vbr { changed.push("aac_encoder"); }
+        if self.audio_cutoff != other.audio_cutoff { changed.push("audio_cutoff"); }
+        if self.channel_layout != other.channel_layout { changed.push("channel_layout"); }
+        if self.silence_trim != other.silence_trim
+            || self.silence_internal != other.silence_internal
+            || self.silence_threshold_db != other.silence_threshold_db
+            || self.silence_min_secs != other.silence_min_secs
+        {
+            changed.push("silence");
+        }
+        if self.audio_container != other.audio_container { changed.push("audio_container"); }
+        if self.gif_fps != other.gif_fps
+            || self.gif_width != other.gif_width
+            || self.gif_palette != other.gif_palette
+            || self.gif_max_colors != other.gif_max_colors
+            || self.gif_dither != other.gif_dither
+        {
+            changed.push("gif");
+        }
+        changed
+    }
+}
+
+/// Fields that Auto picks, as named by `changed_fields`
+pub const AUTO_FIELDS: [&str; 5] = ["quality_mode", "crf", "qp", "encoding_preset", "scale_height"];
+
+/// Quality mode, quality, preset and output size suited to the probed source, for users who'd
+/// rather not pick a CRF. Everything else is kept from `current`.
+pub fn recommend_settings(info: &MediaInfo, current: &EncodeSettings) -> EncodeSettings {
+    let mut settings = current.clone();
+    let Some((width, height)) = info.resolution() else {
+        return settings;
+    };
+    let fps = info.fps().unwrap_or(30.0);
+    let short_side = width.min(height);   // So portrait video is judged like landscape
+    
+    // In x264 CRF terms: bigger frames hide more loss per pixel
+    let mut crf: u8 = match short_side {
+        2160.. => 24,
+        1080.. => 23,
+        720.. => 22,
+        _ => 21,
+    };
+    // A starved source has little detail left, so matching its blockiness closely wastes bits
+    let bpp = info.video_bitrate_kbps().map(|kbps| ffmpeg_utils::bits_per_pixel(kbps, width, height, fps));
+    let starved = bpp.is_some_and(|bpp| bpp < ffmpeg_utils::LOW_BITS_PER_PIXEL);
+    if starved {
+        crf += 2;
+    }
+    
+    // Beyond 4K is more than anyone casually needs; a starved source above 1080p has no more detail than 1080p
+    settings.scale_height = match height {
+        h if h > 2160 && width >= height => Some(2160),
+        h if h > 1080 && width >= height && starved => Some(1080),
+        _ => None,
+    };
+    
+    let encoder = current.video_encoder;
+    settings.quality_mode = encoder.quality_modes()[0];
+    match encoder {
+        VideoEncoder::X264 => settings.crf = crf,
+        VideoEncoder::X265 => settings.crf = crf + 5,        // x265's scale runs about 5 higher for the same look
+        VideoEncoder::Vp8 => settings.crf = crf + 8,         // libvpx's 4-63 scale
+        VideoEncoder::Theora => settings.qp = if starved { 6 } else { 7 }, // 0-10, higher is better
+        _ => settings.qp = crf + 1,                          // Hardware quantizers lose a little at the same number
+    }
+    
+    // Keep software encodes of very high pixel rates (4K60 and up) from taking hours
+    let pixel_rate = width as f32 * height as f32 * fps;
+    settings.encoding_preset = match encoder {
+        VideoEncoder::X264 | VideoEncoder::X265 if pixel_rate > 3840.0 * 2160.0 * 30.0 => "fast".to_string(),
+        _ => encoder.default_preset().to_string(),
+    };
+    settings
+}
+
+/// Every field of two sets of settings as (name, value in `a`, value in `b`), read off their
+/// serialized form so new fields show up without being listed here
+pub fn field_values(a: &EncodeSettings, b: &EncodeSettings) -> Vec<(String, String, String)> {
+    let (Ok(serde_json::Value::Object(a)), Ok(serde_json::Value::Object(b))) = (serde_json::to_value(a), serde_json::to_value(b)) else {
+        return Vec::new();
+    };
+    let show = |value: Option<&serde_json::Value>| match value {
+        Some(serde_json::Value::String(text)) => text.clone(),
+        Some(value) => value.to_string(),
+        None => String::new(),
+    };
+    a.keys()
+        .map(|name| (name.clone(), show(a.get(name)), show(b.get(name))))
+        .collect()
+}
+
+/// A named set of encode settings the user saved
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Preset {
+    pub name: String,
+    pub settings: EncodeSettings,
+    #[serde(default)]
+    pub favorite: bool,     // Shown as a one-click button in the top bar
+}
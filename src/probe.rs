@@ -0,0 +1,367 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use parking_lot::RwLock;
+
+use anyhow::{Context, bail};
+
+/// One stream as reported by `ffprobe -show_streams`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct StreamInfo {
+    pub index: u32,
+    pub codec_type: String,         // "video", "audio", "subtitle", ...
+    pub codec_name: String,
+    pub width: u32,
+    pub height: u32,
+    pub r_frame_rate: String,       // Rational, e.g. "30000/1001"
+    pub bit_rate: Option<String>,
+    pub tags: HashMap<String, String>,
+    pub disposition: HashMap<String, u8>, // Flags such as "attached_pic": 1
+    pub side_data_list: Vec<SideData>,
+    pub pix_fmt: String,
+    pub color_transfer: String,     // "smpte2084" for HDR10 (PQ)
+    pub color_primaries: String,
+    pub color_range: String,        // "tv" (limited) or "pc" (full); often "unknown" or missing
+    pub channels: u32,
+    pub channel_layout: String,     // e.g. "stereo", "5.1(side)"; empty when unknown
+    pub nb_frames: Option<String>,  // Frame count from the container header; Matroska and others leave it out
+}
+
+impl StreamInfo {
+    /// Short description for stream lists, e.g. "#2 audio: aac, eng, 5.1(side)"
+    pub fn label(&self) -> String {
+        let mut details = vec![self.codec_name.clone()];
+        details.extend(self.tags.get("language").filter(|language| *language != "und").cloned());
+        if self.width > 0 && self.height > 0 {
+            details.push(format!("{}x{}", self.width, self.height));
+        }
+        if !self.channel_layout.is_empty() {
+            details.push(self.channel_layout.clone());
+        }
+        details.extend(self.tags.get("title").cloned());
+        format!("#{} {}: {}", self.index, self.codec_type, details.join(", "))
+    }
+}
+
+/// Per-stream (or per-frame) side data: the display matrix rotation and HDR10 metadata
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SideData {
+    pub side_data_type: String,
+    pub rotation: Option<f64>,      // Display matrix, counter-clockwise degrees
+    // Mastering display metadata, as rationals like "34000/50000"
+    pub red_x: Option<String>,
+    pub red_y: Option<String>,
+    pub green_x: Option<String>,
+    pub green_y: Option<String>,
+    pub blue_x: Option<String>,
+    pub blue_y: Option<String>,
+    pub white_point_x: Option<String>,
+    pub white_point_y: Option<String>,
+    pub min_luminance: Option<String>,
+    pub max_luminance: Option<String>,
+    // Content light level, in cd/m²
+    pub max_content: Option<u32>,
+    pub max_average: Option<u32>,
+}
+
+impl SideData {
+    fn is_mastering_display(&self) -> bool {
+        self.max_luminance.is_some()
+    }
+    
+    /// The mastering display in x265's `master-display` syntax: chromaticities in
+    /// units of 0.00002 and luminance in units of 0.0001 cd/m²
+    fn master_display(&self) -> Option<String> {
+        let chroma = |value: &Option<String>| value.as_deref().and_then(parse_ratio).map(|v| (v * 50000.0).round() as u32);
+        let luminance = |value: &Option<String>| value.as_deref().and_then(parse_ratio).map(|v| (v * 10000.0).round() as u32);
+        Some(format!(
+            "G({},{})B({},{})R({},{})WP({},{})L({},{})",
+            chroma(&self.green_x)?, chroma(&self.green_y)?,
+            chroma(&self.blue_x)?, chroma(&self.blue_y)?,
+            chroma(&self.red_x)?, chroma(&self.red_y)?,
+            chroma(&self.white_point_x)?, chroma(&self.white_point_y)?,
+            luminance(&self.max_luminance)?, luminance(&self.min_luminance)?,
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct FormatInfo {
+    duration: Option<String>,
+    tags: HashMap<String, String>,
+}
+
+/// One chapter as reported by `ffprobe -show_chapters`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct ChapterEntry {
+    start_time: String,
+    end_time: String,
+    tags: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct ProbeOutput {
+    streams: Vec<StreamInfo>,
+    format: FormatInfo,
+    chapters: Vec<ChapterEntry>,
+}
+
+/// A chapter of the input, in seconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chapter {
+    pub start: f64,
+    pub end: f64,
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct FrameInfo {
+    side_data_list: Vec<SideData>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct FramesOutput {
+    frames: Vec<FrameInfo>,
+}
+
+/// What ffprobe knows about an input file
+#[derive(Debug, Clone, Default)]
+pub struct MediaInfo {
+    pub duration: Option<f64>,      // Seconds, if the container reports one
+    pub creation_time: Option<String>, // Container creation_time tag, ISO 8601
+    pub streams: Vec<StreamInfo>,
+    pub chapters: Vec<Chapter>,
+}
+
+impl MediaInfo {
+    /// The first video stream, ignoring attached cover art
+    pub fn video_stream(&self) -> Option<&StreamInfo> {
+        self.streams.iter().find(|s| {
+            s.codec_type == "video" && s.r_frame_rate != "0/0" && s.disposition.get("attached_pic") != Some(&1)
+        })
+    }
+    
+    pub fn has_video(&self) -> bool {
+        self.video_stream().is_some()
+    }
+    
+    pub fn has_audio(&self) -> bool {
+        self.streams.iter().any(|s| s.codec_type == "audio")
+    }
+    
+    /// Layout of the first audio stream, for display; falls back to the channel count
+    pub fn audio_layout(&self) -> Option<String> {
+        let stream = self.streams.iter().find(|s| s.codec_type == "audio")?;
+        if !stream.channel_layout.is_empty() {
+            Some(stream.channel_layout.clone())
+        } else if stream.channels > 0 {
+            Some(format!("{} ch", stream.channels))
+        } else {
+            None
+        }
+    }
+
+    pub fn resolution(&self) -> Option<(u32, u32)> {
+        self.video_stream()
+            .filter(|s| s.width > 0 && s.height > 0)
+            .map(|s| (s.width, s.height))
+    }
+
+    pub fn fps(&self) -> Option<f32> {
+        self.video_stream().and_then(|s| parse_rational(&s.r_frame_rate))
+    }
+    
+    /// The video's colour range, "tv" or "pc", when the source declares one
+    pub fn color_range(&self) -> Option<&'static str> {
+        match self.video_stream()?.color_range.as_str() {
+            "tv" => Some("tv"),
+            "pc" => Some("pc"),
+            _ => None,
+        }
+    }
+    
+    /// The video's frame count as the container reports it, without decoding anything
+    pub fn frame_count(&self) -> Option<u64> {
+        self.video_stream()?.nb_frames.as_deref()?.parse().ok().filter(|&frames| frames > 0)
+    }
+
+    /// No usable duration and no audio or video streams: not a media file at all,
+    /// as opposed to a valid one whose length is unknown (e.g. a live capture)
+    pub fn looks_corrupt(&self) -> bool {
+        let has_duration = self.duration.is_some_and(|d| d > 0.0);
+        let has_media = self.streams.iter().any(|s| matches!(s.codec_type.as_str(), "video" | "audio"));
+        !has_duration && !has_media
+    }
+
+    /// Clockwise rotation players apply to the video when displaying it, 0 if upright.
+    /// Newer FFmpeg reports a display matrix; older versions a "rotate" tag.
+    pub fn rotation(&self) -> u32 {
+        let Some(stream) = self.video_stream() else { return 0 };
+        let degrees = stream.side_data_list.iter()
+            .find_map(|d| d.rotation)
+            .map(|ccw| -ccw)
+            .or_else(|| stream.tags.get("rotate").and_then(|r| r.parse::<f64>().ok()))
+            .unwrap_or(0.0);
+        (degrees.round() as i64).rem_euclid(360) as u32
+    }
+
+    /// Whether the video uses the PQ transfer of HDR10
+    pub fn is_hdr10(&self) -> bool {
+        self.video_stream().is_some_and(|s| s.color_transfer == "smpte2084")
+    }
+    
+    /// x265 parameters that carry the source's HDR10 signalling and static metadata over
+    /// to the encode, or None for SDR sources. Mastering display and content light level
+    /// are only included when the source has them.
+    pub fn hdr10_x265_params(&self) -> Option<String> {
+        let stream = self.video_stream().filter(|_| self.is_hdr10())?;
+        let mut params = vec![
+            "hdr10=1".to_string(),
+            "repeat-headers=1".to_string(),     // Metadata on every keyframe, so seeking players pick it up
+            "colorprim=bt2020".to_string(),
+            "transfer=smpte2084".to_string(),
+            "colormatrix=bt2020nc".to_string(),
+        ];
+        if let Some(display) = stream.side_data_list.iter().find(|d| d.is_mastering_display()).and_then(SideData::master_display) {
+            params.push(format!("master-display={}", display));
+        }
+        if let Some(light) = stream.side_data_list.iter().find(|d| d.max_content.is_some()) {
+            params.push(format!("max-cll={},{}", light.max_content.unwrap_or(0), light.max_average.unwrap_or(0)));
+        }
+        Some(params.join(":"))
+    }
+    
+    /// Video stream bitrate in kbit/s; often missing for MKV sources
+    pub fn video_bitrate_kbps(&self) -> Option<u32> {
+        self.video_stream()
+            .and_then(|s| s.bit_rate.as_deref())
+            .and_then(|b| b.parse::<u64>().ok())
+            .map(|b| (b / 1000) as u32)
+    }
+}
+
+/// Parse an ffprobe rational like "30000/1001" (or a plain number)
+pub fn parse_rational(value: &str) -> Option<f32> {
+    match value.split_once('/') {
+        Some((num, den)) => {
+            let (num, den) = (num.parse::<f32>().ok()?, den.parse::<f32>().ok()?);
+            (den > 0.0 && num > 0.0).then(|| num / den)
+        },
+        None => value.parse::<f32>().ok().filter(|v| *v > 0.0),
+    }
+}
+
+/// Like `parse_rational`, but zero is a valid value (e.g. a mastering display's minimum luminance)
+fn parse_ratio(value: &str) -> Option<f64> {
+    match value.split_once('/') {
+        Some((num, den)) => {
+            let (num, den) = (num.parse::<f64>().ok()?, den.parse::<f64>().ok()?);
+            (den > 0.0).then(|| num / den)
+        },
+        None => value.parse::<f64>().ok(),
+    }
+}
+
+/// Side data attached to the first video frame. MP4 and raw HEVC sources carry HDR10
+/// metadata in the bitstream, where ffprobe only reports it per frame.
+fn first_frame_side_data(path: &Path) -> Vec<SideData> {
+    let Ok(output) = Command::new("ffprobe")
+        .args(["-v", "error", "-select_streams", "v:0", "-read_intervals", "%+#1"])
+        .args(["-show_entries", "frame=side_data_list", "-print_format", "json"])
+        .arg(path)
+        .output()
+    else {
+        return Vec::new();
+    };
+    serde_json::from_slice::<FramesOutput>(&output.stdout)
+        .map(|parsed| parsed.frames.into_iter().flat_map(|frame| frame.side_data_list).collect())
+        .unwrap_or_default()
+}
+
+/// Run ffprobe on `path` and collect its format and stream information
+pub fn probe(path: &Path) -> anyhow::Result<MediaInfo> {
+    let output = Command::new("ffprobe")
+        .args(["-v", "error", "-print_format", "json", "-show_format", "-show_streams", "-show_chapters"])
+        .arg(path)
+        .output()
+        .context("could not run ffprobe")?;
+
+    if !output.status.success() {
+        bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    let parsed: ProbeOutput = serde_json::from_slice(&output.stdout).context("unexpected ffprobe output")?;
+    let mut info = MediaInfo {
+        duration: parsed.format.duration.and_then(|d| d.parse().ok()),
+        creation_time: parsed.format.tags.get("creation_time").cloned(),
+        streams: parsed.streams,
+        chapters: parsed.chapters.into_iter()
+            .filter_map(|chapter| {
+                let (start, end) = (chapter.start_time.parse::<f64>().ok()?, chapter.end_time.parse::<f64>().ok()?);
+                let title = chapter.tags.get("title").map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+                (end > start).then_some(Chapter { start, end, title })
+            })
+            .collect(),
+    };
+    
+    // HDR10 metadata missing at stream level may still be in the first frame
+    let needs_frame_data = info.is_hdr10()
+        && info.video_stream().is_some_and(|s| !s.side_data_list.iter().any(SideData::is_mastering_display));
+    if needs_frame_data {
+        let frame_data = first_frame_side_data(path);
+        let index = info.video_stream().map(|s| s.index);
+        if let Some(stream) = info.streams.iter_mut().find(|s| Some(s.index) == index) {
+            stream.side_data_list.extend(frame_data);
+        }
+    }
+    Ok(info)
+}
+
+/// Count the frames of the first video stream exactly by decoding all of it, which can
+/// take minutes on a long file. `progress` follows along from 0 to 1 when the duration
+/// is known. Setting `cancel` stops FFmpeg and gives Ok(None).
+pub fn count_frames(path: &Path, duration: Option<f64>, progress: &RwLock<f32>, cancel: &AtomicBool) -> anyhow::Result<Option<u64>> {
+    let mut child = Command::new("ffmpeg")
+        .args(["-nostdin", "-v", "error", "-i"])
+        .arg(path)
+        .args(["-map", "0:v:0", "-f", "null", "-", "-progress", "pipe:1", "-nostats"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())  // Unread, it could fill up and stall FFmpeg on a damaged file
+        .spawn()
+        .context("could not run ffmpeg")?;
+    
+    // -progress writes key=value lines, a block at a time
+    let mut frames = 0;
+    let stdout = child.stdout.take().context("no ffmpeg output")?;
+    for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+        if cancel.load(Ordering::Relaxed) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(None);
+        }
+        if let Some(value) = line.strip_prefix("frame=") {
+            frames = value.trim().parse().unwrap_or(frames);
+        } else if let Some(value) = line.strip_prefix("out_time_us=")
+            && let (Ok(micros), Some(duration)) = (value.trim().parse::<f64>(), duration)
+        {
+            *progress.write() = (micros / 1e6 / duration).clamp(0.0, 1.0) as f32;
+        }
+    }
+    
+    let status = child.wait()?;
+    if !status.success() {
+        bail!("FFmpeg could not decode the video ({})", status);
+    }
+    Ok(Some(frames))
+}
@@ -0,0 +1,70 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::presets::EncodeSettings;
+use crate::probe::MediaInfo;
+
+/// What ffprobe said about the source, as recorded in a report
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceSummary {
+    pub duration: Option<f64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fps: Option<f32>,
+    pub video_codec: Option<String>,
+    pub audio_codecs: Vec<String>,
+    pub hdr10: bool,
+}
+
+impl SourceSummary {
+    pub fn from_info(info: &MediaInfo) -> Self {
+        let resolution = info.resolution();
+        Self {
+            duration: info.duration,
+            width: resolution.map(|(w, _)| w),
+            height: resolution.map(|(_, h)| h),
+            fps: info.fps(),
+            video_codec: info.video_stream().map(|s| s.codec_name.clone()),
+            audio_codecs: info.streams.iter()
+                .filter(|s| s.codec_type == "audio")
+                .map(|s| s.codec_name.clone())
+                .collect(),
+            hdr10: info.is_hdr10(),
+        }
+    }
+}
+
+/// Machine-readable record of how an output was produced, written next to it as `<output>.json`
+#[derive(Debug, Clone, Serialize)]
+pub struct EncodeReport {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub command: Vec<String>,       // FFmpeg arguments, without the program name
+    pub input_size: Option<u64>,
+    pub output_size: Option<u64>,
+    pub duration_secs: Option<f64>, // Length of the encoded output, when known
+    pub elapsed_secs: f64,          // Wall-clock time the encode took
+    pub settings: EncodeSettings,
+    pub source: Option<SourceSummary>,
+}
+
+impl EncodeReport {
+    /// Fill in the sizes and timing once the encode has finished, then write the report
+    pub fn finish(mut self, elapsed: Duration) -> anyhow::Result<PathBuf> {
+        self.input_size = std::fs::metadata(&self.input).ok().map(|m| m.len());
+        self.output_size = std::fs::metadata(&self.output).ok().map(|m| m.len());
+        self.elapsed_secs = elapsed.as_secs_f64();
+        
+        let path = report_path(&self.output);
+        std::fs::write(&path, serde_json::to_string_pretty(&self)?)?;
+        Ok(path)
+    }
+}
+
+/// `clip.mp4` -> `clip.mp4.json`
+pub fn report_path(output: &Path) -> PathBuf {
+    let mut name = output.as_os_str().to_owned();
+    name.push(".json");
+    PathBuf::from(name)
+}
@@ -0,0 +1,38 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use crate::two_pass::TwoPassPlan;
+
+/// The commands for encoding an output's audio and video on their own and then muxing
+/// them together, resolved from the UI when the job is built
+#[derive(Debug, Clone)]
+pub struct SeparatePlan {
+    pub audio_args: Vec<String>,        // Writes to the .part file of `audio_path`
+    pub audio_path: PathBuf,            // Kept for the session, so a video-only change reuses it
+    pub video_args: Vec<String>,
+    pub video_passes: Option<TwoPassPlan>, // `video_args` split in two, when two-pass is on
+    pub video_path: PathBuf,
+    pub mux_args: Vec<String>,
+}
+
+/// Intermediate audio for `input` encoded with `audio_args` (without the output). The
+/// name depends on both, and on the input's size and modification time, so any change
+/// that would give different audio misses it.
+pub fn audio_path(input: &Path, audio_args: &[String]) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    audio_args.hash(&mut hasher);
+    if let Ok(meta) = std::fs::metadata(input) {
+        meta.len().hash(&mut hasher);
+        meta.modified().ok().hash(&mut hasher);
+    }
+    let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+    // Matroska audio takes any codec
+    std::env::temp_dir().join(format!("{}.audio-{:016x}.mka", stem, hasher.finish()))
+}
+
+/// Intermediate video-only file for `output`, in the output's container
+pub fn video_path(output: &Path) -> PathBuf {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = output.extension().unwrap_or_default().to_string_lossy();
+    std::env::temp_dir().join(format!("{}.video.{}", stem, ext))
+}
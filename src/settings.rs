@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+
+use crate::enums::{NamingScheme, UiMode};
+use crate::ffmpeg_utils;
+use crate::presets::Preset;
+
+const CONFIG_FILE: &str = "config.json";
+const BACKUP_FILE: &str = "config.bak";
+
+/// User preferences that persist between sessions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub naming_scheme: NamingScheme,    // Suffix style used when an output name is taken
+    pub ui_mode: UiMode,                // Simple hides the less common options
+    pub force_format: String,           // Muxer passed as -f, empty to infer from the extension
+    pub global_options: String,         // Extra options placed before the first -i, e.g. -hwaccel auto
+    pub exact_ntsc_rates: bool,         // Emit 30000/1001 instead of 29.970 for NTSC frame rates
+    pub smooth_progress: bool,          // Ease the progress bar towards each new time= sample
+    pub background_priority: bool,      // Run encodes at lowered CPU priority
+    pub global_hotkeys: bool,           // Register system-wide Start/Stop hotkeys
+    pub start_hotkey: String,           // e.g. "Ctrl+Alt+S"
+    pub stop_hotkey: String,
+    pub presets: Vec<Preset>,           // Named encode settings saved by the user
+    pub post_command: String,           // Shell command run after a successful encode, empty for none
+    pub write_report: bool,             // Write <output>.json describing each finished encode
+    pub stall_timeout_secs: u32,        // Warn when FFmpeg reports no progress for this long, 0 for never
+    pub kill_stalled: bool,             // Also stop a stalled FFmpeg, so a batch can move on
+    pub wrap_command: bool,             // Show the command preview one option per line
+    pub auto_video: bool,               // Compress Video: pick quality, preset and size from the probed input
+    pub ui_scale: f32,                  // Zoom of the whole window on top of the display's own scaling
+    pub log_font_size: f32,             // Points, for the output log's monospace text
+    pub count_frames: bool,             // Decode each new input to count its frames exactly (slow)
+    pub keep_partial: bool,             // On Stop, let FFmpeg close the output written so far rather than delete it
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            naming_scheme: NamingScheme::default(),
+            ui_mode: UiMode::default(),
+            force_format: String::new(),
+            global_options: String::new(),
+            exact_ntsc_rates: true,
+            smooth_progress: true,
+            background_priority: false,
+            global_hotkeys: false,
+            start_hotkey: "Ctrl+Alt+S".to_string(),
+            stop_hotkey: "Ctrl+Alt+X".to_string(),
+            presets: Vec::new(),
+            post_command: String::new(),
+            write_report: false,
+            stall_timeout_secs: 0,
+            kill_stalled: false,
+            wrap_command: false,
+            auto_video: false,
+            ui_scale: 1.0,
+            log_font_size: 12.0,
+            count_frames: false,
+            keep_partial: true,
+        }
+    }
+}
+
+impl Settings {
+    /// Load saved settings, falling back to defaults if there are none. A config that
+    /// can't be read back (e.g. cut short by a crash) is moved aside to config.bak and
+    /// the error returned, so the caller can start with defaults and tell the user.
+    pub fn load() -> anyhow::Result<Settings> {
+        let path = ffmpeg_utils::app_file(CONFIG_FILE);
+        let Ok(data) = std::fs::read_to_string(&path) else {
+            return Ok(Settings::default());
+        };
+        
+        serde_json::from_str(&data).or_else(|e| {
+            let backup = ffmpeg_utils::app_file(BACKUP_FILE);
+            std::fs::rename(&path, &backup)?;
+            Err(anyhow::anyhow!("{} is corrupt ({}), moved it to {}", CONFIG_FILE, e, backup.display()))
+        })
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        ffmpeg_utils::write_atomic(&ffmpeg_utils::app_file(CONFIG_FILE), &serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
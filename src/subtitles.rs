@@ -0,0 +1,100 @@
+use std::path::{Path, PathBuf};
+
+const SUBTITLE_EXTENSIONS: [&str; 4] = ["srt", "ass", "ssa", "vtt"];
+
+/// Character encodings offered for burning in subtitle files, as (FFmpeg/iconv name, label)
+pub const CHARSETS: [(&str, &str); 10] = [
+    ("UTF-8", "UTF-8"),
+    ("CP1250", "Windows-1250 (Central European)"),
+    ("CP1251", "Windows-1251 (Cyrillic)"),
+    ("CP1252", "Windows-1252 (Western)"),
+    ("CP1253", "Windows-1253 (Greek)"),
+    ("CP1254", "Windows-1254 (Turkish)"),
+    ("CP1256", "Windows-1256 (Arabic)"),
+    ("GB18030", "GB18030 (Simplified Chinese)"),
+    ("BIG5", "Big5 (Traditional Chinese)"),
+    ("SHIFT_JIS", "Shift JIS (Japanese)"),
+];
+
+pub fn charset_label(charset: &str) -> &str {
+    CHARSETS.iter().find(|(name, _)| *name == charset).map_or(charset, |(_, label)| label)
+}
+
+/// A subtitle file found next to the input, e.g. `movie.srt` or `movie.en.srt`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sidecar {
+    pub path: PathBuf,
+    pub language: Option<String>,   // From a `.en`/`.eng` style suffix on the stem
+}
+
+impl Sidecar {
+    pub fn label(&self) -> String {
+        let name = self.path.file_name().unwrap_or_default().to_string_lossy();
+        match &self.language {
+            Some(language) => format!("{} ({})", name, language),
+            None => name.to_string(),
+        }
+    }
+}
+
+/// Subtitle files in the input's directory that share its stem, optionally followed by a language code
+pub fn find_sidecars(input: &Path) -> Vec<Sidecar> {
+    let (Some(dir), Some(stem)) = (input.parent(), input.file_stem().and_then(|s| s.to_str())) else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut sidecars: Vec<Sidecar> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter_map(|path| {
+            let ext = path.extension()?.to_str()?.to_lowercase();
+            if !SUBTITLE_EXTENSIONS.contains(&ext.as_str()) {
+                return None;
+            }
+            let sub_stem = path.file_stem()?.to_str()?;
+            let language = if sub_stem == stem {
+                None
+            } else {
+                // movie.en.srt: the remainder after "movie." must look like a language code
+                let suffix = sub_stem.strip_prefix(stem)?.strip_prefix('.')?;
+                let is_code = (2..=3).contains(&suffix.len()) && suffix.chars().all(|c| c.is_ascii_alphabetic());
+                if !is_code {
+                    return None;
+                }
+                Some(suffix.to_lowercase())
+            };
+            Some(Sidecar { path, language })
+        })
+        .collect();
+    sidecars.sort_by(|a, b| a.path.cmp(&b.path));
+    sidecars
+}
+
+/// Subtitle codec for muxing a text subtitle file into the given container
+pub fn mux_codec(path: &Path, container_ext: &str) -> &'static str {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_lowercase();
+    match (container_ext, ext.as_str()) {
+        ("mp4", _) => "mov_text",           // The only text subtitle format MP4 players understand
+        ("webm", _) => "webvtt",            // Likewise for WebM
+        (_, "vtt") => "srt",
+        _ => "copy",
+    }
+}
+
+/// Quote a path for use inside a filter option such as `subtitles=`
+pub fn filter_path(path: &Path) -> String {
+    let path = path.display().to_string().replace('\\', "/");
+    format!("'{}'", path.replace('\'', "'\\''").replace(':', "\\:"))
+}
+
+/// The `subtitles` filter burning in `path`, decoding it from `charset` unless it's already UTF-8
+pub fn burn_filter(path: &Path, charset: &str) -> String {
+    let mut filter = format!("subtitles={}", filter_path(path));
+    if !charset.eq_ignore_ascii_case("UTF-8") {
+        filter.push_str(&format!(":charenc={}", charset));
+    }
+    filter
+}
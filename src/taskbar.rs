@@ -0,0 +1,126 @@
+use eframe::egui;
+
+pub const APP_TITLE: &str = "FFmpeg GUI";
+
+/// Shows encode progress outside the window, for when the app is minimized or behind
+/// other windows: on the taskbar button on Windows and as a badge on the dock icon on
+/// macOS. Elsewhere, or if those can't be reached, the percentage goes in the window
+/// title instead, which window lists and Alt+Tab display.
+#[derive(Default)]
+pub struct TaskbarProgress {
+    shown: Option<u32>,             // Percentage currently shown, None when idle
+    native: Option<native::Native>, // Connected on the first change, once the window exists
+    connect_tried: bool,
+}
+
+impl TaskbarProgress {
+    /// Show `progress` (0-1) while a job runs, or nothing for None
+    pub fn update(&mut self, ctx: &egui::Context, frame: &eframe::Frame, progress: Option<f32>) {
+        let percent = progress.map(|p| (p.clamp(0.0, 1.0) * 100.0).floor() as u32);
+        // Only call out when the value changes; every frame would spam the window system
+        if percent == self.shown {
+            return;
+        }
+        self.shown = percent;
+        if !self.connect_tried {
+            self.connect_tried = true;
+            self.native = native::Native::connect(frame);
+        }
+        
+        if let Some(native) = &self.native {
+            native.show(percent);
+            return;
+        }
+        let title = match percent {
+            Some(percent) => format!("{}% - {}", percent, APP_TITLE),
+            None => APP_TITLE.to_string(),
+        };
+        ctx.send_viewport_cmd(egui::ViewportCommand::Title(title));
+    }
+}
+
+#[cfg(windows)]
+mod native {
+    use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::System::Com::{CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED, CoCreateInstance, CoInitializeEx};
+    use windows::Win32::UI::Shell::{ITaskbarList3, TBPF_NOPROGRESS, TaskbarList};
+    
+    /// The window's taskbar button, through ITaskbarList3
+    pub struct Native {
+        taskbar: ITaskbarList3,
+        hwnd: HWND,
+    }
+    
+    impl Native {
+        pub fn connect(frame: &eframe::Frame) -> Option<Self> {
+            let RawWindowHandle::Win32(handle) = frame.window_handle().ok()?.as_raw() else {
+                return None;
+            };
+            let hwnd = HWND(handle.hwnd.get());
+            // SAFETY: called on the UI thread. COM is usually initialised there already,
+            // in which case the second initialisation is refused harmlessly.
+            unsafe {
+                let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+                let taskbar: ITaskbarList3 = CoCreateInstance(&TaskbarList, None, CLSCTX_INPROC_SERVER).ok()?;
+                taskbar.HrInit().ok()?;
+                Some(Native { taskbar, hwnd })
+            }
+        }
+        
+        pub fn show(&self, percent: Option<u32>) {
+            // SAFETY: the window lives as long as the app holding this; setting a value
+            // switches the button to the normal progress state
+            let _ = unsafe {
+                match percent {
+                    Some(percent) => self.taskbar.SetProgressValue(self.hwnd, u64::from(percent), 100),
+                    None => self.taskbar.SetProgressState(self.hwnd, TBPF_NOPROGRESS),
+                }
+            };
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod native {
+    use objc2::MainThreadMarker;
+    use objc2::rc::Retained;
+    use objc2_app_kit::{NSApplication, NSDockTile};
+    use objc2_foundation::NSString;
+    
+    /// The app's dock icon, badged through NSDockTile
+    pub struct Native {
+        tile: Retained<NSDockTile>,
+    }
+    
+    impl Native {
+        pub fn connect(_frame: &eframe::Frame) -> Option<Self> {
+            let mtm = MainThreadMarker::new()?;
+            // SAFETY: on the main thread, which the marker proves
+            let tile = unsafe { NSApplication::sharedApplication(mtm).dockTile() };
+            Some(Native { tile })
+        }
+        
+        pub fn show(&self, percent: Option<u32>) {
+            let label = percent.map(|percent| NSString::from_str(&format!("{}%", percent)));
+            // SAFETY: the tile is only used from the UI thread, which is the main thread
+            unsafe { self.tile.setBadgeLabel(label.as_deref()) };
+        }
+    }
+}
+
+/// No taskbar progress to set here, so the title shows it
+#[cfg(not(any(windows, target_os = "macos")))]
+mod native {
+    pub enum Native {}
+    
+    impl Native {
+        pub fn connect(_frame: &eframe::Frame) -> Option<Self> {
+            None
+        }
+        
+        pub fn show(&self, _percent: Option<u32>) {
+            match *self {}
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use std::path::{Path, PathBuf};
+
+/// Share of a size limit left unused, for container overhead and the encoder missing its target
+pub const SIZE_MARGIN: f64 = 0.05;
+
+/// Files an encoder may write under a pass log prefix; x264 adds its macroblock-tree stats,
+/// and both are written under a .temp name first
+const LOG_SUFFIXES: [&str; 4] = ["-0.log", "-0.log.temp", "-0.log.mbtree", "-0.log.mbtree.temp"];
+
+/// The analysis and encode commands of a two-pass encode, resolved from the UI when the job is built
+#[derive(Debug, Clone)]
+pub struct TwoPassPlan {
+    pub first_args: Vec<String>,        // Video only, to the null muxer; writes nothing but the log
+    pub second_args: Vec<String>,
+    pub log_prefix: PathBuf,
+}
+
+impl TwoPassPlan {
+    /// Split a complete single-pass command, which ends in `-y <output>`, into its two passes
+    pub fn new(args: &[String], log_prefix: PathBuf) -> Self {
+        let (options, output) = args.split_at(args.len().saturating_sub(2));
+        let pass = |number: &str| [
+            "-pass".to_string(), number.to_string(),
+            "-passlogfile".to_string(), log_prefix.display().to_string(),
+        ];
+        
+        // Later options win, so these override the output's own muxer and stream choices
+        let mut first_args = options.to_vec();
+        first_args.extend(pass("1"));
+        first_args.extend(["-an", "-sn", "-dn", "-f", "null", "-y", "-"].map(String::from));
+        
+        let mut second_args = options.to_vec();
+        second_args.extend(pass("2"));
+        second_args.extend_from_slice(output);
+        
+        TwoPassPlan { first_args, second_args, log_prefix }
+    }
+    
+    pub fn log_files(&self) -> Vec<PathBuf> {
+        LOG_SUFFIXES
+            .iter()
+            .map(|suffix| {
+                let mut name = self.log_prefix.clone().into_os_string();
+                name.push(suffix);
+                PathBuf::from(name)
+            })
+            .collect()
+    }
+}
+
+/// Pass log prefix for a two-pass encode of `output`, in the temp folder
+pub fn log_prefix(output: &Path) -> PathBuf {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    std::env::temp_dir().join(format!("{}.passlog", stem))
+}
+
+/// Video bitrate in kbps that fits `duration` seconds, plus `audio_kbps` of audio, under
+/// `megabytes` (of 1,000,000 bytes) with `SIZE_MARGIN` to spare. None if the audio alone fills it.
+pub fn fit_bitrate(megabytes: f64, duration: f64, audio_kbps: u32) -> Option<u32> {
+    if duration <= 0.0 {
+        return None;
+    }
+    let total_kbps = megabytes * 8000.0 * (1.0 - SIZE_MARGIN) / duration;
+    let video_kbps = total_kbps - f64::from(audio_kbps);
+    (video_kbps >= 1.0).then(|| video_kbps.floor() as u32)
+}